@@ -0,0 +1,83 @@
+use base58::FromBase58;
+use serde_json::Value;
+use wevm::DataEntry;
+
+/// Parsing function arguments from a JSON array.
+///
+/// Supported values:
+/// * Integers are passed as `Integer`
+/// * `true` and `false` are passed as `Boolean`
+/// * Strings are passed as `String`
+/// * Objects of the form `{"binary": "<base58>"}` are passed as `Binary`
+pub fn parse(input: &str) -> Result<Vec<DataEntry>, String> {
+    let value: Value = serde_json::from_str(input).map_err(|error| error.to_string())?;
+
+    match value {
+        Value::Array(values) => values.iter().map(parse_value).collect(),
+        _ => Err(String::from("Arguments must be a JSON array")),
+    }
+}
+
+/// Serializing arguments in the format expected by the `Vm`.
+pub fn serialize(args: &[DataEntry]) -> Vec<u8> {
+    let mut result = (args.len() as u16).to_be_bytes().to_vec();
+
+    for (index, arg) in args.iter().enumerate() {
+        let key = format!("arg{}", index);
+        result.extend(arg.serialize(Some(key.as_bytes())));
+    }
+
+    result
+}
+
+fn parse_value(value: &Value) -> Result<DataEntry, String> {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(DataEntry::Integer)
+            .ok_or_else(|| format!("Unsupported number: {}", number)),
+        Value::Bool(value) => Ok(DataEntry::Boolean(*value as i32)),
+        Value::String(value) => Ok(DataEntry::String(value.clone().into_bytes())),
+        Value::Object(object) => match (object.get("binary"), object.len()) {
+            (Some(Value::String(value)), 1) => value
+                .from_base58()
+                .map(DataEntry::Binary)
+                .map_err(|_| format!("Invalid base58 string: {}", value)),
+            _ => Err(format!("Expected {{\"binary\": \"<base58>\"}}: {}", value)),
+        },
+        _ => Err(format!("Unsupported value: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let result = parse(r#"[42, -1, true, false, "a\"b", {"binary": "2g"}]"#)
+            .expect("Failed to parse arguments");
+
+        assert_eq!(
+            result,
+            vec![
+                DataEntry::Integer(42),
+                DataEntry::Integer(-1),
+                DataEntry::Boolean(1),
+                DataEntry::Boolean(0),
+                DataEntry::String(b"a\"b".to_vec()),
+                DataEntry::Binary(vec![97]),
+            ]
+        );
+
+        assert_eq!(parse(" [ ] "), Ok(vec![]));
+        assert!(parse("[1,]").is_err());
+        assert!(parse("[1] 2").is_err());
+        assert!(parse("[1.5]").is_err());
+        assert!(parse(r#"[{"binary": "2g", "key": 1}]"#).is_err());
+        assert_eq!(
+            parse(r#"["\u00e9\ud83d\ude00"]"#),
+            Ok(vec![DataEntry::String("é😀".as_bytes().to_vec())])
+        );
+    }
+}
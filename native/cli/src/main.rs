@@ -0,0 +1,323 @@
+mod args;
+
+use base58::{FromBase58, ToBase58};
+use clap::Parser;
+use std::{fs, path::PathBuf, process::ExitCode};
+use wevm::{
+    coverage::Coverage,
+    mock::{Event, Mock},
+    modules,
+    node::Node,
+    profile::Profile,
+    trace::{Recorder, Replayer, Trace},
+    vm::{ExecutionMode, Vm},
+    DataEntry, ExecutionLimits,
+};
+
+/// Local execution of WASM smart-contracts against an in-memory node.
+#[derive(Parser)]
+#[command(name = "wevm", version, about)]
+struct Cli {
+    /// Path to the contract in `.wasm` or `.wat` format
+    path: PathBuf,
+    /// Name of the function to call
+    #[arg(required_unless_present = "inspect")]
+    function: Option<String>,
+    /// Function arguments as a JSON array, binary values are passed as `{"binary": "<base58>"}`
+    #[arg(short, long, default_value = "[]")]
+    args: String,
+    /// Maximum amount of fuel available to the contract
+    #[arg(short, long, default_value_t = 1_000_000)]
+    fuel_limit: u64,
+    /// Identifier of the contract in base58
+    #[arg(short, long)]
+    contract_id: Option<String>,
+    /// Print the code coverage of the executed contracts
+    #[arg(long)]
+    coverage: bool,
+    /// Print the fuel consumed by the called functions of the contracts and by the host functions
+    #[arg(long)]
+    profile: bool,
+    /// Save the time of the contract calls to the file in the collapsed stack format
+    #[arg(long)]
+    stacks: Option<PathBuf>,
+    /// Save the interactions with the node to the file
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Serve the responses of the node from a file saved by `--record`
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    /// Print the imports, exports, memory and the disassembly of the contract instead of calling it
+    #[arg(long)]
+    inspect: bool,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode, String> {
+    // The text format is converted by the virtual machine
+    let bytecode =
+        fs::read(&cli.path).map_err(|error| format!("{}: {}", cli.path.display(), error))?;
+
+    if cli.inspect {
+        inspect(&bytecode)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let contract_id = match cli.contract_id {
+        Some(contract_id) => contract_id
+            .from_base58()
+            .map_err(|_| "Invalid base58 contract id".to_string())?,
+        None => vec![0; 32],
+    };
+
+    let params = args::serialize(&args::parse(&cli.args)?);
+
+    let mock = Mock::new();
+    mock.set_bytecode(&contract_id, bytecode.clone());
+
+    let trace = Trace::new();
+    let node: Box<dyn Node> = match &cli.replay {
+        Some(path) => {
+            let bytes = fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+            let trace = Trace::from_bytes(&bytes).map_err(|error| error.to_string())?;
+            Box::new(Replayer::new(&trace))
+        }
+        None if cli.record.is_some() => {
+            Box::new(Recorder::new(Box::new(mock.clone()), trace.clone()))
+        }
+        None => Box::new(mock.clone()),
+    };
+
+    let mut vm = Vm::new(
+        contract_id,
+        bytecode,
+        ExecutionLimits {
+            fuel: cli.fuel_limit,
+            ..Default::default()
+        },
+        modules::all(),
+        node,
+        ExecutionMode::Apply,
+    )
+    .map_err(|error| error.to_string())?;
+
+    if cli.coverage {
+        vm.set_coverage(Some(Coverage::new()));
+    }
+
+    if cli.profile || cli.stacks.is_some() {
+        vm.set_profile(Some(Profile::new()));
+    }
+
+    let result = vm.run(cli.function.as_deref().unwrap_or_default(), &params);
+
+    let code = match result {
+        Ok(values) => {
+            let values: Vec<String> = values.iter().map(format_value).collect();
+            println!("Result: [{}]", values.join(", "));
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            println!("Error: {} (code {})", error, error.as_i32());
+            ExitCode::FAILURE
+        }
+    };
+
+    println!("Fuel used: {}", vm.fuel_consumed());
+    println!("Peak memory: {} pages", vm.peak_memory());
+
+    let error_message = mock.error_message();
+    if !error_message.is_empty() {
+        println!("Require: {}", String::from_utf8_lossy(&error_message));
+    }
+
+    println!("Events:");
+    for event in mock.events() {
+        println!("  {}", format_event(&event));
+    }
+
+    println!("Write set:");
+    for write in mock.write_set() {
+        println!(
+            "  {} {} = {}",
+            write.contract_id.to_base58(),
+            String::from_utf8_lossy(&write.key),
+            format_data_entry(&write.value)
+        );
+    }
+
+    if let Some(coverage) = vm.coverage() {
+        println!("Coverage:");
+        print!("{}", coverage);
+    }
+
+    if let Some(profile) = vm.profile().filter(|_| cli.profile) {
+        println!("Profile:");
+        print!("{}", profile);
+    }
+
+    if let (Some(path), Some(profile)) = (&cli.stacks, vm.profile()) {
+        fs::write(path, profile.collapsed())
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+
+    if let Some(path) = &cli.record {
+        fs::write(path, trace.to_bytes())
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+
+    Ok(code)
+}
+
+fn inspect(bytecode: &[u8]) -> Result<(), String> {
+    let inspection = Vm::inspect(bytecode, true).map_err(|error| error.to_string())?;
+
+    println!("Imports:");
+    for import in &inspection.imports {
+        println!("  {} {}::{}", import.kind, import.module, import.name);
+    }
+
+    println!("Exports:");
+    for export in &inspection.exports {
+        println!("  {} {}", export.kind, export.name);
+    }
+
+    match inspection.memory {
+        Some((initial, Some(maximum))) => println!("Memory: {} - {} pages", initial, maximum),
+        Some((initial, None)) => println!("Memory: {} pages", initial),
+        None => println!("Memory: none"),
+    }
+
+    let data_segments: Vec<String> = inspection
+        .data_segments
+        .iter()
+        .map(|size| size.to_string())
+        .collect();
+    println!("Data segments: [{}]", data_segments.join(", "));
+
+    if let Some(wat) = inspection.wat {
+        println!("{}", wat);
+    }
+
+    Ok(())
+}
+
+fn format_value(value: &wasmi::Val) -> String {
+    match value {
+        wasmi::Val::I32(value) => value.to_string(),
+        wasmi::Val::I64(value) => value.to_string(),
+        wasmi::Val::F32(value) => f32::from(*value).to_string(),
+        wasmi::Val::F64(value) => f64::from(*value).to_string(),
+        value => format!("{:?}", value),
+    }
+}
+
+fn format_data_entry(value: &DataEntry) -> String {
+    match value {
+        DataEntry::Integer(value) => format!("Integer({})", value),
+        DataEntry::Boolean(value) => format!("Boolean({})", *value != 0),
+        DataEntry::Binary(value) => format!("Binary({})", value.to_base58()),
+        DataEntry::String(value) => format!("String({:?})", String::from_utf8_lossy(value)),
+    }
+}
+
+fn format_event(event: &Event) -> String {
+    match event {
+        Event::Transfer {
+            contract_id,
+            asset_id,
+            recipient,
+            amount,
+        } => format!(
+            "Transfer: contract {} asset {} recipient {} amount {}",
+            contract_id.to_base58(),
+            asset_id.to_base58(),
+            recipient.to_base58(),
+            amount
+        ),
+        Event::Issue {
+            contract_id,
+            asset_id,
+            name,
+            quantity,
+            decimals,
+            is_reissuable,
+            ..
+        } => format!(
+            "Issue: contract {} asset {} name {:?} quantity {} decimals {} reissuable {}",
+            contract_id.to_base58(),
+            asset_id.to_base58(),
+            String::from_utf8_lossy(name),
+            quantity,
+            decimals,
+            is_reissuable
+        ),
+        Event::Burn {
+            contract_id,
+            asset_id,
+            amount,
+        } => format!(
+            "Burn: contract {} asset {} amount {}",
+            contract_id.to_base58(),
+            asset_id.to_base58(),
+            amount
+        ),
+        Event::Reissue {
+            contract_id,
+            asset_id,
+            amount,
+            is_reissuable,
+        } => format!(
+            "Reissue: contract {} asset {} amount {} reissuable {}",
+            contract_id.to_base58(),
+            asset_id.to_base58(),
+            amount,
+            is_reissuable
+        ),
+        Event::Lease {
+            contract_id,
+            lease_id,
+            recipient,
+            amount,
+        } => format!(
+            "Lease: contract {} lease {} recipient {} amount {}",
+            contract_id.to_base58(),
+            lease_id.to_base58(),
+            recipient.to_base58(),
+            amount
+        ),
+        Event::CancelLease {
+            contract_id,
+            lease_id,
+        } => format!(
+            "Cancel lease: contract {} lease {}",
+            contract_id.to_base58(),
+            lease_id.to_base58()
+        ),
+        Event::Payments {
+            contract_id,
+            payment_id,
+            payments,
+        } => {
+            let payments: Vec<String> = payments
+                .iter()
+                .map(|(asset_id, amount)| format!("{} {}", asset_id.to_base58(), amount))
+                .collect();
+            format!(
+                "Payments: contract {} payment {} [{}]",
+                contract_id.to_base58(),
+                payment_id.to_base58(),
+                payments.join(", ")
+            )
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Writes guest-side bindings of the host functions for contract SDKs.
+//!
+//! ```text
+//! cargo run --example bindings -- <output directory>
+//! ```
+use std::{env, fs, path::PathBuf};
+
+fn main() -> std::io::Result<()> {
+    let output = PathBuf::from(env::args().nth(1).unwrap_or_else(|| "bindings".to_string()));
+    fs::create_dir_all(&output)?;
+
+    let modules = [
+        (
+            "env0",
+            wevm::v0::RUST_BINDINGS,
+            wevm::v0::ASSEMBLYSCRIPT_BINDINGS,
+        ),
+        (
+            "env1",
+            wevm::v1::RUST_BINDINGS,
+            wevm::v1::ASSEMBLYSCRIPT_BINDINGS,
+        ),
+    ];
+
+    for (name, rust, assemblyscript) in modules {
+        fs::write(output.join(format!("{}.rs", name)), rust)?;
+        fs::write(output.join(format!("{}.ts", name)), assemblyscript)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Measures the calls of a block executed with the configurations of the engine.
+//!
+//! Each call of the caller makes a nested call of the callee, so the execution stacks
+//! and the modules of two depths are used. The callee has functions that are never
+//! called, as the contracts exporting several methods.
+//!
+//! ```text
+//! cargo run --release --features testing --example engine -- <calls>
+//! ```
+use std::{env, time::Instant};
+use wevm::{
+    mock::Mock,
+    session::{Call, Session},
+    CompilationMode, EngineConfig,
+};
+
+const CALLER_ID: [u8; 32] = [1; 32];
+const CALLEE_ID: [u8; 32] = [2; 32];
+
+const ROUNDS: usize = 5;
+
+const UNUSED_FUNCTIONS: usize = 100;
+
+const CALLER: &str = r#"
+(module
+    (import "env" "memory" (memory 2 16))
+
+    (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+    (func (export "_constructor") (result i32)
+        (i32.const 0)
+    )
+
+    (func (export "call") (result i32)
+        (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 3))
+    )
+
+    (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+    (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+    (data (i32.const 32) "sum")
+
+    (global $__heap_base (export "__heap_base") i32 (i32.const 35))
+)
+"#;
+
+const CALLEE: &str = r#"
+(module
+    (import "env" "memory" (memory 2 16))
+
+    (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+    (func (export "_constructor") (result i32)
+        (i32.const 0)
+    )
+
+    (func (export "sum") (result i32)
+        (local $i i64) (local $sum i64)
+        (loop $loop
+            (local.set $sum (i64.add (local.get $sum) (i64.mul (local.get $i) (local.get $i))))
+            (local.set $i (i64.add (local.get $i) (i64.const 1)))
+            (br_if $loop (i64.lt_u (local.get $i) (i64.const 10)))
+        )
+        (call $set_storage_int
+            (i32.const 0) ;; Key offset
+            (i32.const 3) ;; Key length
+            (local.get $sum)
+        )
+    )
+
+    (data (i32.const 0) "sum")
+
+    (global $__heap_base (export "__heap_base") i32 (i32.const 3))
+"#;
+
+/// Function of the callee that is never called.
+fn unused_function(index: usize) -> String {
+    format!(
+        r#"
+    (func (export "unused_{}") (param $n i64) (result i32)
+        (local $i i64) (local $sum i64)
+        (loop $loop
+            (local.set $sum (i64.add (local.get $sum) (i64.mul (local.get $i) (local.get $n))))
+            (local.set $i (i64.add (local.get $i) (i64.const 1)))
+            (br_if $loop (i64.lt_u (local.get $i) (local.get $n)))
+        )
+        (i32.wrap_i64 (local.get $sum))
+    )
+"#,
+        index
+    )
+}
+
+fn main() {
+    let calls = env::args()
+        .nth(1)
+        .and_then(|calls| calls.parse().ok())
+        .unwrap_or(1000);
+
+    let caller = wat::parse_str(CALLER).expect("WAT code parsing failed");
+    let unused: String = (0..UNUSED_FUNCTIONS).map(unused_function).collect();
+    let callee = wat::parse_str(format!("{}{})", CALLEE, unused)).expect("WAT code parsing failed");
+
+    println!("compilation_mode\tcache_modules\tcached_stacks\tus/call");
+
+    for compilation_mode in [CompilationMode::Eager, CompilationMode::LazyTranslation] {
+        for cache_modules in [false, true] {
+            for cached_stacks in [0, 1, 2, 4, 8] {
+                let mock = Mock::new();
+                mock.set_bytecode(&CALLER_ID, caller.clone());
+                mock.set_bytecode(&CALLEE_ID, callee.clone());
+
+                let mut session = Session::new(Box::new(mock)).with_engine_config(EngineConfig {
+                    compilation_mode,
+                    cached_stacks,
+                    cache_modules,
+                    ..EngineConfig::default()
+                });
+                let call = Call {
+                    contract_id: CALLER_ID.to_vec(),
+                    bytecode: caller.clone(),
+                    func_name: String::from("call"),
+                    params: vec![0, 0],
+                    fuel_limit: 10_000_000,
+                };
+
+                // The best of the rounds is taken, the others are slowed down by the system
+                let elapsed = (0..ROUNDS)
+                    .map(|_| {
+                        let start = Instant::now();
+                        for _ in 0..calls {
+                            let result = session.execute(&call).expect("Execution failed");
+                            assert_eq!(result[0].i32(), Some(0));
+                        }
+                        start.elapsed()
+                    })
+                    .min()
+                    .unwrap_or_default();
+
+                println!(
+                    "{:?}\t\t\t{}\t\t{}\t\t{:.1}",
+                    compilation_mode,
+                    cache_modules,
+                    cached_stacks,
+                    elapsed.as_secs_f64() * 1e6 / calls as f64
+                );
+            }
+        }
+    }
+}
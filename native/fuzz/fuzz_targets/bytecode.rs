@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wevm::vm::Vm;
+
+// Parsing, validation and execution of arbitrary bytecode
+fuzz_target!(|input: (Vec<u8>, Vec<u8>)| {
+    let (bytecode, params) = input;
+    let _ = Vm::run_untrusted(&bytecode, &params);
+});
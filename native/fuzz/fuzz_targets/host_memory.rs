@@ -0,0 +1,49 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wevm::{fuzzing, vm::Vm};
+
+// Linear memory accesses of host functions with offsets and lengths passed by the contract
+fuzz_target!(|input: (u32, u32, Vec<u8>)| {
+    let (offset, length, bytes) = input;
+
+    let mut memory = vec![0u8; 65536];
+    let _ = fuzzing::read_memory(&memory, offset, length);
+    let _ = fuzzing::write_memory(&mut memory, offset, bytes);
+
+    let wat = format!(
+        r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+            (import "env0" "base_58" (func $base_58 (param i32 i32) (result i32 i32 i32)))
+            (import "env0" "join" (func $join (param i32 i32 i32 i32) (result i32 i32 i32)))
+            (import "env0" "to_int" (func $to_int (param i32 i32) (result i32 i64)))
+
+            (func (export "_constructor") (result i32)
+                (call $base_58 (i32.const {offset}) (i32.const {length}))
+                (drop)
+                (drop)
+                (drop)
+                (call $join
+                    (i32.const {offset})
+                    (i32.const {length})
+                    (i32.const {length})
+                    (i32.const {offset})
+                )
+                (drop)
+                (drop)
+                (drop)
+                (call $to_int (i32.const {offset}) (i32.const {length}))
+                (drop)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const {offset}))
+        )
+        "#,
+        offset = offset as i32,
+        length = length as i32,
+    );
+
+    let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+    let _ = Vm::run_untrusted(&bytecode, &[]);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wevm::fuzzing;
+
+// Decoding of function params and data entries received from the node
+fuzz_target!(|data: &[u8]| {
+    let _ = fuzzing::decode_params(data);
+    let _ = fuzzing::decode_data_entry(data);
+});
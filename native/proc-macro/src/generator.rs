@@ -4,8 +4,15 @@ use quote::quote;
 pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, syn::Error> {
     let mut bindings: Vec<TokenStream2> = vec![];
     let mut modules: Vec<TokenStream2> = vec![];
+    let mut rust_bindings: Vec<String> = vec![];
+    let mut assemblyscript_bindings: Vec<String> = vec![];
+    let mut manifest: Vec<TokenStream2> = vec![];
 
     let env = attr.to_string();
+    let version: u32 = env
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .parse()
+        .unwrap_or_default();
 
     let input = syn::parse2::<syn::ItemMod>(item)?;
 
@@ -14,14 +21,19 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
             if let syn::Item::Fn(func) = item {
                 let func_name = &func.sig.ident;
                 let func_block = &func.block;
+                let fuel = parse_fuel(&func.attrs)?;
+                let since = parse_since(&func.attrs)?;
 
                 let mut bindings_inputs: Vec<TokenStream2> = vec![];
                 let mut modules_inputs: Vec<TokenStream2> = vec![];
+                let mut signature_inputs: Vec<(String, syn::Type)> = vec![];
+                let mut stub_inputs: Vec<TokenStream2> = vec![];
 
                 for arg in func.sig.inputs.iter() {
                     if let syn::FnArg::Typed(a) = arg {
                         if let syn::Pat::Ident(pat_ident) = &*a.pat {
                             let arg_name = &pat_ident.ident;
+                            signature_inputs.push((arg_name.to_string(), (*a.ty).clone()));
 
                             if let Some(type_string) = parse_type(&a.ty, true) {
                                 bindings_inputs.push(quote!(
@@ -33,6 +45,7 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
                                 modules_inputs.push(quote!(
                                     #arg_name: #type_string
                                 ));
+                                stub_inputs.push(quote!(_: #type_string));
                             }
                         }
                     }
@@ -40,17 +53,106 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
 
                 let mut bindings_output: Vec<TokenStream2> = vec![];
                 let mut modules_output: Vec<TokenStream2> = vec![];
+                let mut signature_output: Vec<syn::Type> = vec![];
+                let mut result_types: Option<Vec<syn::Type>> = None;
 
-                if let syn::ReturnType::Type(_, ty) = func.sig.output {
-                    if let Some(type_string) = parse_type(&ty, true) {
-                        bindings_output.push(quote!(#type_string));
-                    }
+                if let syn::ReturnType::Type(_, ty) = &func.sig.output {
+                    match parse_result(ty) {
+                        // `Result<T>` is returned to the contract as an error code followed by `T`
+                        Some(types) => {
+                            bindings_output.push(quote!(i32));
+                            modules_output.push(quote!(i32));
+                            signature_output.push(syn::parse_quote!(i32));
+                            signature_output.extend(types.iter().cloned());
+
+                            for item in types.iter() {
+                                if let Some(type_string) = parse_type(item, true) {
+                                    bindings_output.push(quote!(#type_string));
+                                }
+
+                                if let Some(type_string) = parse_type(item, false) {
+                                    modules_output.push(quote!(#type_string));
+                                }
+                            }
+
+                            result_types = Some(types);
+                        }
+                        None => {
+                            match &**ty {
+                                syn::Type::Tuple(type_tuple) => {
+                                    signature_output.extend(type_tuple.elems.iter().cloned())
+                                }
+                                ty => signature_output.push(ty.clone()),
+                            }
+
+                            if let Some(type_string) = parse_type(ty, true) {
+                                bindings_output.push(quote!(#type_string));
+                            }
 
-                    if let Some(type_string) = parse_type(&ty, false) {
-                        modules_output.push(quote!(#type_string));
+                            if let Some(type_string) = parse_type(ty, false) {
+                                modules_output.push(quote!(#type_string));
+                            }
+                        }
                     }
                 }
 
+                let call = match result_types {
+                    Some(types) => unwrap_result(&types),
+                    None => quote!(func(caller)),
+                };
+
+                let host_function = format!("{}::{}", env, func_name);
+
+                // The wrapper consumes fuel before invoking the function body
+                // and traps if the fuel is exhausted or the execution is interrupted
+                let wrapper = match fuel {
+                    Some((base, per_byte)) => {
+                        let lengths: Vec<&syn::Ident> = func
+                            .sig
+                            .inputs
+                            .iter()
+                            .filter_map(|arg| match arg {
+                                syn::FnArg::Typed(a) => match (&*a.pat, &*a.ty) {
+                                    (syn::Pat::Ident(pat_ident), syn::Type::Path(type_path))
+                                        if type_path.path.is_ident("usize") =>
+                                    {
+                                        Some(&pat_ident.ident)
+                                    }
+                                    _ => None,
+                                },
+                                _ => None,
+                            })
+                            .collect();
+
+                        quote!(
+                            |mut caller: Caller<Runtime>, #( #modules_inputs ),* | -> Result<( #( #modules_output ),* ), wasmi::Error> {
+                                caller.data_mut().vm.set_host_function(#host_function);
+                                let _span = tracing::trace_span!("host_function", name = #host_function).entered();
+
+                                let bytes: u64 = 0 #( + #lengths as u64 )*;
+                                let fuel = (#base as u64).saturating_add((#per_byte as u64).saturating_mul(bytes));
+                                crate::runtime::consume_fuel(&mut caller, fuel)?;
+                                caller.data_mut().vm.profile_host_function(#host_function, fuel);
+                                caller.data().vm.check_interrupted()?;
+
+                                let func = #func_block;
+                                Ok(#call)
+                            }
+                        )
+                    }
+                    None => quote!(
+                        |mut caller: Caller<Runtime>, #( #modules_inputs ),* | -> Result<( #( #modules_output ),* ), wasmi::Error> {
+                            caller.data_mut().vm.set_host_function(#host_function);
+                            caller.data_mut().vm.profile_host_function(#host_function, 0);
+                            let _span = tracing::trace_span!("host_function", name = #host_function).entered();
+                            caller.data().vm.check_interrupted()?;
+
+                            let func = #func_block;
+                            Ok(#call)
+                        }
+                    ),
+                };
+
                 bindings.push(quote!(
                     #[no_mangle]
                     pub fn #func_name( #( #bindings_inputs ),* ) -> ( #( #bindings_output ),* );
@@ -59,15 +161,58 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
                 let module = attr.to_string();
                 let name = func_name.to_string();
 
+                rust_bindings.push(rust_signature(&name, &signature_inputs, &signature_output));
+                assemblyscript_bindings.push(assemblyscript_signature(
+                    &module,
+                    &name,
+                    &signature_inputs,
+                    &signature_output,
+                ));
+
+                let feature = match &since {
+                    Some(feature) => quote!(Some(#feature)),
+                    None => quote!(None),
+                };
+                let params: Vec<TokenStream2> = signature_inputs
+                    .iter()
+                    .map(|(arg_name, ty)| {
+                        let ty = wasm_type(ty);
+                        quote!((#arg_name, #ty))
+                    })
+                    .collect();
+                let results: Vec<&str> = signature_output.iter().map(wasm_type).collect();
+
+                manifest.push(quote!(
+                    crate::modules::HostFunction {
+                        module: #module,
+                        name: #name,
+                        version: #version,
+                        params: &[ #( #params ),* ],
+                        results: &[ #( #results ),* ],
+                        feature: #feature,
+                    }
+                ));
+
+                // A function of a non-activated feature is linked as a stub that traps
+                let func = match &since {
+                    Some(feature) => quote!(
+                        if store.data_mut().vm.feature_activated(#feature)? {
+                            Func::wrap(store, #wrapper)
+                        } else {
+                            Func::wrap(
+                                store,
+                                |_caller: Caller<Runtime>, #( #stub_inputs ),* | -> Result<( #( #modules_output ),* ), wasmi::Error> {
+                                    Err(Error::Runtime(RuntimeError::FeatureNotActivated(#feature.to_string())).into())
+                                }
+                            )
+                        }
+                    ),
+                    None => quote!(Func::wrap(store, #wrapper)),
+                };
+
                 modules.push(quote!(
-                    fn #func_name(store: &mut Store<Runtime>) -> (String, String, Func) {
-                        (#module.to_string(), #name.to_string(), Func::wrap(
-                            store,
-                            |caller: Caller<Runtime>, #( #modules_inputs ),* | -> ( #( #modules_output ),* ) {
-                                let func = #func_block;
-                                func(caller)
-                            }
-                        ))
+                    fn #func_name(store: &mut Store<Runtime>) -> Result<(String, String, Func), Error> {
+                        Ok((#module.to_string(), #name.to_string(), #func))
                     }
 
                     vec.push(#func_name);
@@ -76,7 +221,23 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
         }
     }
 
+    let rust_bindings = format!(
+        "#[link(wasm_import_module = \"{}\")]\nextern \"C\" {{\n{}}}\n",
+        env,
+        rust_bindings.concat()
+    );
+    let assemblyscript_bindings = assemblyscript_bindings.join("\n");
+
     Ok(quote!(
+        /// Source of the Rust `extern "C"` block importing the functions of the module.
+        pub const RUST_BINDINGS: &str = #rust_bindings;
+
+        /// Source of the AssemblyScript declarations importing the functions of the module.
+        pub const ASSEMBLYSCRIPT_BINDINGS: &str = #assemblyscript_bindings;
+
+        /// Description of the functions of the module.
+        pub const MANIFEST: &[crate::modules::HostFunction] = &[ #( #manifest ),* ];
+
         #[cfg(feature = "bindings")]
         pub mod bindings {
             #[link(wasm_import_module = #env)]
@@ -85,7 +246,7 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
             }
         }
 
-        #[cfg(not(feature = "bindings"))]
+        #[cfg(feature = "jvm")]
         pub mod modules {
             use crate::{env, error::{Error, RuntimeError}, modules::Module, runtime::Runtime};
             use wasmi::{Caller, Func, Store};
@@ -101,6 +262,211 @@ pub fn module(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, sy
     ))
 }
 
+/// Parses the `#[fuel(base = N, per_byte = M)]` attribute of the function.
+/// `per_byte` is charged for the total length of the `usize` arguments.
+fn parse_fuel(attrs: &[syn::Attribute]) -> Result<Option<(u64, u64)>, syn::Error> {
+    let mut result = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("fuel")) {
+        let mut base = 0u64;
+        let mut per_byte = 0u64;
+
+        attr.parse_nested_meta(|meta| {
+            let value: syn::LitInt = meta.value()?.parse()?;
+
+            if meta.path.is_ident("base") {
+                base = value.base10_parse()?;
+            } else if meta.path.is_ident("per_byte") {
+                per_byte = value.base10_parse()?;
+            } else {
+                return Err(meta.error("expected `base` or `per_byte`"));
+            }
+
+            Ok(())
+        })?;
+
+        result = Some((base, per_byte));
+    }
+
+    Ok(result)
+}
+
+/// Parses the `#[since(feature = "X")]` attribute of the function.
+fn parse_since(attrs: &[syn::Attribute]) -> Result<Option<String>, syn::Error> {
+    let mut result = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("since")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("feature") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                result = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `feature`"))
+            }
+        })?;
+    }
+
+    Ok(result)
+}
+
+/// Returns the list of value types if the function returns `Result<T>`.
+fn parse_result(type_: &syn::Type) -> Option<Vec<syn::Type>> {
+    let syn::Type::Path(type_path) = type_ else {
+        return None;
+    };
+
+    let path_seg = type_path.path.segments.last()?;
+    if path_seg.ident != "Result" {
+        return None;
+    }
+
+    let syn::PathArguments::AngleBracketed(args) = &path_seg.arguments else {
+        return None;
+    };
+
+    match args.args.first()? {
+        syn::GenericArgument::Type(syn::Type::Tuple(type_tuple)) => {
+            Some(type_tuple.elems.iter().cloned().collect())
+        }
+        syn::GenericArgument::Type(ty) => Some(vec![ty.clone()]),
+        _ => None,
+    }
+}
+
+/// Converts the `Result<T>` returned by the function body
+/// into an error code followed by the values of `T`.
+fn unwrap_result(types: &[syn::Type]) -> TokenStream2 {
+    let values: Vec<TokenStream2> = match types.len() {
+        0 => vec![],
+        1 => vec![cast_value(quote!(value), &types[0])],
+        _ => types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| {
+                let index = syn::Index::from(index);
+                cast_value(quote!(value.#index), ty)
+            })
+            .collect(),
+    };
+    let defaults: Vec<TokenStream2> = types.iter().map(|_| quote!(Default::default())).collect();
+
+    if types.is_empty() {
+        return quote!({
+            let result: Result<(), Error> = func(caller);
+            match result {
+                Ok(()) => 0,
+                Err(error) => {
+                    log::debug!("{}", error);
+                    error.as_i32()
+                }
+            }
+        });
+    }
+
+    quote!({
+        let result: Result<_, Error> = func(caller);
+        match result {
+            Ok(value) => (0, #( #values ),* ),
+            Err(error) => {
+                log::debug!("{}", error);
+                (error.as_i32(), #( #defaults ),* )
+            }
+        }
+    })
+}
+
+fn cast_value(value: TokenStream2, type_: &syn::Type) -> TokenStream2 {
+    match type_ {
+        syn::Type::Path(type_path) if type_path.path.is_ident("bool") => quote!(#value as i32),
+        _ => value,
+    }
+}
+
+/// Rust declaration of the function as it appears in the `bindings` module.
+fn rust_signature(name: &str, inputs: &[(String, syn::Type)], output: &[syn::Type]) -> String {
+    let inputs: Vec<String> = inputs
+        .iter()
+        .map(|(arg_name, ty)| format!("{}: {}", arg_name, rust_type(ty)))
+        .collect();
+    let output: Vec<String> = output.iter().map(rust_type).collect();
+
+    let output = match output.len() {
+        0 => String::new(),
+        1 => format!(" -> {}", output[0]),
+        _ => format!(" -> ({})", output.join(", ")),
+    };
+
+    format!("    pub fn {}({}){};\n", name, inputs.join(", "), output)
+}
+
+/// AssemblyScript declaration of the function.
+/// AssemblyScript has no multiple return values,
+/// such functions are listed in a comment instead of being declared.
+fn assemblyscript_signature(
+    module: &str,
+    name: &str,
+    inputs: &[(String, syn::Type)],
+    output: &[syn::Type],
+) -> String {
+    let inputs: Vec<String> = inputs
+        .iter()
+        .map(|(arg_name, ty)| format!("{}: {}", arg_name, assemblyscript_type(ty)))
+        .collect();
+    let output: Vec<String> = output.iter().map(assemblyscript_type).collect();
+
+    let output = match output.len() {
+        0 => "void".to_string(),
+        1 => output[0].clone(),
+        _ => {
+            return format!(
+                "// {}.{}({}): ({}) returns multiple values and isn't declared\n",
+                module,
+                name,
+                inputs.join(", "),
+                output.join(", ")
+            )
+        }
+    };
+
+    format!(
+        "// @ts-ignore: decorator\n@external(\"{}\", \"{}\")\nexport declare function {}({}): {};\n",
+        module,
+        name,
+        name,
+        inputs.join(", "),
+        output
+    )
+}
+
+/// WASM value type the argument is passed to the contract as.
+fn wasm_type(type_: &syn::Type) -> &'static str {
+    match type_ {
+        syn::Type::Path(type_path) => match type_path.path.segments[0].ident.to_string().as_str() {
+            "i64" | "u64" => "i64",
+            "f32" => "f32",
+            "f64" => "f64",
+            _ => "i32",
+        },
+        _ => "i32",
+    }
+}
+
+fn rust_type(type_: &syn::Type) -> String {
+    match type_ {
+        syn::Type::Ptr(_) => "*const u8".to_string(),
+        syn::Type::Path(type_path) => type_path.path.segments[0].ident.to_string(),
+        _ => quote!(#type_).to_string(),
+    }
+}
+
+fn assemblyscript_type(type_: &syn::Type) -> String {
+    match type_ {
+        syn::Type::Ptr(_) => "usize".to_string(),
+        _ => rust_type(type_),
+    }
+}
+
 fn parse_type(type_: &syn::Type, is_bindings: bool) -> Option<TokenStream2> {
     match (type_, is_bindings) {
         (syn::Type::Ptr(_), true) => Some(quote!(*const u8)),
@@ -0,0 +1,517 @@
+//! Processing of contract bytecode on the level of module sections.
+
+use crate::{
+    error::{Error, ExecutableError, Result},
+    exec::Executable,
+};
+use sha2::{Digest, Sha256};
+use wasmparser::{ExternalKind, FuncType, Parser, Payload, TypeRef};
+
+/// Name of the custom section kept by the normalization.
+pub const ABI_SECTION: &str = "abi";
+
+pub(crate) const SECTION_CUSTOM: u8 = 0;
+pub(crate) const SECTION_GLOBAL: u8 = 6;
+pub(crate) const SECTION_EXPORT: u8 = 7;
+pub(crate) const SECTION_START: u8 = 8;
+pub(crate) const SECTION_CODE: u8 = 10;
+pub(crate) const SECTION_DATA_COUNT: u8 = 12;
+
+/// Bytecode in the canonical form together with its SHA-256 hash.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Normalized {
+    pub bytecode: Vec<u8>,
+    pub hash: Vec<u8>,
+}
+
+/// Normalizing the bytecode before it is stored or executed.
+///
+/// Name and custom sections are stripped except the ABI section, which is moved to the end
+/// of the module. All sizes and counts are rewritten in the shortest LEB128 encoding, so
+/// the same contract built by different toolchains gets the same bytes and hash.
+pub fn normalize(bytecode: &[u8]) -> Result<Normalized> {
+    Executable::validate_bytecode(bytecode)?;
+
+    let bytecode = canonicalize(bytecode)
+        .map_err(|error| Error::Executable(ExecutableError::InvalidBytecode(error.to_string())))?;
+    let hash = Sha256::digest(&bytecode).to_vec();
+
+    Ok(Normalized { bytecode, hash })
+}
+
+/// Imported entity of a contract.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    /// One of `func`, `table`, `memory`, `global` and `tag`.
+    pub kind: &'static str,
+}
+
+/// Exported entity of a contract.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Export {
+    pub name: String,
+    /// One of `func`, `table`, `memory`, `global` and `tag`.
+    pub kind: &'static str,
+}
+
+/// Description of what a contract imports, exports and allocates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Inspection {
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+    /// Initial and maximum number of pages of the linear memory.
+    pub memory: Option<(u64, Option<u64>)>,
+    /// Sizes of the data segments in bytes.
+    pub data_segments: Vec<usize>,
+    /// Disassembly of the module in the WAT text format, if requested.
+    pub wat: Option<String>,
+}
+
+impl Inspection {
+    /// Getting the JSON representation of the inspection.
+    pub fn to_json(&self) -> String {
+        let imports: Vec<serde_json::Value> = self
+            .imports
+            .iter()
+            .map(|import| {
+                serde_json::json!({
+                    "module": import.module,
+                    "name": import.name,
+                    "kind": import.kind,
+                })
+            })
+            .collect();
+        let exports: Vec<serde_json::Value> = self
+            .exports
+            .iter()
+            .map(|export| serde_json::json!({ "name": export.name, "kind": export.kind }))
+            .collect();
+        let memory = self.memory.map(
+            |(initial, maximum)| serde_json::json!({ "initial": initial, "maximum": maximum }),
+        );
+
+        serde_json::json!({
+            "imports": imports,
+            "exports": exports,
+            "memory": memory,
+            "data_segments": self.data_segments,
+            "wat": self.wat,
+        })
+        .to_string()
+    }
+}
+
+/// Inspecting the imports, exports, memory and data of the bytecode.
+/// The bytecode is only parsed, so contracts rejected by the validation can be inspected as well.
+pub fn inspect(bytecode: &[u8], disassemble: bool) -> Result<Inspection> {
+    let invalid = |error: String| Error::Executable(ExecutableError::InvalidBytecode(error));
+    let mut inspection = Inspection::default();
+
+    for payload in Parser::new(0).parse_all(bytecode) {
+        match payload.map_err(|error| invalid(error.to_string()))? {
+            Payload::ImportSection(reader) => {
+                for import in reader.into_imports() {
+                    let import = import.map_err(|error| invalid(error.to_string()))?;
+                    let kind = match import.ty {
+                        TypeRef::Func(_) | TypeRef::FuncExact(_) => "func",
+                        TypeRef::Table(_) => "table",
+                        TypeRef::Memory(memory) => {
+                            inspection.memory = Some((memory.initial, memory.maximum));
+                            "memory"
+                        }
+                        TypeRef::Global(_) => "global",
+                        TypeRef::Tag(_) => "tag",
+                    };
+                    inspection.imports.push(Import {
+                        module: import.module.to_string(),
+                        name: import.name.to_string(),
+                        kind,
+                    });
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    let memory = memory.map_err(|error| invalid(error.to_string()))?;
+                    inspection.memory = Some((memory.initial, memory.maximum));
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(|error| invalid(error.to_string()))?;
+                    let kind = match export.kind {
+                        ExternalKind::Func | ExternalKind::FuncExact => "func",
+                        ExternalKind::Table => "table",
+                        ExternalKind::Memory => "memory",
+                        ExternalKind::Global => "global",
+                        ExternalKind::Tag => "tag",
+                    };
+                    inspection.exports.push(Export {
+                        name: export.name.to_string(),
+                        kind,
+                    });
+                }
+            }
+            Payload::DataSection(reader) => {
+                for data in reader {
+                    let data = data.map_err(|error| invalid(error.to_string()))?;
+                    inspection.data_segments.push(data.data.len());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if disassemble {
+        let wat = wasmprinter::print_bytes(bytecode).map_err(|error| invalid(error.to_string()))?;
+        inspection.wat = Some(wat);
+    }
+
+    Ok(inspection)
+}
+
+/// Getting the type of the exported function without compiling the bytecode.
+/// Returns `None` if the function isn't exported.
+pub(crate) fn func_type(bytecode: &[u8], func_name: &str) -> Result<Option<FuncType>> {
+    let invalid = |error: String| Error::Executable(ExecutableError::InvalidBytecode(error));
+    let mut types = vec![];
+    // Type indexes of the imported and defined functions
+    let mut functions = vec![];
+    let mut export = None;
+
+    for payload in Parser::new(0).parse_all(bytecode) {
+        match payload.map_err(|error| invalid(error.to_string()))? {
+            Payload::TypeSection(reader) => {
+                for ty in reader.into_iter_err_on_gc_types() {
+                    types.push(ty.map_err(|error| invalid(error.to_string()))?);
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader.into_imports() {
+                    let import = import.map_err(|error| invalid(error.to_string()))?;
+                    if let TypeRef::Func(index) | TypeRef::FuncExact(index) = import.ty {
+                        functions.push(index);
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for index in reader {
+                    functions.push(index.map_err(|error| invalid(error.to_string()))?);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for item in reader {
+                    let item = item.map_err(|error| invalid(error.to_string()))?;
+                    if item.name == func_name
+                        && matches!(item.kind, ExternalKind::Func | ExternalKind::FuncExact)
+                    {
+                        export = Some(item.index);
+                    }
+                }
+            }
+            // Types, functions and exports precede the code
+            Payload::CodeSectionStart { .. } => break,
+            _ => (),
+        }
+    }
+
+    Ok(export
+        .and_then(|index| functions.get(index as usize))
+        .and_then(|index| types.get(*index as usize))
+        .cloned())
+}
+
+/// Checking if the bytecode defines mutable globals, e.g. a stack pointer or the state of an allocator.
+pub(crate) fn has_mutable_globals(bytecode: &[u8]) -> Result<bool> {
+    let invalid = |error: String| Error::Executable(ExecutableError::InvalidBytecode(error));
+
+    for payload in Parser::new(0).parse_all(bytecode) {
+        match payload.map_err(|error| invalid(error.to_string()))? {
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    if global
+                        .map_err(|error| invalid(error.to_string()))?
+                        .ty
+                        .mutable
+                    {
+                        return Ok(true);
+                    }
+                }
+            }
+            // Globals precede the code
+            Payload::CodeSectionStart { .. } => break,
+            _ => (),
+        }
+    }
+
+    Ok(false)
+}
+
+fn canonicalize(bytecode: &[u8]) -> wasmparser::Result<Vec<u8>> {
+    let mut result = bytecode[..8].to_vec();
+    let mut abi = None;
+
+    for (id, content) in sections(bytecode)? {
+        match id {
+            SECTION_CUSTOM => {
+                let mut reader = wasmparser::BinaryReader::new(content, 0);
+                if reader.read_string()? == ABI_SECTION {
+                    abi = Some(content);
+                }
+            }
+            SECTION_START | SECTION_DATA_COUNT => {
+                let mut reader = wasmparser::BinaryReader::new(content, 0);
+                let mut value = vec![];
+                write_u32(&mut value, reader.read_var_u32()?);
+                write_section(&mut result, id, &value);
+            }
+            SECTION_CODE => {
+                let mut reader = wasmparser::BinaryReader::new(content, 0);
+                let count = reader.read_var_u32()?;
+
+                let mut code = vec![];
+                write_u32(&mut code, count);
+                for _ in 0..count {
+                    let size = reader.read_var_u32()?;
+                    write_u32(&mut code, size);
+                    code.extend_from_slice(reader.read_bytes(size as usize)?);
+                }
+                write_section(&mut result, id, &code);
+            }
+            _ => {
+                // Other known sections are vectors of entries
+                let mut reader = wasmparser::BinaryReader::new(content, 0);
+                let count = reader.read_var_u32()?;
+
+                let mut vector = vec![];
+                write_u32(&mut vector, count);
+                vector.extend_from_slice(&content[reader.current_position()..]);
+                write_section(&mut result, id, &vector);
+            }
+        }
+    }
+
+    if let Some(abi) = abi {
+        write_section(&mut result, SECTION_CUSTOM, abi);
+    }
+
+    Ok(result)
+}
+
+/// Position of the section in the module, custom sections may appear anywhere.
+pub(crate) fn order(id: u8) -> u8 {
+    match id {
+        0 => 0,
+        1..=5 => id,
+        13 => 6,
+        6 => 7,
+        7 => 8,
+        8 => 9,
+        9 => 10,
+        12 => 11,
+        10 => 12,
+        11 => 13,
+        _ => u8::MAX,
+    }
+}
+
+/// Splitting the module into sections.
+pub(crate) fn sections(bytecode: &[u8]) -> wasmparser::Result<Vec<(u8, &[u8])>> {
+    let mut result = vec![];
+    let mut reader = wasmparser::BinaryReader::new(bytecode, 0);
+    reader.read_bytes(8)?;
+
+    while !reader.eof() {
+        let id = reader.read_u8()?;
+        let size = reader.read_var_u32()?;
+        let content = reader.read_bytes(size as usize)?;
+        result.push((id, content));
+    }
+
+    Ok(result)
+}
+
+pub(crate) fn write_section(output: &mut Vec<u8>, id: u8, content: &[u8]) {
+    output.push(id);
+    write_u32(output, content.len() as u32);
+    output.extend_from_slice(content);
+}
+
+/// Writing the LEB128 encoding of the number.
+pub(crate) fn write_u32(output: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            output.push(byte);
+            break;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+/// Writing the signed LEB128 encoding of the number, used by the constants.
+pub(crate) fn write_i32(output: &mut Vec<u8>, mut value: i32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            output.push(byte);
+            break;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmparser::ValType;
+
+    const CONTRACT: &str = r#"
+    (module
+        (func $sum (param $p0 i32) (result i32)
+            (i32.add (local.get $p0) (i32.const 1))
+        )
+
+        (func (export "_constructor") (result i32)
+            (call $sum (i32.const 1))
+        )
+
+        (@custom "producers" "wat")
+        (@custom "abi" "{}")
+    )
+    "#;
+
+    /// Custom section names present in the module.
+    fn custom_sections(bytecode: &[u8]) -> Vec<String> {
+        sections(bytecode)
+            .expect("Invalid module")
+            .into_iter()
+            .filter(|(id, _)| *id == SECTION_CUSTOM)
+            .map(|(_, content)| {
+                let mut reader = wasmparser::BinaryReader::new(content, 0);
+                reader.read_string().expect("Invalid name").to_string()
+            })
+            .collect()
+    }
+
+    /// Rewriting the sizes of the sections in the padded 5-byte LEB128 encoding.
+    fn pad(bytecode: &[u8]) -> Vec<u8> {
+        let mut result = bytecode[..8].to_vec();
+        for (id, content) in sections(bytecode).expect("Invalid module") {
+            result.push(id);
+            let size = content.len() as u32;
+            for shift in [0, 7, 14, 21] {
+                result.push(((size >> shift) & 0x7f) as u8 | 0x80);
+            }
+            result.push((size >> 28) as u8);
+            result.extend_from_slice(content);
+        }
+        result
+    }
+
+    #[test]
+    fn test_normalize() {
+        let bytecode = wat::parse_str(CONTRACT).expect("WAT code parsing failed");
+        assert_eq!(custom_sections(&bytecode), vec!["name", "producers", "abi"]);
+
+        let normalized = normalize(&bytecode).expect("Normalization failed");
+        assert_eq!(custom_sections(&normalized.bytecode), vec!["abi"]);
+        assert_eq!(normalized.hash.len(), 32);
+        assert!(Executable::validate_bytecode(&normalized.bytecode).is_ok());
+
+        assert_eq!(normalize(&normalized.bytecode), Ok(normalized.clone()));
+        assert_eq!(normalize(&pad(&bytecode)), Ok(normalized));
+
+        assert!(normalize(&[0, 97, 115, 109, 1, 0, 0, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+            (import "env0" "get_balance" (func $get_balance (param i32 i32 i32 i32) (result i32 i64)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (data (i32.const 0) "asset")
+            (global $__heap_base (export "__heap_base") i32 (i32.const 5))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let inspection = inspect(&bytecode, false).expect("Inspection failed");
+        assert_eq!(
+            inspection.imports,
+            vec![
+                Import {
+                    module: "env".to_string(),
+                    name: "memory".to_string(),
+                    kind: "memory"
+                },
+                Import {
+                    module: "env0".to_string(),
+                    name: "get_balance".to_string(),
+                    kind: "func"
+                },
+            ]
+        );
+        assert_eq!(inspection.exports.len(), 2);
+        assert_eq!(inspection.memory, Some((2, Some(16))));
+        assert_eq!(inspection.data_segments, vec![5]);
+        assert_eq!(inspection.wat, None);
+        assert!(inspection.to_json().starts_with(
+            "{\"imports\":[{\"module\":\"env\",\"name\":\"memory\",\"kind\":\"memory\"},"
+        ));
+
+        let inspection = inspect(&bytecode, true).expect("Inspection failed");
+        let json: serde_json::Value =
+            serde_json::from_str(&inspection.to_json()).expect("Invalid JSON");
+        let wat = inspection.wat.expect("Disassembly not found");
+        assert!(wat.contains("(import \"env0\" \"get_balance\""));
+        assert_eq!(json["wat"], wat.as_str());
+
+        assert!(inspect(&[0, 97, 115, 109, 1, 0, 0, 0, 1], false).is_err());
+    }
+
+    #[test]
+    fn test_func_type() {
+        let wat = r#"
+        (module
+            (import "env0" "get_balance" (func $get_balance (param i32 i32 i32 i32) (result i32 i64)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "transfer") (param i32 i32 i64) (result i32 i64)
+                (i32.const 0)
+                (i64.const 0)
+            )
+
+            (export "balance" (func $get_balance))
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let ty = func_type(&bytecode, "transfer")
+            .expect("Parsing failed")
+            .expect("Function not found");
+        assert_eq!(ty.params(), &[ValType::I32, ValType::I32, ValType::I64]);
+        assert_eq!(ty.results(), &[ValType::I32, ValType::I64]);
+
+        let ty = func_type(&bytecode, "balance")
+            .expect("Parsing failed")
+            .expect("Function not found");
+        assert_eq!(ty.params().len(), 4);
+
+        assert_eq!(func_type(&bytecode, "__heap_base"), Ok(None));
+        assert_eq!(func_type(&bytecode, "missing"), Ok(None));
+    }
+}
@@ -0,0 +1,163 @@
+//! Cancellation of running executions from other threads.
+//!
+//! The token is checked before a contract function is executed, on every call of a host
+//! function and periodically by the checkpoints injected at the entries of the functions and
+//! the starts of the loops, the execution then fails with `RuntimeError::Cancelled`.
+
+use crate::error::{Error, Result, RuntimeError};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Flag shared by the clones, once cancelled it stays cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signaling the cancellation to the executions using the token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Failing with `RuntimeError::Cancelled` if the token is cancelled.
+    pub fn check(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Err(Error::Runtime(RuntimeError::Cancelled)),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        external::ExternalFunction,
+        limits::ExecutionLimits,
+        mock::Mock,
+        modules,
+        vm::{ExecutionMode, Vm},
+    };
+
+    #[test]
+    fn test_cancellation() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "get_block_timestamp" (func $get_block_timestamp (result i32 i64)))
+            (import "plugin" "abort" (func $abort))
+
+            (func (export "_constructor") (result i32)
+                (call $abort)
+                (loop $loop
+                    (drop (call $get_block_timestamp))
+                    (drop)
+                    (br $loop)
+                )
+                (i32.const 0)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let token = CancellationToken::new();
+        let abort = {
+            let token = token.clone();
+            ExternalFunction::new(
+                "plugin",
+                "abort",
+                "->",
+                0,
+                Arc::new(move |_| {
+                    token.cancel();
+                    Ok(vec![])
+                }),
+            )
+            .expect("Invalid function")
+        };
+
+        let mut vm = Vm::new(
+            vec![],
+            bytecode,
+            ExecutionLimits {
+                fuel: u64::MAX,
+                ..Default::default()
+            },
+            modules::all(),
+            Box::new(Mock::new()),
+            ExecutionMode::Apply,
+        )
+        .expect("Call stack creation failed");
+        vm.set_external_functions(vec![abort]);
+        vm.set_cancellation_token(token.clone());
+
+        // The loop is stopped by the next host call
+        for _ in 0..2 {
+            let error = vm
+                .run("_constructor", &[0, 0])
+                .map(|_| ())
+                .map_err(|error| error.error);
+            assert_eq!(error, Err(Error::Runtime(RuntimeError::Cancelled)));
+        }
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_cancellation_of_loop() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (func (export "_constructor") (result i32)
+                (loop $loop
+                    (br $loop)
+                )
+                (i32.const 0)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let mut vm = Vm::new(
+            vec![],
+            bytecode,
+            ExecutionLimits {
+                fuel: u64::MAX,
+                ..Default::default()
+            },
+            modules::all(),
+            Box::new(Mock::new()),
+            ExecutionMode::Apply,
+        )
+        .expect("Call stack creation failed");
+        let token = CancellationToken::new();
+        vm.set_cancellation_token(token.clone());
+
+        // The loop without host calls is stopped by the checkpoints
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            token.cancel();
+        });
+        let error = vm
+            .run("_constructor", &[0, 0])
+            .map(|_| ())
+            .map_err(|error| error.error);
+        assert_eq!(error, Err(Error::Runtime(RuntimeError::Cancelled)));
+
+        canceller.join().expect("Cancellation failed");
+    }
+}
@@ -0,0 +1,430 @@
+use crate::{
+    bytecode::{
+        order, sections, write_section, write_u32, SECTION_CODE, SECTION_EXPORT, SECTION_GLOBAL,
+    },
+    error::{Error, ExecutableError, Result},
+};
+use base58::ToBase58;
+use std::{collections::BTreeMap, fmt, ops::Range};
+use wasmparser::{ExternalKind, Operator, Parser, Payload, TypeRef};
+
+/// Prefix of the names of exported counters.
+const COUNTER_PREFIX: &str = "__coverage_";
+
+/// Place of the contract code where a counter is injected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CounterKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+    /// Code following the end of a block.
+    End,
+}
+
+impl fmt::Display for CounterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CounterKind::Function => "function",
+            CounterKind::Block => "block",
+            CounterKind::Loop => "loop",
+            CounterKind::If => "if",
+            CounterKind::Else => "else",
+            CounterKind::End => "end",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Counter of the executions of a basic block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Counter {
+    /// Index of the function in the function index space.
+    pub func_index: u32,
+    /// Offset of the instruction starting the block in the original bytecode.
+    pub offset: usize,
+    pub kind: CounterKind,
+}
+
+/// Coverage of a single contract.
+#[derive(Clone, Debug)]
+pub struct ContractCoverage {
+    original: Vec<u8>,
+    instrumented: Vec<u8>,
+    /// Names of the exported functions by index.
+    pub names: BTreeMap<u32, String>,
+    pub counters: Vec<Counter>,
+    /// Hit counts of the counters accumulated over all executions.
+    pub hits: Vec<u64>,
+}
+
+impl ContractCoverage {
+    pub fn covered(&self) -> usize {
+        self.hits.iter().filter(|hits| **hits > 0).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.counters.len()
+    }
+}
+
+/// Code coverage of the contracts executed in the coverage mode.
+///
+/// Before the execution, a counter is injected at the entry of every function and
+/// at the start of every block. Counters are mutable globals exported by the module,
+/// they are read after the execution. Instrumented contracts consume more fuel.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    contracts: BTreeMap<Vec<u8>, ContractCoverage>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contract(&self, contract_id: &[u8]) -> Option<&ContractCoverage> {
+        self.contracts.get(contract_id)
+    }
+
+    pub fn contracts(&self) -> impl Iterator<Item = (&Vec<u8>, &ContractCoverage)> {
+        self.contracts.iter()
+    }
+
+    /// Getting the instrumented bytecode of the contract.
+    /// Hit counts are reset if the bytecode of the contract has changed.
+    pub(crate) fn instrument(&mut self, contract_id: &[u8], bytecode: &[u8]) -> Result<Vec<u8>> {
+        if let Some(contract) = self.contracts.get(contract_id) {
+            if contract.original == bytecode {
+                return Ok(contract.instrumented.clone());
+            }
+        }
+
+        let (instrumented, counters, names) = instrument(bytecode).map_err(|error| {
+            Error::Executable(ExecutableError::InvalidBytecode(format!("{:?}", error)))
+        })?;
+
+        self.contracts.insert(
+            contract_id.to_vec(),
+            ContractCoverage {
+                original: bytecode.to_vec(),
+                instrumented: instrumented.clone(),
+                names,
+                hits: vec![0; counters.len()],
+                counters,
+            },
+        );
+
+        Ok(instrumented)
+    }
+
+    /// Adding hit counts read from the exported counters of the module.
+    pub(crate) fn record(&mut self, contract_id: &[u8], counters: Vec<(String, u64)>) {
+        let Some(contract) = self.contracts.get_mut(contract_id) else {
+            return;
+        };
+
+        for (name, hits) in counters {
+            let index = name
+                .strip_prefix(COUNTER_PREFIX)
+                .and_then(|index| index.parse::<usize>().ok());
+
+            if let Some(total) = index.and_then(|index| contract.hits.get_mut(index)) {
+                *total += hits;
+            }
+        }
+    }
+
+    /// Checking if the export is a counter injected by the instrumentation.
+    pub(crate) fn is_counter(name: &str) -> bool {
+        name.starts_with(COUNTER_PREFIX)
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (contract_id, contract) in &self.contracts {
+            writeln!(
+                f,
+                "Contract: {}. Covered: {}/{}",
+                contract_id.to_base58(),
+                contract.covered(),
+                contract.total()
+            )?;
+
+            let mut functions: BTreeMap<u32, Vec<(&Counter, u64)>> = BTreeMap::new();
+            for (counter, hits) in contract.counters.iter().zip(&contract.hits) {
+                functions
+                    .entry(counter.func_index)
+                    .or_default()
+                    .push((counter, *hits));
+            }
+
+            for (func_index, counters) in functions {
+                let name = contract
+                    .names
+                    .get(&func_index)
+                    .map(|name| format!(" {}", name))
+                    .unwrap_or_default();
+                let covered = counters.iter().filter(|(_, hits)| *hits > 0).count();
+                let calls = counters.first().map(|(_, hits)| *hits).unwrap_or_default();
+
+                writeln!(
+                    f,
+                    "  func[{}]{}: covered {}/{}, calls {}",
+                    func_index,
+                    name,
+                    covered,
+                    counters.len(),
+                    calls
+                )?;
+
+                for (counter, _) in counters.iter().filter(|(_, hits)| *hits == 0) {
+                    writeln!(
+                        f,
+                        "    not covered: {} at 0x{:x}",
+                        counter.kind, counter.offset
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+type Instrumented = (Vec<u8>, Vec<Counter>, BTreeMap<u32, String>);
+
+/// Injecting counters into the module.
+fn instrument(bytecode: &[u8]) -> wasmparser::Result<Instrumented> {
+    let mut imported_funcs = 0;
+    let mut imported_globals = 0;
+    let mut defined_globals = 0;
+    let mut names = BTreeMap::new();
+    let mut bodies: Vec<(Range<usize>, Vec<usize>)> = vec![];
+    let mut counters = vec![];
+
+    for payload in Parser::new(0).parse_all(bytecode) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader.into_imports() {
+                    match import?.ty {
+                        TypeRef::Func(_) => imported_funcs += 1,
+                        TypeRef::Global(_) => imported_globals += 1,
+                        _ => (),
+                    }
+                }
+            }
+            Payload::GlobalSection(reader) => defined_globals = reader.count(),
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        names.insert(export.index, export.name.to_string());
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let func_index = imported_funcs + bodies.len() as u32;
+                let mut reader = body.get_operators_reader()?;
+                let mut points = vec![reader.original_position()];
+                counters.push(Counter {
+                    func_index,
+                    offset: reader.original_position(),
+                    kind: CounterKind::Function,
+                });
+
+                let mut depth = 0;
+                while !reader.eof() {
+                    let (operator, offset) = reader.read_with_offset()?;
+                    let kind = match operator {
+                        Operator::Block { .. } => CounterKind::Block,
+                        Operator::Loop { .. } => CounterKind::Loop,
+                        Operator::If { .. } => CounterKind::If,
+                        Operator::Else => CounterKind::Else,
+                        Operator::End if depth == 0 => continue,
+                        Operator::End => CounterKind::End,
+                        _ => continue,
+                    };
+
+                    match kind {
+                        CounterKind::Block | CounterKind::Loop | CounterKind::If => depth += 1,
+                        CounterKind::End => depth -= 1,
+                        _ => (),
+                    }
+
+                    points.push(reader.original_position());
+                    counters.push(Counter {
+                        func_index,
+                        offset,
+                        kind,
+                    });
+                }
+
+                bodies.push((body.range(), points));
+            }
+            _ => (),
+        }
+    }
+
+    if counters.is_empty() {
+        return Ok((bytecode.to_vec(), counters, names));
+    }
+
+    let first_counter = imported_globals + defined_globals;
+
+    let mut globals = vec![];
+    let mut exports = vec![];
+    for index in 0..counters.len() as u32 {
+        // (global (mut i64) (i64.const 0))
+        globals.extend_from_slice(&[0x7e, 0x01, 0x42, 0x00, 0x0b]);
+
+        let name = format!("{}{}", COUNTER_PREFIX, index);
+        write_u32(&mut exports, name.len() as u32);
+        exports.extend_from_slice(name.as_bytes());
+        exports.push(0x03);
+        write_u32(&mut exports, first_counter + index);
+    }
+
+    let mut code = vec![];
+    write_u32(&mut code, bodies.len() as u32);
+    let mut index = first_counter;
+    for (range, points) in bodies {
+        let mut body = vec![];
+        let mut position = range.start;
+        for point in points {
+            body.extend_from_slice(&bytecode[position..point]);
+            // global.get, i64.const 1, i64.add, global.set
+            body.push(0x23);
+            write_u32(&mut body, index);
+            body.extend_from_slice(&[0x42, 0x01, 0x7c, 0x24]);
+            write_u32(&mut body, index);
+            position = point;
+            index += 1;
+        }
+        body.extend_from_slice(&bytecode[position..range.end]);
+
+        write_u32(&mut code, body.len() as u32);
+        code.extend(body);
+    }
+
+    let sections = sections(bytecode)?;
+    let mut result = bytecode[..8].to_vec();
+    let mut has_globals = false;
+    let mut has_exports = false;
+
+    for (id, content) in sections {
+        if !has_globals && order(id) > order(SECTION_GLOBAL) {
+            write_section(
+                &mut result,
+                SECTION_GLOBAL,
+                &extend(&[], &globals, counters.len()),
+            );
+            has_globals = true;
+        }
+        if !has_exports && order(id) > order(SECTION_EXPORT) {
+            write_section(
+                &mut result,
+                SECTION_EXPORT,
+                &extend(&[], &exports, counters.len()),
+            );
+            has_exports = true;
+        }
+
+        match id {
+            SECTION_GLOBAL => {
+                write_section(&mut result, id, &extend(content, &globals, counters.len()));
+                has_globals = true;
+            }
+            SECTION_EXPORT => {
+                write_section(&mut result, id, &extend(content, &exports, counters.len()));
+                has_exports = true;
+            }
+            SECTION_CODE => write_section(&mut result, id, &code),
+            _ => write_section(&mut result, id, content),
+        }
+    }
+
+    Ok((result, counters, names))
+}
+
+/// Appending entries to the content of a vector section.
+fn extend(content: &[u8], entries: &[u8], count: usize) -> Vec<u8> {
+    let (old_count, old_entries) = if content.is_empty() {
+        (0, content)
+    } else {
+        let mut reader = wasmparser::BinaryReader::new(content, 0);
+        let count = reader.read_var_u32().unwrap_or_default();
+        (count, &content[reader.current_position()..])
+    };
+
+    let mut result = vec![];
+    write_u32(&mut result, old_count + count as u32);
+    result.extend_from_slice(old_entries);
+    result.extend_from_slice(entries);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instrument() {
+        let wat = r#"
+        (module
+            (func $sum (export "sum") (param $p0 i32) (result i32)
+                (if (result i32) (local.get $p0)
+                    (then (i32.const 1))
+                    (else (i32.const 2))
+                )
+            )
+
+            (func (export "_constructor") (result i32)
+                (block
+                    (call $sum (i32.const 1))
+                    (drop)
+                )
+                (i32.const 0)
+            )
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let mut coverage = Coverage::new();
+        let instrumented = coverage
+            .instrument(&[1], &bytecode)
+            .expect("Instrumentation failed");
+        wasmparser::Validator::new()
+            .validate_all(&instrumented)
+            .expect("Invalid instrumented module");
+
+        let contract = coverage.contract(&[1]).expect("Contract not found");
+        let kinds: Vec<CounterKind> = contract.counters.iter().map(|item| item.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                CounterKind::Function,
+                CounterKind::If,
+                CounterKind::Else,
+                CounterKind::End,
+                CounterKind::Function,
+                CounterKind::Block,
+                CounterKind::End,
+            ]
+        );
+        assert_eq!(contract.names.get(&1), Some(&"_constructor".to_string()));
+
+        coverage.record(
+            &[1],
+            vec![
+                ("__coverage_0".to_string(), 1),
+                ("__coverage_1".to_string(), 1),
+            ],
+        );
+        let contract = coverage.contract(&[1]).expect("Contract not found");
+        assert_eq!(contract.covered(), 2);
+        assert_eq!(contract.total(), 7);
+    }
+}
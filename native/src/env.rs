@@ -5,24 +5,35 @@ pub mod converts;
 pub mod crypto;
 pub mod lease;
 pub mod memory;
+pub mod permission;
 pub mod storage;
 pub mod tx;
 pub mod utils;
 
 use crate::{
-    error::Result,
-    node::Node,
+    error::{Error, Result, RuntimeError},
     runtime::{
         asset_holder::{AddressVersion, AssetHolder, Type},
         Runtime,
     },
 };
+use wasmi::Caller;
 
 pub enum Field {
     String(String),
     Binary(u32, u32),
 }
 
+/// Getting linear memory of the contract together with the runtime.
+pub(in crate::env) fn memory<'a, 'b>(
+    caller: &'a mut Caller<'_, Runtime<'b>>,
+) -> Result<(&'a mut [u8], &'a mut Runtime<'b>)> {
+    match caller.data().memory() {
+        Some(memory) => Ok(memory.data_and_store_mut(caller)),
+        None => Err(Error::Runtime(RuntimeError::MemoryNotFound)),
+    }
+}
+
 pub(in crate::env) fn get_asset_holder(
     ctx: &mut Runtime,
     type_: u32,
@@ -31,23 +42,45 @@ pub(in crate::env) fn get_asset_holder(
 ) -> Result<Vec<u8>> {
     let type_ = Type::try_from(type_)?;
     let version = AddressVersion::try_from(version)?;
-    let chain_id = ctx.vm.get_chain_id()? as u8;
+    let chain_id = ctx.vm.node.get_chain_id()? as u8;
     Ok(AssetHolder::from_bytes(type_, version, chain_id, bytes).as_bytes())
 }
 
+/// Getting a slice of WASM linear memory.
+/// Fails if the slice is out of the bounds of the memory.
+pub(crate) fn read_memory(memory: &[u8], offset: u32, length: u32) -> Result<&[u8]> {
+    let start = offset as usize;
+    let end = start + length as usize;
+    memory
+        .get(start..end)
+        .ok_or(Error::Runtime(RuntimeError::MemoryOutOfBounds))
+}
+
+/// Checking that a result of at most `size` bytes fits into the memory above the heap base,
+/// so large results are rejected before they are computed.
+pub(in crate::env) fn check_output(ctx: &Runtime, memory: &[u8], size: usize) -> Result<()> {
+    let available = memory.len().saturating_sub(ctx.heap_base() as usize);
+    match size > available {
+        true => Err(Error::Runtime(RuntimeError::MemoryLimitExceeded)),
+        false => Ok(()),
+    }
+}
+
 /// Wrapper over writing to WASM linear memory.
-/// Functions using this wrapper return (i32, i32, i32):
-/// * First value - error code
-/// * Second value - memory offset
-/// * Third value - length of data in memory
-pub(in crate::env) fn write_memory(
+/// Functions using this wrapper return `Result<(u32, u32)>`:
+/// * First value - memory offset
+/// * Second value - length of data in memory
+pub(crate) fn write_memory(
     ctx: &mut Runtime,
     memory: &mut [u8],
     offset_memory: usize,
     result: Vec<u8>,
-) -> (i32, u32, u32) {
+) -> Result<(u32, u32)> {
     let length = result.len();
-    memory[offset_memory..offset_memory + length].copy_from_slice(result.as_slice());
+    memory
+        .get_mut(offset_memory..offset_memory + length)
+        .ok_or(Error::Runtime(RuntimeError::MemoryOutOfBounds))?
+        .copy_from_slice(result.as_slice());
     ctx.set_heap_base((offset_memory + length) as i32);
-    (0, offset_memory as u32, length as u32)
+    Ok((offset_memory as u32, length as u32))
 }
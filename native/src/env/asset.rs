@@ -1,5 +1,4 @@
-use crate::{error::RuntimeError, node::Node, runtime::Runtime};
-use log::error;
+use crate::{error::Result, runtime::Runtime};
 use wasmi::Caller;
 
 pub fn get_balance(
@@ -10,37 +9,24 @@ pub fn get_balance(
     type_: u32,
     version: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i64) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<i64> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
-    let asset_id =
-        &memory[offset_asset_id as usize..offset_asset_id as usize + length_asset_id as usize];
+    let asset_id = crate::env::read_memory(memory, offset_asset_id, length_asset_id)?;
 
     let (type_, bytes) = if length_asset_holder != 0 {
-        let bytes = &memory[offset_asset_holder as usize
-            ..offset_asset_holder as usize + length_asset_holder as usize];
+        let bytes = crate::env::read_memory(memory, offset_asset_holder, length_asset_holder)?;
         (type_, bytes.to_vec())
     } else {
         (1, ctx.vm.top_frame().contract_id())
     };
 
-    let asset_holder = match crate::env::get_asset_holder(ctx, type_, version, bytes) {
-        Ok(bytes) => bytes,
-        Err(error) => return (error.as_i32(), 0),
-    };
+    let asset_holder = crate::env::get_asset_holder(ctx, type_, version, bytes)?;
 
-    match ctx.vm.get_balance(asset_id, asset_holder.as_slice()) {
-        Ok(result) => (0, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0)
-        }
-    }
+    ctx.vm.node.get_balance(asset_id, asset_holder.as_slice())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn transfer(
     offset_asset_id: u32,
     length_asset_id: u32,
@@ -50,40 +36,26 @@ pub fn transfer(
     version: u32,
     amount: i64,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Transfer")?;
 
     let contract_id = ctx.vm.top_frame().contract_id();
-    let asset_id =
-        &memory[offset_asset_id as usize..offset_asset_id as usize + length_asset_id as usize];
-
-    let recipient =
-        &memory[offset_recipient as usize..offset_recipient as usize + length_recipient as usize];
-    let asset_holder = match crate::env::get_asset_holder(ctx, type_, version, recipient.to_vec()) {
-        Ok(bytes) => bytes,
-        Err(error) => {
-            error!("{}", error);
-            return error.as_i32();
-        }
-    };
+    ctx.vm.check_paused(&contract_id)?;
+    let asset_id = crate::env::read_memory(memory, offset_asset_id, length_asset_id)?;
+
+    let recipient = crate::env::read_memory(memory, offset_recipient, length_recipient)?;
+    let asset_holder = crate::env::get_asset_holder(ctx, type_, version, recipient.to_vec())?;
 
-    match ctx.vm.transfer(
+    ctx.vm.node.transfer(
         contract_id.as_slice(),
         asset_id,
         asset_holder.as_slice(),
         amount,
-    ) {
-        Ok(_) => 0,
-        Err(error) => {
-            error!("{}", error);
-            error.as_i32()
-        }
-    }
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn issue(
     offset_name: u32,
     length_name: u32,
@@ -93,32 +65,26 @@ pub fn issue(
     decimals: i64,
     is_reissuable: i32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Issue")?;
     let offset_memory = ctx.heap_base() as usize;
 
     let contract_id = ctx.vm.top_frame().contract_id();
-    let name = &memory[offset_name as usize..offset_name as usize + length_name as usize];
-    let description = &memory
-        [offset_description as usize..offset_description as usize + length_description as usize];
+    ctx.vm.check_paused(&contract_id)?;
+    let name = crate::env::read_memory(memory, offset_name, length_name)?;
+    let description = crate::env::read_memory(memory, offset_description, length_description)?;
 
-    match ctx.vm.issue(
+    let result = ctx.vm.node.issue(
         contract_id.as_slice(),
         name,
         description,
         quantity,
         decimals,
         is_reissuable != 0,
-    ) {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0, 0)
-        }
-    }
+    )?;
+
+    crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
 pub fn burn(
@@ -126,25 +92,15 @@ pub fn burn(
     length_asset_id: u32,
     amount: i64,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => {
-            return RuntimeError::MemoryNotFound.as_i32();
-        }
-    };
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Burn")?;
 
     let contract_id = ctx.vm.top_frame().contract_id();
-    let asset_id =
-        &memory[offset_asset_id as usize..offset_asset_id as usize + length_asset_id as usize];
-
-    match ctx.vm.burn(contract_id.as_slice(), asset_id, amount) {
-        Ok(_) => 0,
-        Err(error) => {
-            error!("{}", error);
-            error.as_i32()
-        }
-    }
+    ctx.vm.check_paused(&contract_id)?;
+    let asset_id = crate::env::read_memory(memory, offset_asset_id, length_asset_id)?;
+
+    ctx.vm.node.burn(contract_id.as_slice(), asset_id, amount)
 }
 
 pub fn reissue(
@@ -153,24 +109,15 @@ pub fn reissue(
     amount: i64,
     is_reissuable: i32,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Reissue")?;
 
     let contract_id = ctx.vm.top_frame().contract_id();
-    let asset_id =
-        &memory[offset_asset_id as usize..offset_asset_id as usize + length_asset_id as usize];
+    ctx.vm.check_paused(&contract_id)?;
+    let asset_id = crate::env::read_memory(memory, offset_asset_id, length_asset_id)?;
 
-    match ctx
-        .vm
+    ctx.vm
+        .node
         .reissue(contract_id.as_slice(), asset_id, amount, is_reissuable != 0)
-    {
-        Ok(_) => 0,
-        Err(error) => {
-            error!("{}", error);
-            error.as_i32()
-        }
-    }
 }
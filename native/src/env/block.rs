@@ -1,52 +1,127 @@
-use crate::{env::Field, error::RuntimeError, node::Node, runtime::Runtime};
-use log::error;
+use crate::{
+    env::Field,
+    error::{Error, Result, RuntimeError},
+    limits::ExecutionLimits,
+    runtime::Runtime,
+};
 use wasmi::Caller;
 
-pub fn get_block_field(field: Field, mut caller: Caller<Runtime>) -> (i32, i64) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+pub fn get_block_field(field: Field, mut caller: Caller<Runtime>) -> Result<i64> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
     let field = match field {
         Field::String(name) => name.into_bytes(),
-        Field::Binary(offset, length) => {
-            memory[offset as usize..offset as usize + length as usize].to_vec()
-        }
+        Field::Binary(offset, length) => crate::env::read_memory(memory, offset, length)?.to_vec(),
     };
 
-    match ctx.vm.block(field.as_slice()) {
-        Ok(bytes) => {
-            let mut result = [0u8; 8];
-            result.copy_from_slice(&bytes);
-            (0, i64::from_be_bytes(result))
-        }
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0)
-        }
-    }
+    parse_i64(&ctx.vm.node.block(field.as_slice())?)
 }
 
-pub fn block(field: Field, mut caller: Caller<Runtime>) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+pub fn block(field: Field, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let field = match field {
         Field::String(name) => name.into_bytes(),
-        Field::Binary(offset, length) => {
-            memory[offset as usize..offset as usize + length as usize].to_vec()
-        }
+        Field::Binary(offset, length) => crate::env::read_memory(memory, offset, length)?.to_vec(),
     };
 
-    match ctx.vm.block(field.as_slice()) {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0, 0)
-        }
+    let result = ctx.vm.node.block(field.as_slice())?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+/// Getting the timestamp and the generator of a past block within the look-back window.
+pub fn get_block_at(height: i64, mut caller: Caller<Runtime>) -> Result<(i64, u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let current = parse_i64(&ctx.vm.node.block(b"height")?)?;
+
+    if height < 1 || height > current {
+        return Err(Error::Runtime(RuntimeError::BlockNotFound));
+    }
+    ExecutionLimits::check(
+        "block lookback",
+        (current - height) as usize,
+        ctx.vm.limits().block_lookback,
+    )?;
+
+    let bytes = ctx.vm.node.get_block_at(height)?;
+    let (timestamp, generator) = bytes.split_at(bytes.len().min(8));
+    let timestamp = parse_i64(timestamp)?;
+
+    let (offset, length) =
+        crate::env::write_memory(ctx, memory, offset_memory, generator.to_vec())?;
+    Ok((timestamp, offset, length))
+}
+
+fn parse_i64(bytes: &[u8]) -> Result<i64> {
+    <[u8; 8]>::try_from(bytes)
+        .map(i64::from_be_bytes)
+        .map_err(|_| Error::Runtime(RuntimeError::InvalidResult("Expected 8 bytes".into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RuntimeError,
+        runtime::data_entry::DataEntry,
+        tests::{test_env, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_get_block_at() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_block_at" (func $get_block_at (param i64) (result i32 i64 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "block") (param $height i64) (result i32 i64)
+                (local $error i32) (local $timestamp i64) (local $offset i32) (local $length i32)
+                (call $get_block_at (local.get $height))
+                (local.set $length)
+                (local.set $offset)
+                (local.set $timestamp)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error) (i64.const 0)))
+                )
+                (call $set_storage_binary
+                    (i32.const 0) ;; Key offset
+                    (i32.const 9) ;; Key length
+                    (local.get $offset)
+                    (local.get $length)
+                )
+                (local.get $timestamp)
+            )
+
+            (data (i32.const 0) "generator")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 9))
+        )
+        "#;
+
+        let env = test_env(wat);
+        env.mock().set_height(1500);
+        env.mock().set_block(1400, 42, &[1; 26]);
+        env.mock().set_block(400, 7, &[2; 26]);
+
+        env.call(&CONTRACT_ID, "block", &[DataEntry::Integer(1400)])
+            .assert_results(&[DataEntry::Integer(42)])
+            .assert_write(&CONTRACT_ID, b"generator", DataEntry::Binary(vec![1; 26]));
+
+        // Blocks outside of the look-back window aren't available
+        env.call(&CONTRACT_ID, "block", &[DataEntry::Integer(400)])
+            .assert_error(RuntimeError::LimitExceeded(String::new()).as_i32());
+        env.call(&CONTRACT_ID, "block", &[DataEntry::Integer(1501)])
+            .assert_error(RuntimeError::BlockNotFound.as_i32());
+        env.call(&CONTRACT_ID, "block", &[DataEntry::Integer(0)])
+            .assert_error(RuntimeError::BlockNotFound.as_i32());
     }
 }
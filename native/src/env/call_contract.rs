@@ -1,11 +1,10 @@
 use crate::{
-    error::RuntimeError,
-    node::Node,
-    runtime::{data_entry::DataEntry, payment_id::PaymentId, Runtime},
+    error::{Error, Result, RuntimeError},
+    runtime::{data_entry::DataEntry, payment_id::PaymentId, payments::Payments, Runtime},
 };
 use log::error;
 use std::str;
-use wasmi::{Caller, Value};
+use wasmi::Caller;
 
 pub fn call_arg_int(value: i64, mut caller: Caller<Runtime>) {
     caller.data_mut().params.push(DataEntry::Integer(value));
@@ -15,28 +14,30 @@ pub fn call_arg_bool(value: i32, mut caller: Caller<Runtime>) {
     caller.data_mut().params.push(DataEntry::Boolean(value));
 }
 
-pub fn call_arg_binary(offset_value: u32, length_value: u32, mut caller: Caller<Runtime>) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+pub fn call_arg_binary(
+    offset_value: u32,
+    length_value: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
-    let value = &memory[offset_value as usize..offset_value as usize + length_value as usize];
+    let value = crate::env::read_memory(memory, offset_value, length_value)?;
     ctx.params.push(DataEntry::Binary(value.to_vec()));
 
-    0
+    Ok(())
 }
 
-pub fn call_arg_string(offset_value: u32, length_value: u32, mut caller: Caller<Runtime>) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+pub fn call_arg_string(
+    offset_value: u32,
+    length_value: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
-    let value = &memory[offset_value as usize..offset_value as usize + length_value as usize];
+    let value = crate::env::read_memory(memory, offset_value, length_value)?;
     ctx.params.push(DataEntry::String(value.to_vec()));
 
-    0
+    Ok(())
 }
 
 pub fn call_payment(
@@ -44,17 +45,26 @@ pub fn call_payment(
     length_asset_id: u32,
     amount: i64,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+
+    let asset_id = crate::env::read_memory(memory, offset_asset_id, length_asset_id)?;
+    let limit = ctx.vm.limits().payments;
+    ctx.payments.push_limited(asset_id, amount, limit)
+}
+
+/// Checking that the contract holds the assets of the payments.
+fn check_balances(ctx: &mut Runtime, payments: &Payments) -> Result<()> {
+    let contract_id = ctx.vm.top_frame().contract_id();
+    let holder = crate::env::get_asset_holder(ctx, 1, 1, contract_id)?;
 
-    let asset_id =
-        &memory[offset_asset_id as usize..offset_asset_id as usize + length_asset_id as usize];
-    ctx.payments.push(asset_id, amount);
+    for (asset_id, amount) in payments.iter() {
+        if ctx.vm.node.get_balance(asset_id, &holder)? < amount {
+            return Err(Error::Runtime(RuntimeError::InsufficientBalance));
+        }
+    }
 
-    0
+    Ok(())
 }
 
 pub fn call_contract(
@@ -64,6 +74,52 @@ pub fn call_contract(
     length_func_name: u32,
     offset_params: Option<u32>,
     length_params: Option<u32>,
+    caller: Caller<Runtime>,
+) -> i32 {
+    call(
+        offset_contract_id,
+        length_contract_id,
+        offset_func_name,
+        length_func_name,
+        offset_params,
+        length_params,
+        false,
+        caller,
+    )
+}
+
+/// Executing the bytecode of another contract with the storage of the calling contract.
+/// Delegate calls can't carry payments and can't target the owner of the storage.
+pub fn delegate_call(
+    offset_contract_id: u32,
+    length_contract_id: u32,
+    offset_func_name: u32,
+    length_func_name: u32,
+    offset_params: u32,
+    length_params: u32,
+    caller: Caller<Runtime>,
+) -> i32 {
+    call(
+        offset_contract_id,
+        length_contract_id,
+        offset_func_name,
+        length_func_name,
+        Some(offset_params),
+        Some(length_params),
+        true,
+        caller,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn call(
+    offset_contract_id: u32,
+    length_contract_id: u32,
+    offset_func_name: u32,
+    length_func_name: u32,
+    offset_params: Option<u32>,
+    length_params: Option<u32>,
+    delegate: bool,
     mut caller: Caller<Runtime>,
 ) -> i32 {
     let (memory, ctx) = match caller.data().memory() {
@@ -71,10 +127,26 @@ pub fn call_contract(
         None => return RuntimeError::MemoryNotFound.as_i32(),
     };
 
-    let callable_contract_id = &memory
-        [offset_contract_id as usize..offset_contract_id as usize + length_contract_id as usize];
+    // Accumulated payments and arguments belong to this call even if it fails,
+    // so they are never passed to the next one
+    let payments = std::mem::take(&mut ctx.payments);
+    let args = ctx.params.as_bytes();
+    ctx.params.reset();
 
-    let bytecode = match ctx.vm.get_bytecode(callable_contract_id) {
+    let callable_contract_id =
+        match crate::env::read_memory(memory, offset_contract_id, length_contract_id) {
+            Ok(bytes) => bytes,
+            Err(error) => return error.as_i32(),
+        };
+
+    if delegate && (!payments.is_empty() || ctx.vm.top_frame().storage_id() == callable_contract_id)
+    {
+        let error = RuntimeError::InvalidDelegateCall;
+        error!("{}", error);
+        return error.as_i32();
+    }
+
+    let bytecode = match ctx.vm.node.get_bytecode(callable_contract_id) {
         Ok(bytecode) => bytecode,
         Err(error) => {
             error!("{}", error);
@@ -82,71 +154,647 @@ pub fn call_contract(
         }
     };
 
-    let func_name = match str::from_utf8(
-        &memory[offset_func_name as usize..offset_func_name as usize + length_func_name as usize],
-    ) {
-        Ok(string) => string,
-        Err(_) => return RuntimeError::Utf8Error.as_i32(),
+    let func_name = match crate::env::read_memory(memory, offset_func_name, length_func_name) {
+        Ok(bytes) => match str::from_utf8(bytes) {
+            Ok(string) => string,
+            Err(_) => return RuntimeError::Utf8Error.as_i32(),
+        },
+        Err(error) => return error.as_i32(),
     };
 
     let params: Vec<u8> = match (offset_params, length_params) {
-        (Some(offset), Some(length)) => {
-            memory[offset as usize..offset as usize + length as usize].to_vec()
+        (Some(offset), Some(length)) => match crate::env::read_memory(memory, offset, length) {
+            Ok(bytes) => bytes.to_vec(),
+            Err(error) => return error.as_i32(),
+        },
+        _ => args,
+    };
+
+    let self_contract_id = ctx.vm.top_frame().contract_id();
+
+    let payments = if payments.is_empty() {
+        None
+    } else {
+        if let Err(error) = ctx
+            .mode
+            .check_change("Payment")
+            .and_then(|_| ctx.vm.check_paused(callable_contract_id))
+            .and_then(|_| check_balances(ctx, &payments))
+        {
+            error!("{}", error);
+            return error.as_i32();
         }
-        _ => {
-            let bytes = ctx.params.as_bytes();
-            ctx.params.reset();
-            bytes
+
+        Some(payments.as_bytes())
+    };
+
+    // Changes made by a failed call are rolled back, the payments to the callee included
+    let snapshot = match ctx.snapshot() {
+        Ok(snapshot) => snapshot,
+        Err(error) => {
+            error!("{}", error);
+            return error.as_i32();
         }
     };
 
     // Since a single contract can be invoked multiple times during execution,
-    // it is necessary to have a unique identifier to distinguish each unique execution
+    // it is necessary to have a unique identifier to distinguish each unique execution.
+    // The nonce is taken once the call is accepted, rejected calls don't use it
     let nonce = ctx.vm.get_nonce();
     let payment_id = PaymentId::new(callable_contract_id.to_vec(), nonce);
 
-    let self_contract_id = ctx.vm.top_frame().contract_id();
-
-    if !ctx.payments.is_empty() {
-        let payments = ctx.payments.as_bytes();
-        ctx.payments.reset();
-
-        match ctx.vm.add_payments(
+    let result = match payments {
+        Some(payments) => ctx.vm.node.add_payments(
             self_contract_id.as_slice(),
             payment_id.as_bytes().as_slice(),
             &payments,
-        ) {
-            Ok(()) => (),
-            Err(error) => {
-                error!("{}", error);
-                return error.as_i32();
-            }
-        }
-    }
+        ),
+        None => Ok(()),
+    };
 
-    match ctx.vm.call(
-        callable_contract_id.to_vec(),
-        bytecode,
-        nonce,
-        func_name,
-        &params,
-    ) {
-        Ok(result) => {
-            // TODO: Functions cannot return any values, they can only return an error code
-            let error = RuntimeError::InvalidResult(format!("Functions cannot return any values, they can only return an error code. Result: {:?}", result));
-            if result.len() != 1 {
-                error!("{}", error);
-                return error.as_i32();
-            }
+    ctx.results.reset();
+
+    let code = match result {
+        Ok(()) => {
+            let contract_id = callable_contract_id.to_vec();
+            let result = if delegate {
+                ctx.vm
+                    .delegate_call(contract_id, bytecode, nonce, func_name, &params)
+            } else {
+                ctx.vm
+                    .call(contract_id, bytecode, nonce, func_name, &params)
+            };
 
-            match result[0] {
-                Value::I32(value) => value,
-                _ => error.as_i32(),
+            match result {
+                Ok(values) => match DataEntry::from_results(&values) {
+                    Ok((code, results)) => {
+                        if code == 0 {
+                            for result in results {
+                                ctx.results.push(result);
+                            }
+                        }
+                        code
+                    }
+                    Err(error) => {
+                        error!("{}", error);
+                        error.as_i32()
+                    }
+                },
+                Err(error) => {
+                    error!("{}", error);
+                    error.as_i32()
+                }
             }
         }
         Err(error) => {
             error!("{}", error);
             error.as_i32()
         }
+    };
+
+    let result = if code == 0 {
+        ctx.discard(snapshot)
+    } else {
+        ctx.restore(snapshot)
+    };
+
+    match result {
+        Ok(()) => code,
+        Err(error) => {
+            error!("{}", error);
+            error.as_i32()
+        }
+    }
+}
+
+pub fn is_paused(
+    offset_contract_id: u32,
+    length_contract_id: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<bool> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+
+    let contract_id = if length_contract_id != 0 {
+        crate::env::read_memory(memory, offset_contract_id, length_contract_id)?.to_vec()
+    } else {
+        ctx.vm.top_frame().contract_id()
+    };
+
+    ctx.vm.node.is_paused(contract_id.as_slice())
+}
+
+/// Getting the SHA-256 hash of the normalized bytecode of the contract,
+/// so it can be compared with an audited build.
+pub fn get_bytecode_hash(
+    offset_contract_id: u32,
+    length_contract_id: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let contract_id = if length_contract_id != 0 {
+        crate::env::read_memory(memory, offset_contract_id, length_contract_id)?.to_vec()
+    } else {
+        ctx.vm.top_frame().contract_id()
+    };
+
+    let bytecode = ctx.vm.node.get_bytecode(contract_id.as_slice())?;
+    let normalized = crate::bytecode::normalize(&bytecode)?;
+    crate::env::write_memory(ctx, memory, offset_memory, normalized.hash)
+}
+
+pub fn get_call_depth(caller: Caller<Runtime>) -> i32 {
+    caller.data().vm.call_depth() as i32
+}
+
+pub fn get_frame_caller(index: i32, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let result = usize::try_from(index)
+        .ok()
+        .and_then(|index| ctx.vm.frame_caller(index))
+        .ok_or(Error::Runtime(RuntimeError::FrameNotFound))?;
+
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+/// Getting the values returned by the last successful call, serialized like the params.
+pub fn get_call_results(mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let results = ctx.results.as_bytes();
+    crate::env::write_memory(ctx, memory, offset_memory, results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bytecode::normalize,
+        error::ExecutableError,
+        runtime::params::Params,
+        testing::TestEnv,
+        tests::{deploy, test_env, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_frame_caller() {
+        const CALLEE_ID: [u8; 32] = [2; 32];
+
+        let caller = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "call_record") (result i32)
+                (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 6))
+            )
+
+            (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 32) "record")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 38))
+        )
+        "#;
+
+        let callee = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_call_depth" (func $get_call_depth (result i32)))
+            (import "env1" "get_frame_caller" (func $get_frame_caller (param i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "record") (result i32)
+                (local $error i32) (local $offset i32) (local $length i32)
+                (block $code
+                    (local.set $error
+                        (call $set_storage_int
+                            (i32.const 0) ;; Key offset
+                            (i32.const 5) ;; Key length
+                            (i64.extend_i32_u (call $get_call_depth))
+                        )
+                    )
+                    (br_if $code (local.get $error))
+
+                    (call $get_frame_caller (i32.const 0))
+                    (local.set $length)
+                    (local.set $offset)
+                    (br_if $code (local.tee $error))
+
+                    (local.set $error
+                        (call $set_storage_binary
+                            (i32.const 5) ;; Key offset
+                            (i32.const 6) ;; Key length
+                            (local.get $offset)
+                            (local.get $length)
+                        )
+                    )
+                )
+                (local.get $error)
+            )
+
+            (func (export "outside") (result i32)
+                (call $get_frame_caller (i32.const 1))
+                (drop)
+                (drop)
+            )
+
+            (data (i32.const 0) "depthcaller")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 11))
+        )
+        "#;
+
+        let env = test_env(caller);
+        deploy(&env, &CALLEE_ID, callee);
+
+        // Called by the transaction
+        env.call(&CALLEE_ID, "record", &[])
+            .assert_success()
+            .assert_write(&CALLEE_ID, b"depth", DataEntry::Integer(1))
+            .assert_write(&CALLEE_ID, b"caller", DataEntry::Binary(vec![]));
+
+        env.call(&CONTRACT_ID, "call_record", &[])
+            .assert_success()
+            .assert_write(&CALLEE_ID, b"depth", DataEntry::Integer(2))
+            .assert_write(
+                &CALLEE_ID,
+                b"caller",
+                DataEntry::Binary(CONTRACT_ID.to_vec()),
+            );
+
+        env.call(&CALLEE_ID, "outside", &[])
+            .assert_error(RuntimeError::FrameNotFound.as_i32());
+    }
+
+    #[test]
+    fn test_call_results() {
+        const CALLEE_ID: [u8; 32] = [2; 32];
+
+        let caller = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_call_results" (func $get_call_results (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func $call (param $offset i32) (param $length i32) (result i32)
+                (local $error i32) (local $results_offset i32) (local $results_length i32)
+                (block $code
+                    (local.set $error
+                        (call $call_contract (i32.const 0) (i32.const 32) (local.get $offset) (local.get $length))
+                    )
+                    (br_if $code (local.get $error))
+
+                    (call $get_call_results)
+                    (local.set $results_length)
+                    (local.set $results_offset)
+                    (br_if $code (local.tee $error))
+
+                    (local.set $error
+                        (call $set_storage_binary
+                            (i32.const 40) ;; Key offset
+                            (i32.const 7) ;; Key length
+                            (local.get $results_offset)
+                            (local.get $results_length)
+                        )
+                    )
+                )
+                (local.get $error)
+            )
+
+            (func (export "call_pair") (result i32)
+                (call $call (i32.const 32) (i32.const 4))
+            )
+
+            (func (export "call_fail") (result i32)
+                (call $call (i32.const 36) (i32.const 4))
+            )
+
+            (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 32) "pairfail")
+            (data (i32.const 40) "results")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 47))
+        )
+        "#;
+
+        let callee = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "pair") (result i32 i64 i32)
+                (i32.const 0)
+                (i64.const 42)
+                (i32.const 7)
+            )
+
+            (func (export "fail") (result i32 i64)
+                (i32.const 3)
+                (i64.const 42)
+            )
+
+            (func (export "value") (result i64)
+                (i64.const -1)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+
+        let env = test_env(caller);
+        deploy(&env, &CALLEE_ID, callee);
+
+        env.call(&CALLEE_ID, "pair", &[])
+            .assert_results(&[DataEntry::Integer(42), DataEntry::Integer(7)]);
+        // The first result is always the error code
+        env.call(&CALLEE_ID, "value", &[])
+            .assert_error(ExecutableError::SignatureMismatch(String::new()).as_i32());
+        env.call(&CALLEE_ID, "fail", &[]).assert_error(3);
+
+        let mut results = Params::new();
+        results.push(DataEntry::Integer(42));
+        results.push(DataEntry::Integer(7));
+
+        env.call(&CONTRACT_ID, "call_pair", &[])
+            .assert_success()
+            .assert_write(
+                &CONTRACT_ID,
+                b"results",
+                DataEntry::Binary(results.as_bytes()),
+            );
+
+        env.call(&CONTRACT_ID, "call_fail", &[]).assert_error(3);
+    }
+
+    #[test]
+    fn test_delegate_call() {
+        const LIBRARY_ID: [u8; 32] = [2; 32];
+
+        let proxy = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "call_payment" (func $call_payment (param i32 i32 i64) (result i32)))
+            (import "env1" "delegate_call" (func $delegate_call (param i32 i32 i32 i32 i32 i32) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "delegate_set") (result i32)
+                (call $delegate_call (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 3) (i32.const 40) (i32.const 13))
+            )
+
+            (func (export "delegate_self") (result i32)
+                (call $delegate_call (i32.const 64) (i32.const 32) (i32.const 32) (i32.const 3) (i32.const 40) (i32.const 13))
+            )
+
+            (func (export "delegate_pay") (result i32)
+                (local $error i32)
+                (block $code
+                    (local.set $error
+                        (call $call_payment (i32.const 0) (i32.const 0) (i64.const 1))
+                    )
+                    (br_if $code (local.get $error))
+
+                    (local.set $error
+                        (call $delegate_call (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 3) (i32.const 40) (i32.const 13))
+                    )
+                )
+                (local.get $error)
+            )
+
+            (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 32) "set")
+            ;; Params with the integer 7
+            (data (i32.const 40) "\00\01\00\00\00\00\00\00\00\00\00\00\07")
+            (data (i32.const 64) "\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01")
+            (data (i32.const 80) "\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01\01")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 96))
+        )
+        "#;
+
+        let library = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "set") (param $p0 i64) (result i32)
+                (call $set_storage_int
+                    (i32.const 0) ;; Key offset
+                    (i32.const 5) ;; Key length
+                    (local.get $p0)
+                )
+            )
+
+            (data (i32.const 0) "value")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 5))
+        )
+        "#;
+
+        let env = test_env(proxy);
+        deploy(&env, &LIBRARY_ID, library);
+
+        env.call(&LIBRARY_ID, "set", &[DataEntry::Integer(3)])
+            .assert_success()
+            .assert_write(&LIBRARY_ID, b"value", DataEntry::Integer(3));
+
+        // The library writes to the storage of the proxy
+        let execution = env.call(&CONTRACT_ID, "delegate_set", &[]);
+        execution
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"value", DataEntry::Integer(7));
+        assert_eq!(execution.write_set.len(), 1);
+        assert_eq!(
+            env.storage(&LIBRARY_ID, b"value"),
+            Some(DataEntry::Integer(3))
+        );
+
+        env.call(&CONTRACT_ID, "delegate_self", &[])
+            .assert_error(RuntimeError::InvalidDelegateCall.as_i32());
+        env.call(&CONTRACT_ID, "delegate_pay", &[])
+            .assert_error(RuntimeError::InvalidDelegateCall.as_i32());
+    }
+
+    #[test]
+    fn test_call_verification() {
+        const CALLEE_ID: [u8; 32] = [2; 32];
+
+        let caller = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "call_missing") (result i32)
+                (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 7))
+            )
+
+            (func (export "call_void") (result i32)
+                (call $call_contract (i32.const 0) (i32.const 32) (i32.const 39) (i32.const 4))
+            )
+
+            (func (export "call_add") (result i32)
+                (call $call_contract (i32.const 0) (i32.const 32) (i32.const 43) (i32.const 3))
+            )
+
+            (func (export "call_value") (result i32)
+                (call $call_contract (i32.const 0) (i32.const 32) (i32.const 46) (i32.const 5))
+            )
+
+            (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 32) "missingvoidaddvalue")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 51))
+        )
+        "#;
+
+        let callee = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "void"))
+
+            (func (export "add") (param $value i64) (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "value") (result i64 i32)
+                (i64.const 42)
+                (i32.const 0)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+
+        let env = test_env(caller);
+        deploy(&env, &CALLEE_ID, callee);
+
+        env.call(&CONTRACT_ID, "call_missing", &[])
+            .assert_error(ExecutableError::FuncNotFound.as_i32());
+        env.call(&CONTRACT_ID, "call_void", &[])
+            .assert_error(ExecutableError::SignatureMismatch(String::new()).as_i32());
+        env.call(&CONTRACT_ID, "call_add", &[])
+            .assert_error(ExecutableError::SignatureMismatch(String::new()).as_i32());
+        env.call(&CONTRACT_ID, "call_value", &[])
+            .assert_error(ExecutableError::SignatureMismatch(String::new()).as_i32());
+    }
+
+    #[test]
+    fn test_get_bytecode_hash() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_bytecode_hash" (func $get_bytecode_hash (param i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "hash") (param $length i64) (result i32)
+                (local $error i32) (local $offset i32) (local $length_hash i32)
+                (call $get_bytecode_hash
+                    (i32.const 0) ;; Contract ID offset
+                    (i32.wrap_i64 (local.get $length))
+                )
+                (local.set $length_hash)
+                (local.set $offset)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_binary
+                    (i32.const 32) ;; Key offset
+                    (i32.const 4) ;; Key length
+                    (local.get $offset)
+                    (local.get $length_hash)
+                )
+            )
+
+            (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 32) "hash")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 36))
+        )
+        "#;
+        // SHA-256 of the other module without the custom section
+        const OTHER_HASH: [u8; 32] = [
+            13, 6, 94, 76, 214, 123, 47, 215, 101, 207, 6, 231, 131, 246, 167, 130, 34, 187, 231,
+            36, 180, 177, 139, 24, 222, 24, 223, 65, 133, 226, 166, 101,
+        ];
+
+        let bytecode = wat::parse_str(wat).expect("Failed to parse WAT");
+        let mut other =
+            wat::parse_str(r#"(module (func (export "_constructor") (result i32) (i32.const 0)))"#)
+                .expect("Failed to parse WAT");
+        // Custom sections are stripped by the normalization
+        other.extend_from_slice(&[0, 5, 4, b'n', b'o', b't', b'e']);
+
+        let env = TestEnv::new();
+        env.deploy(&CONTRACT_ID, bytecode.clone());
+        env.deploy(&[2; 32], other.clone());
+
+        // The contract itself
+        env.call(&CONTRACT_ID, "hash", &[DataEntry::Integer(0)])
+            .assert_success()
+            .assert_write(
+                &CONTRACT_ID,
+                b"hash",
+                DataEntry::Binary(normalize(&bytecode).expect("Normalization failed").hash),
+            );
+
+        env.call(&CONTRACT_ID, "hash", &[DataEntry::Integer(32)])
+            .assert_success()
+            .assert_write(
+                &CONTRACT_ID,
+                b"hash",
+                DataEntry::Binary(OTHER_HASH.to_vec()),
+            );
     }
 }
@@ -1,71 +1,194 @@
-use crate::{error::RuntimeError, runtime::Runtime};
+use crate::{
+    error::{Error, Result, RuntimeError},
+    runtime::Runtime,
+};
 use std::{fmt::Display, str};
 use wasmi::Caller;
 
-pub fn parse_int(offset: u32, length: u32, mut caller: Caller<Runtime>) -> (i32, i64) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+pub fn parse_int(offset: u32, length: u32, mut caller: Caller<Runtime>) -> Result<i64> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    let string = match str::from_utf8(&memory[offset as usize..offset as usize + length as usize]) {
-        Ok(value) => value,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0),
-    };
+    let string = str::from_utf8(crate::env::read_memory(memory, offset, length)?)
+        .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
 
-    match string.parse::<i64>() {
-        Ok(value) => (0, value),
-        Err(_) => (RuntimeError::ParseError.as_i32(), 0),
-    }
+    string
+        .parse::<i64>()
+        .map_err(|_| Error::Runtime(RuntimeError::ParseError))
 }
 
-pub fn parse_bool(offset: u32, length: u32, mut caller: Caller<Runtime>) -> (i32, i32) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+pub fn parse_bool(offset: u32, length: u32, mut caller: Caller<Runtime>) -> Result<bool> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    let string = match str::from_utf8(&memory[offset as usize..offset as usize + length as usize]) {
-        Ok(value) => value,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0),
-    };
+    let string = str::from_utf8(crate::env::read_memory(memory, offset, length)?)
+        .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
 
-    match string.parse::<bool>() {
-        Ok(value) => (0, value as i32),
-        Err(_) => (RuntimeError::ParseError.as_i32(), 0),
-    }
+    string
+        .parse::<bool>()
+        .map_err(|_| Error::Runtime(RuntimeError::ParseError))
 }
 
-pub fn to_bytes(value: i64, mut caller: Caller<Runtime>) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+pub fn to_bytes(value: i64, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let result = value.to_be_bytes().to_vec();
     crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
-pub fn to_int(offset: u32, length: u32, mut caller: Caller<Runtime>) -> (i32, i64) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+pub fn to_int(offset: u32, length: u32, mut caller: Caller<Runtime>) -> Result<i64> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    let mut bytes = [0u8; 8];
-    bytes.copy_from_slice(&memory[offset as usize..offset as usize + length as usize]);
+    let bytes = <[u8; 8]>::try_from(crate::env::read_memory(memory, offset, length)?)
+        .map_err(|_| Error::Runtime(RuntimeError::ConvertingNumericTypes))?;
 
-    (0, i64::from_be_bytes(bytes))
+    Ok(i64::from_be_bytes(bytes))
 }
 
-pub fn to_string<T: Display>(value: T, mut caller: Caller<Runtime>) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+pub fn to_string<T: Display>(value: T, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let result = value.to_string().into_bytes();
     crate::env::write_memory(ctx, memory, offset_memory, result)
 }
+
+pub fn is_valid_utf8(offset: u32, length: u32, mut caller: Caller<Runtime>) -> Result<bool> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
+
+    let bytes = crate::env::read_memory(memory, offset, length)?;
+    Ok(str::from_utf8(bytes).is_ok())
+}
+
+/// Replacing invalid sequences of bytes with `U+FFFD`.
+pub fn to_valid_utf8(offset: u32, length: u32, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    // Each invalid byte is replaced with at most 3 bytes
+    crate::env::check_output(ctx, memory, length as usize * 3)?;
+
+    let bytes = crate::env::read_memory(memory, offset, length)?;
+    let result = String::from_utf8_lossy(bytes).into_owned().into_bytes();
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RuntimeError,
+        runtime::data_entry::DataEntry,
+        tests::{test_env, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_to_int() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "to_int" (func $to_int (param i32 i32) (result i32 i64)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "to_int") (param $length i64) (result i32 i64)
+                (call $to_int
+                    (i32.const 0)
+                    (i32.wrap_i64 (local.get $length))
+                )
+            )
+
+            (data (i32.const 0) "\00\00\00\00\00\00\00\2a\00")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 9))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        env.call(&CONTRACT_ID, "to_int", &[DataEntry::Integer(8)])
+            .assert_success()
+            .assert_results(&[DataEntry::Integer(42)]);
+
+        // Other lengths fail instead of panicking
+        for length in [7, 9] {
+            env.call(&CONTRACT_ID, "to_int", &[DataEntry::Integer(length)])
+                .assert_error(RuntimeError::ConvertingNumericTypes.as_i32());
+        }
+    }
+
+    #[test]
+    fn test_utf8_functions() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_string" (func $set_storage_string (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "is_valid_utf8" (func $is_valid_utf8 (param i32 i32) (result i32 i32)))
+            (import "env1" "to_valid_utf8" (func $to_valid_utf8 (param i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "is_valid") (param $offset i64) (param $length i64) (result i32 i32)
+                (call $is_valid_utf8
+                    (i32.wrap_i64 (local.get $offset))
+                    (i32.wrap_i64 (local.get $length))
+                )
+            )
+
+            (func (export "sanitize") (result i32)
+                (local $error i32) (local $offset i32) (local $length i32)
+                (call $to_valid_utf8
+                    (i32.const 0)
+                    (i32.const 8)
+                )
+                (local.set $length)
+                (local.set $offset)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_string
+                    (i32.const 8) ;; Key offset
+                    (i32.const 4) ;; Key length
+                    (local.get $offset)
+                    (local.get $length)
+                )
+            )
+
+            (data (i32.const 0) "name\ff\fe!!")
+            (data (i32.const 8) "name")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 12))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        env.call(
+            &CONTRACT_ID,
+            "is_valid",
+            &[DataEntry::Integer(0), DataEntry::Integer(4)],
+        )
+        .assert_success()
+        .assert_results(&[DataEntry::Integer(1)]);
+        env.call(
+            &CONTRACT_ID,
+            "is_valid",
+            &[DataEntry::Integer(0), DataEntry::Integer(8)],
+        )
+        .assert_success()
+        .assert_results(&[DataEntry::Integer(0)]);
+
+        env.call(&CONTRACT_ID, "sanitize", &[])
+            .assert_success()
+            .assert_write(
+                &CONTRACT_ID,
+                b"name",
+                DataEntry::String("name\u{FFFD}\u{FFFD}!!".as_bytes().to_vec()),
+            );
+    }
+}
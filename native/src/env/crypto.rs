@@ -1,4 +1,8 @@
-use crate::{error::RuntimeError, node::Node, runtime::Runtime};
+use crate::{
+    error::{Error, Result, RuntimeError},
+    node::Node,
+    runtime::Runtime,
+};
 use blake2::{digest::consts::U32, Blake2b, Digest};
 use sha2::Sha256;
 use sha3::Keccak256;
@@ -8,53 +12,40 @@ pub fn fast_hash(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
-    let bytes = &memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize];
+    let bytes = crate::env::read_memory(memory, offset_bytes, length_bytes)?;
 
-    match ctx.vm.fast_hash(bytes) {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => (error.as_i32(), 0, 0),
-    }
+    let result = ctx.vm.node.fast_hash(bytes)?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
 pub fn secure_hash(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
-    let bytes = &memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize];
+    let bytes = crate::env::read_memory(memory, offset_bytes, length_bytes)?;
 
-    match ctx.vm.secure_hash(bytes) {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => (error.as_i32(), 0, 0),
-    }
+    let result = ctx.vm.node.secure_hash(bytes)?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
 pub fn blake2b256(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let mut hasher: Blake2b<U32> = Blake2b::new();
-    hasher.update(&memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize]);
+    hasher.update(crate::env::read_memory(memory, offset_bytes, length_bytes)?);
 
     crate::env::write_memory(ctx, memory, offset_memory, hasher.finalize().to_vec())
 }
@@ -63,15 +54,12 @@ pub fn keccak256(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let mut hasher = Keccak256::new();
-    hasher.update(&memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize]);
+    hasher.update(crate::env::read_memory(memory, offset_bytes, length_bytes)?);
 
     crate::env::write_memory(ctx, memory, offset_memory, hasher.finalize().to_vec())
 }
@@ -80,15 +68,12 @@ pub fn sha256(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let mut hasher = Sha256::new();
-    hasher.update(&memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize]);
+    hasher.update(crate::env::read_memory(memory, offset_bytes, length_bytes)?);
 
     crate::env::write_memory(ctx, memory, offset_memory, hasher.finalize().to_vec())
 }
@@ -101,21 +86,311 @@ pub fn sig_verify(
     offset_public_key: u32,
     length_public_key: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
+) -> Result<bool> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+
+    let message = crate::env::read_memory(memory, offset_message, length_message)?;
+    let signature = crate::env::read_memory(memory, offset_signature, length_signature)?;
+    let public_key = crate::env::read_memory(memory, offset_public_key, length_public_key)?;
+
+    ctx.vm.node.sig_verify(message, signature, public_key)
+}
+
+pub fn get_random(
+    offset_seed: u32,
+    length_seed: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    // Blocks formed before the activation of VRF are seeded by their signature
+    let mut source = ctx.vm.node.block("vrf".as_bytes())?;
+    if source.is_empty() {
+        source = ctx.vm.node.block("signature".as_bytes())?;
+    }
+    let tx_id = ctx.vm.node.tx("id".as_bytes())?;
+    // The node returns empty values if the block isn't formed yet
+    if source.is_empty() || tx_id.is_empty() {
+        return Err(Error::Runtime(RuntimeError::RandomnessUnavailable));
+    }
+
+    // The value is unpredictable before the block is formed,
+    // but identical on every node that executes the transaction
+    let seed = crate::env::read_memory(memory, offset_seed, length_seed)?;
+    let result = ctx
+        .vm
+        .node
+        .fast_hash(&[&source[..], &tx_id[..], seed].concat())?;
+
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+/// Generating a 32-byte identifier unique within the transaction.
+pub fn unique_id(mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let tx_id = ctx.vm.node.tx("id".as_bytes())?;
+    let contract_id = ctx.vm.top_frame().contract_id();
+    let counter = ctx.vm.next_unique_id();
+
+    // Transaction identifiers are unique, so the identifier is unique on the chain
+    // and identical on every node that executes the transaction
+    let mut hasher: Blake2b<U32> = Blake2b::new();
+    hasher.update(tx_id);
+    hasher.update(contract_id);
+    hasher.update(counter.to_be_bytes());
+
+    crate::env::write_memory(ctx, memory, offset_memory, hasher.finalize().to_vec())
+}
+
+/// Version of the addresses derived from public keys.
+const ADDRESS_VERSION: u8 = 1;
+
+/// Length of the public keys of the accounts.
+const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Length of the public key hash in an address.
+const ADDRESS_HASH_LENGTH: usize = 20;
+
+/// Length of the checksum of an address.
+const ADDRESS_CHECKSUM_LENGTH: usize = 4;
+
+/// Deriving the address of the public key: version, chain id,
+/// the beginning of the secure hash of the key and the checksum of the previous bytes.
+fn address(node: &dyn Node, chain_id: u8, public_key: &[u8]) -> Result<Vec<u8>> {
+    if public_key.len() != PUBLIC_KEY_LENGTH {
+        return Err(Error::Runtime(RuntimeError::InvalidPublicKey));
+    }
+
+    let mut address = vec![ADDRESS_VERSION, chain_id];
+    address.extend_from_slice(&node.secure_hash(public_key)?[..ADDRESS_HASH_LENGTH]);
+
+    let checksum = node.secure_hash(&address)?;
+    address.extend_from_slice(&checksum[..ADDRESS_CHECKSUM_LENGTH]);
+    Ok(address)
+}
+
+pub fn address_from_public_key(
+    offset_public_key: u32,
+    length_public_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let public_key = crate::env::read_memory(memory, offset_public_key, length_public_key)?;
+    let chain_id = ctx.vm.node.get_chain_id()? as u8;
+
+    let result = address(ctx.vm.node.as_ref(), chain_id, public_key)?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RuntimeError,
+        node::Node,
+        runtime::data_entry::DataEntry,
+        tests::{test_env, CONTRACT_ID},
     };
+    use base58::FromBase58;
+
+    #[test]
+    fn test_address_from_public_key() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "address_from_public_key" (func $address_from_public_key (param i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "address") (param $length i64) (result i32)
+                (local $error i32) (local $offset i32) (local $length_address i32)
+                (call $address_from_public_key
+                    (i32.const 0) ;; Public key offset
+                    (i32.wrap_i64 (local.get $length)) ;; Public key length
+                )
+                (local.set $length_address)
+                (local.set $offset)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_binary
+                    (i32.const 33) ;; Key offset
+                    (i32.const 7) ;; Key length
+                    (local.get $offset)
+                    (local.get $length_address)
+                )
+            )
+
+            (data (i32.const 0) "0123456789abcdef0123456789abcdef0")
+            (data (i32.const 33) "address")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 40))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        // Waves address of the key on the chain `V`
+        let address = "3Ni3Uy9pRDFiadD6bze8bu1SBw6NwNcHVjK"
+            .from_base58()
+            .expect("Base58 decoding failed");
+
+        env.call(&CONTRACT_ID, "address", &[DataEntry::Integer(32)])
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"address", DataEntry::Binary(address));
+
+        for length in [31, 33] {
+            env.call(&CONTRACT_ID, "address", &[DataEntry::Integer(length)])
+                .assert_error(RuntimeError::InvalidPublicKey.as_i32());
+        }
+    }
+
+    #[test]
+    fn test_unique_id() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "unique_id" (func $unique_id (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func $store (param $key i32) (result i32)
+                (local $error i32) (local $offset i32) (local $length i32)
+                (call $unique_id)
+                (local.set $length)
+                (local.set $offset)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_binary
+                    (local.get $key)
+                    (i32.const 1) ;; Key length
+                    (local.get $offset)
+                    (local.get $length)
+                )
+            )
+
+            (func (export "mint") (result i32)
+                (local $error i32)
+                (local.tee $error (call $store (i32.const 0)))
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $store (i32.const 1))
+            )
+
+            (data (i32.const 0) "ab")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 2))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        env.call(&CONTRACT_ID, "mint", &[]).assert_success();
+        let first = env.storage(&CONTRACT_ID, b"a");
+        let second = env.storage(&CONTRACT_ID, b"b");
+        assert!(matches!(&first, Some(DataEntry::Binary(id)) if id.len() == 32));
+        assert_ne!(first, second);
+
+        // The same transaction generates the same identifiers
+        env.call(&CONTRACT_ID, "mint", &[]).assert_success();
+        assert_eq!(env.storage(&CONTRACT_ID, b"a"), first);
+        assert_eq!(env.storage(&CONTRACT_ID, b"b"), second);
+    }
+
+    #[test]
+    fn test_get_random() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_random" (func $get_random (param i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "random") (param $nonce i64) (result i32)
+                (local $error i32) (local $offset i32) (local $length i32)
+                (call $get_random
+                    (i32.wrap_i64 (local.get $nonce)) ;; Nonce offset
+                    (i32.const 1) ;; Nonce length
+                )
+                (local.set $length)
+                (local.set $offset)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_binary
+                    (i32.const 2) ;; Key offset
+                    (i32.const 6) ;; Key length
+                    (local.get $offset)
+                    (local.get $length)
+                )
+            )
+
+            (data (i32.const 0) "ab")
+            (data (i32.const 2) "random")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 8))
+        )
+        "#;
+
+        let env = test_env(wat);
+        let vrf = env.mock().block(b"vrf").expect("Block field not found");
+        let tx_id = env.mock().tx(b"id").expect("Transaction field not found");
+        let expected = env
+            .mock()
+            .fast_hash(&[&vrf[..], &tx_id[..], b"a"].concat())
+            .expect("Hashing failed");
+
+        env.call(&CONTRACT_ID, "random", &[DataEntry::Integer(0)])
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"random", DataEntry::Binary(expected.clone()));
 
-    let message =
-        &memory[offset_message as usize..offset_message as usize + length_message as usize];
-    let signature =
-        &memory[offset_signature as usize..offset_signature as usize + length_signature as usize];
-    let public_key = &memory
-        [offset_public_key as usize..offset_public_key as usize + length_public_key as usize];
+        // The same nonce gives the same value, a different one doesn't
+        env.call(&CONTRACT_ID, "random", &[DataEntry::Integer(0)])
+            .assert_success();
+        assert_eq!(
+            env.storage(&CONTRACT_ID, b"random"),
+            Some(DataEntry::Binary(expected.clone()))
+        );
+        env.call(&CONTRACT_ID, "random", &[DataEntry::Integer(1)])
+            .assert_success();
+        assert_ne!(
+            env.storage(&CONTRACT_ID, b"random"),
+            Some(DataEntry::Binary(expected))
+        );
 
-    match ctx.vm.sig_verify(message, signature, public_key) {
-        Ok(result) => (0, result as i32),
-        Err(error) => (error.as_i32(), 0),
+        // Without VRF the value is seeded by the signature of the block
+        env.mock().set_vrf(&[]);
+        let signature = env
+            .mock()
+            .block(b"signature")
+            .expect("Block field not found");
+        let expected = env
+            .mock()
+            .fast_hash(&[&signature[..], &tx_id[..], b"a"].concat())
+            .expect("Hashing failed");
+        env.call(&CONTRACT_ID, "random", &[DataEntry::Integer(0)])
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"random", DataEntry::Binary(expected));
     }
 }
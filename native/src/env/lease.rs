@@ -1,5 +1,7 @@
-use crate::{error::RuntimeError, node::Node, runtime::Runtime};
-use log::error;
+use crate::{
+    error::{Error, Result, RuntimeError},
+    runtime::Runtime,
+};
 use wasmi::Caller;
 
 pub fn lease(
@@ -8,53 +10,191 @@ pub fn lease(
     version: u32,
     amount: i64,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Lease")?;
     let offset_memory = ctx.heap_base() as usize;
 
     let contract_id = ctx.vm.top_frame().contract_id();
-    let bytes =
-        &memory[offset_recipient as usize..offset_recipient as usize + length_recipient as usize];
+    ctx.vm.check_paused(&contract_id)?;
+    let bytes = crate::env::read_memory(memory, offset_recipient, length_recipient)?;
 
-    let asset_holder = match crate::env::get_asset_holder(ctx, 0, version, bytes.to_vec()) {
-        Ok(bytes) => bytes,
-        Err(error) => return (error.as_i32(), 0, 0),
-    };
+    let asset_holder = crate::env::get_asset_holder(ctx, 0, version, bytes.to_vec())?;
 
-    match ctx
+    let result = ctx
         .vm
-        .lease(contract_id.as_slice(), asset_holder.as_slice(), amount)
-    {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0, 0)
-        }
+        .node
+        .lease(contract_id.as_slice(), asset_holder.as_slice(), amount)?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+/// Getting the sender, the recipient, the amount and the active flag of the lease.
+pub fn get_lease_info(
+    offset_lease_id: u32,
+    length_lease_id: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<(u32, u32, u32, u32, i64, bool)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+
+    let lease_id = crate::env::read_memory(memory, offset_lease_id, length_lease_id)?;
+    let bytes = ctx.vm.node.get_lease_info(lease_id)?;
+
+    let invalid = || Error::Runtime(RuntimeError::InvalidResult("Invalid lease".into()));
+    let (&active, bytes) = bytes.split_first().ok_or_else(invalid)?;
+    let (amount, bytes) = bytes.split_at(bytes.len().min(8));
+    let amount = <[u8; 8]>::try_from(amount)
+        .map(i64::from_be_bytes)
+        .map_err(|_| invalid())?;
+    let (&length_sender, holders) = bytes.split_first().ok_or_else(invalid)?;
+    if holders.len() < length_sender as usize {
+        return Err(invalid());
     }
+
+    // The sender and the recipient are written one after another
+    let (offset, length) = crate::env::write_memory(ctx, memory, offset_memory, holders.to_vec())?;
+    let length_sender = length_sender as u32;
+    Ok((
+        offset,
+        length_sender,
+        offset + length_sender,
+        length - length_sender,
+        amount,
+        active != 0,
+    ))
 }
 
 pub fn cancel_lease(
     offset_lease_id: u32,
     length_lease_id: u32,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Lease cancellation")?;
 
     let contract_id = ctx.vm.top_frame().contract_id();
-    let lease_id =
-        &memory[offset_lease_id as usize..offset_lease_id as usize + length_lease_id as usize];
-
-    match ctx.vm.cancel_lease(contract_id.as_slice(), lease_id) {
-        Ok(_) => 0,
-        Err(error) => {
-            error!("{}", error);
-            error.as_i32()
-        }
+    ctx.vm.check_paused(&contract_id)?;
+    let lease_id = crate::env::read_memory(memory, offset_lease_id, length_lease_id)?;
+
+    ctx.vm.node.cancel_lease(contract_id.as_slice(), lease_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtime::data_entry::DataEntry,
+        tests::{test_env, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_get_lease_info() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env0" "lease_address" (func $lease_address (param i32 i32 i64) (result i32 i32 i32)))
+            (import "env0" "cancel_lease" (func $cancel_lease (param i32 i32) (result i32)))
+            (import "env1" "get_lease_info" (func $get_lease_info (param i32 i32) (result i32 i32 i32 i32 i32 i64 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            ;; Stores the sender and the recipient, returns the amount and the active flag
+            (func $info (param $offset_id i32) (param $length_id i32) (result i32 i64 i32)
+                (local $error i32) (local $amount i64) (local $active i32)
+                (local $offset_sender i32) (local $length_sender i32)
+                (local $offset_recipient i32) (local $length_recipient i32)
+                (call $get_lease_info
+                    (local.get $offset_id)
+                    (local.get $length_id)
+                )
+                (local.set $active)
+                (local.set $amount)
+                (local.set $length_recipient)
+                (local.set $offset_recipient)
+                (local.set $length_sender)
+                (local.set $offset_sender)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error) (i64.const 0) (i32.const 0)))
+                )
+                (drop
+                    (call $set_storage_binary
+                        (i32.const 26) ;; Key offset
+                        (i32.const 6) ;; Key length
+                        (local.get $offset_sender)
+                        (local.get $length_sender)
+                    )
+                )
+                (drop
+                    (call $set_storage_binary
+                        (i32.const 32) ;; Key offset
+                        (i32.const 9) ;; Key length
+                        (local.get $offset_recipient)
+                        (local.get $length_recipient)
+                    )
+                )
+                (i32.const 0)
+                (local.get $amount)
+                (local.get $active)
+            )
+
+            (func (export "lease") (result i32 i64 i32 i32)
+                (local $error i32) (local $offset_id i32) (local $length_id i32)
+                (local $amount i64) (local $active i32) (local $active_cancelled i32)
+                (call $lease_address
+                    (i32.const 0) ;; Address offset
+                    (i32.const 26) ;; Address length
+                    (i64.const 100)
+                )
+                (local.set $length_id)
+                (local.set $offset_id)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error) (i64.const 0) (i32.const 0) (i32.const 0)))
+                )
+                (call $info (local.get $offset_id) (local.get $length_id))
+                (local.set $active)
+                (local.set $amount)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error) (i64.const 0) (i32.const 0) (i32.const 0)))
+                )
+                (drop (call $cancel_lease (local.get $offset_id) (local.get $length_id)))
+                (call $info (local.get $offset_id) (local.get $length_id))
+                (local.set $active_cancelled)
+                (drop)
+                (local.get $amount)
+                (local.get $active)
+                (local.get $active_cancelled)
+            )
+
+            (data (i32.const 0) "\01\56\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+            (data (i32.const 26) "sender")
+            (data (i32.const 32) "recipient")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 41))
+        )
+        "#;
+        let address = [&[1, 0x56][..], &[2; 24]].concat();
+
+        let env = test_env(wat);
+        env.set_balance(&[], &CONTRACT_ID, 100);
+
+        // Cancelled leases stay available as inactive
+        env.call(&CONTRACT_ID, "lease", &[])
+            .assert_results(&[
+                DataEntry::Integer(100),
+                DataEntry::Integer(1),
+                DataEntry::Integer(0),
+            ])
+            .assert_write(
+                &CONTRACT_ID,
+                b"sender",
+                DataEntry::Binary(CONTRACT_ID.to_vec()),
+            )
+            .assert_write(&CONTRACT_ID, b"recipient", DataEntry::Binary(address));
     }
 }
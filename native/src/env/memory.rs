@@ -1,4 +1,7 @@
-use crate::{error::RuntimeError, runtime::Runtime};
+use crate::{
+    error::{Error, Result, RuntimeError},
+    runtime::Runtime,
+};
 use std::str;
 use wasmi::Caller;
 
@@ -8,16 +11,61 @@ pub fn binary_equals(
     offset_right: u32,
     length_right: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i32) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<bool> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
+
+    let left = crate::env::read_memory(memory, offset_left, length_left)?;
+    let right = crate::env::read_memory(memory, offset_right, length_right)?;
+
+    Ok(left == right)
+}
+
+/// Comparing the bytes lexicographically, returns `-1`, `0` or `1`.
+pub fn mem_compare(
+    offset_left: u32,
+    length_left: u32,
+    offset_right: u32,
+    length_right: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<i32> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
+
+    let left = crate::env::read_memory(memory, offset_left, length_left)?;
+    let right = crate::env::read_memory(memory, offset_right, length_right)?;
 
-    let left = &memory[offset_left as usize..offset_left as usize + length_left as usize];
-    let right = &memory[offset_right as usize..offset_right as usize + length_right as usize];
+    Ok(left.cmp(right) as i32)
+}
+
+/// Copying the bytes within linear memory, the ranges may overlap.
+pub fn mem_copy(
+    offset_destination: u32,
+    offset_source: u32,
+    length: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<()> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    (0, (left == right) as i32)
+    // Both ranges must be within the memory
+    crate::env::read_memory(memory, offset_source, length)?;
+    crate::env::read_memory(memory, offset_destination, length)?;
+
+    let start = offset_source as usize;
+    memory.copy_within(start..start + length as usize, offset_destination as usize);
+
+    Ok(())
+}
+
+/// Filling the bytes with the low byte of the value.
+pub fn mem_fill(offset: u32, length: u32, value: i32, mut caller: Caller<Runtime>) -> Result<()> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
+
+    let start = offset as usize;
+    memory
+        .get_mut(start..start + length as usize)
+        .ok_or(Error::Runtime(RuntimeError::MemoryOutOfBounds))?
+        .fill(value as u8);
+
+    Ok(())
 }
 
 pub fn string_equals(
@@ -26,27 +74,16 @@ pub fn string_equals(
     offset_right: u32,
     length_right: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i32) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<bool> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    let left = match str::from_utf8(
-        &memory[offset_left as usize..offset_left as usize + length_left as usize],
-    ) {
-        Ok(string) => string,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0),
-    };
+    let left = str::from_utf8(crate::env::read_memory(memory, offset_left, length_left)?)
+        .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
 
-    let right = match str::from_utf8(
-        &memory[offset_right as usize..offset_right as usize + length_right as usize],
-    ) {
-        Ok(string) => string,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0),
-    };
+    let right = str::from_utf8(crate::env::read_memory(memory, offset_right, length_right)?)
+        .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
 
-    (0, (left == right) as i32)
+    Ok(left == right)
 }
 
 pub fn join(
@@ -55,15 +92,12 @@ pub fn join(
     offset_right: u32,
     length_right: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
-    let left = &memory[offset_left as usize..offset_left as usize + length_left as usize];
-    let right = &memory[offset_right as usize..offset_right as usize + length_right as usize];
+    let left = crate::env::read_memory(memory, offset_left, length_left)?;
+    let right = crate::env::read_memory(memory, offset_right, length_right)?;
 
     let mut result = vec![];
     result.extend_from_slice(left);
@@ -78,32 +112,25 @@ pub fn contains(
     offset_subbytes: u32,
     length_subbytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i32) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<bool> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    let bytes = &memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize];
-    let subbytes =
-        &memory[offset_subbytes as usize..offset_subbytes as usize + length_subbytes as usize];
+    let bytes = crate::env::read_memory(memory, offset_bytes, length_bytes)?;
+    let subbytes = crate::env::read_memory(memory, offset_subbytes, length_subbytes)?;
 
-    let result = bytes.windows(subbytes.len()).any(|item| item == subbytes);
-    (0, result as i32)
+    Ok(bytes.windows(subbytes.len()).any(|item| item == subbytes))
 }
 
-pub fn drop(offset_bytes: u32, length_bytes: u32, n: i64) -> (i32, u32, u32) {
-    match u32::try_from(n) {
-        Ok(value) => (0, offset_bytes + value, length_bytes - value),
-        Err(_) => (RuntimeError::ConvertingNumericTypes.as_i32(), 0, 0),
-    }
+pub fn drop(offset_bytes: u32, length_bytes: u32, n: i64) -> Result<(u32, u32)> {
+    let value =
+        u32::try_from(n).map_err(|_| Error::Runtime(RuntimeError::ConvertingNumericTypes))?;
+    Ok((offset_bytes + value, length_bytes - value))
 }
 
-pub fn drop_right(offset_bytes: u32, length_bytes: u32, n: i64) -> (i32, u32, u32) {
-    match u32::try_from(n) {
-        Ok(value) => (0, offset_bytes, length_bytes - value),
-        Err(_) => (RuntimeError::ConvertingNumericTypes.as_i32(), 0, 0),
-    }
+pub fn drop_right(offset_bytes: u32, length_bytes: u32, n: i64) -> Result<(u32, u32)> {
+    let value =
+        u32::try_from(n).map_err(|_| Error::Runtime(RuntimeError::ConvertingNumericTypes))?;
+    Ok((offset_bytes, length_bytes - value))
 }
 
 pub fn index_of(
@@ -113,25 +140,22 @@ pub fn index_of(
     offset_substring: u32,
     length_substring: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i64) {
-    let (memory, _) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<i64> {
+    let (memory, _) = crate::env::memory(&mut caller)?;
 
-    let string = match str::from_utf8(
-        &memory[offset_string as usize..offset_string as usize + length_string as usize],
-    ) {
-        Ok(value) => value,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0),
-    };
+    let string = str::from_utf8(crate::env::read_memory(
+        memory,
+        offset_string,
+        length_string,
+    )?)
+    .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
 
-    let substring = match str::from_utf8(
-        &memory[offset_substring as usize..offset_substring as usize + length_substring as usize],
-    ) {
-        Ok(value) => value,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0),
-    };
+    let substring = str::from_utf8(crate::env::read_memory(
+        memory,
+        offset_substring,
+        length_substring,
+    )?)
+    .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
 
     let result = if is_last {
         string.rfind(substring)
@@ -140,21 +164,101 @@ pub fn index_of(
     };
 
     match result {
-        Some(index) => (0, index as i64),
-        None => (0, -1),
+        Some(index) => Ok(index as i64),
+        None => Ok(-1),
     }
 }
 
-pub fn take(offset_bytes: u32, _length_bytes: u32, n: i64) -> (i32, u32, u32) {
-    match u32::try_from(n) {
-        Ok(value) => (0, offset_bytes, value),
-        Err(_) => (RuntimeError::ConvertingNumericTypes.as_i32(), 0, 0),
-    }
+pub fn take(offset_bytes: u32, _length_bytes: u32, n: i64) -> Result<(u32, u32)> {
+    let value =
+        u32::try_from(n).map_err(|_| Error::Runtime(RuntimeError::ConvertingNumericTypes))?;
+    Ok((offset_bytes, value))
+}
+
+pub fn take_right(offset_bytes: u32, length_bytes: u32, n: i64) -> Result<(u32, u32)> {
+    let value =
+        u32::try_from(n).map_err(|_| Error::Runtime(RuntimeError::ConvertingNumericTypes))?;
+    Ok((offset_bytes + (length_bytes - value), value))
 }
 
-pub fn take_right(offset_bytes: u32, length_bytes: u32, n: i64) -> (i32, u32, u32) {
-    match u32::try_from(n) {
-        Ok(value) => (0, offset_bytes + (length_bytes - value), value),
-        Err(_) => (RuntimeError::ConvertingNumericTypes.as_i32(), 0, 0),
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RuntimeError,
+        runtime::data_entry::DataEntry,
+        tests::{test_env, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_memory_functions() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "mem_compare" (func $mem_compare (param i32 i32 i32 i32) (result i32 i32)))
+            (import "env1" "mem_copy" (func $mem_copy (param i32 i32 i32) (result i32)))
+            (import "env1" "mem_fill" (func $mem_fill (param i32 i32 i32) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "compare") (result i32 i32)
+                (call $mem_compare
+                    (i32.const 0) ;; "hello"
+                    (i32.const 5)
+                    (i32.const 6) ;; "world"
+                    (i32.const 5)
+                )
+            )
+
+            (func (export "copy") (result i32)
+                (local $error i32)
+                (block $code
+                    ;; Overlapping ranges
+                    (br_if $code
+                        (local.tee $error (call $mem_copy (i32.const 1) (i32.const 0) (i32.const 5)))
+                    )
+                    (br_if $code
+                        (local.tee $error (call $mem_fill (i32.const 6) (i32.const 3) (i32.const 33)))
+                    )
+                    (local.set $error
+                        (call $set_storage_binary
+                            (i32.const 11) ;; Key offset
+                            (i32.const 3) ;; Key length
+                            (i32.const 0) ;; Value offset
+                            (i32.const 9) ;; Value length
+                        )
+                    )
+                )
+                (local.get $error)
+            )
+
+            (func (export "fill_out_of_bounds") (result i32)
+                (call $mem_fill (i32.const 131070) (i32.const 10) (i32.const 0))
+            )
+
+            (data (i32.const 0) "hello world")
+            (data (i32.const 11) "key")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 14))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        env.call(&CONTRACT_ID, "compare", &[])
+            .assert_success()
+            .assert_results(&[DataEntry::Integer(-1)]);
+        env.call(&CONTRACT_ID, "copy", &[])
+            .assert_success()
+            .assert_write(
+                &CONTRACT_ID,
+                b"key",
+                DataEntry::Binary(b"hhello!!!".to_vec()),
+            );
+        env.call(&CONTRACT_ID, "fill_out_of_bounds", &[])
+            .assert_error(RuntimeError::MemoryOutOfBounds.as_i32());
     }
 }
@@ -0,0 +1,15 @@
+use crate::{error::Result, runtime::Runtime};
+use wasmi::Caller;
+
+pub fn check_permission(
+    offset_address: u32,
+    length_address: u32,
+    role_id: i32,
+    mut caller: Caller<Runtime>,
+) -> Result<bool> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+
+    let address = crate::env::read_memory(memory, offset_address, length_address)?;
+
+    ctx.vm.node.check_permission(address, role_id)
+}
@@ -1,6 +1,6 @@
 use crate::{
-    error::{ExecutableError, RuntimeError},
-    node::Node,
+    error::{Error, ExecutableError, Result, RuntimeError},
+    limits::ExecutionLimits,
     runtime::{data_entry::DataEntry, Runtime},
 };
 use wasmi::Caller;
@@ -11,81 +11,138 @@ pub fn contains_key(
     offset_key: u32,
     length_key: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, i32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<bool> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
     let address = if length_address != 0 {
-        memory[offset_address as usize..offset_address as usize + length_address as usize].to_vec()
+        crate::env::read_memory(memory, offset_address, length_address)?.to_vec()
     } else {
-        ctx.vm.top_frame().contract_id()
+        ctx.vm.top_frame().storage_id()
     };
 
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
 
-    match ctx.vm.contains_key(address.as_slice(), key) {
-        Ok(result) => (0, result as i32),
-        Err(error) => (error.as_i32(), 0),
-    }
+    ctx.vm.node.contains_key(address.as_slice(), key)
 }
 
-pub fn get_storage_int(
+/// Reading the entry of the storage, `None` if the key is absent.
+///
+/// The node returns empty bytes for absent keys. The strict getters fail with
+/// `FailedDeserialize` on them, the `_or` getters return the default instead.
+fn get_storage_entry(
     offset_address: u32,
     length_address: u32,
     offset_key: u32,
     length_key: u32,
-    mut caller: Caller<Runtime>,
-) -> (i32, i64) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+    caller: &mut Caller<Runtime>,
+) -> Result<Option<DataEntry>> {
+    let (memory, ctx) = crate::env::memory(caller)?;
 
     let address = if length_address != 0 {
-        memory[offset_address as usize..offset_address as usize + length_address as usize].to_vec()
+        crate::env::read_memory(memory, offset_address, length_address)?.to_vec()
     } else {
-        ctx.vm.top_frame().contract_id()
+        ctx.vm.top_frame().storage_id()
     };
 
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
 
-    match ctx.vm.get_storage(address.as_slice(), key) {
-        Ok(bytes) => match DataEntry::deserialize(bytes.as_slice()) {
-            Ok(DataEntry::Integer(integer)) => (0, integer),
-            _ => (ExecutableError::FailedDeserialize.as_i32(), 0),
-        },
-        Err(error) => (error.as_i32(), 0),
+    let bytes = ctx.vm.node.get_storage(address.as_slice(), key)?;
+    if bytes.is_empty() {
+        return Ok(None);
     }
+
+    DataEntry::deserialize(bytes.as_slice())
+        .map(Some)
+        .map_err(|_| Error::Executable(ExecutableError::FailedDeserialize))
 }
 
-pub fn get_storage_bool(
+/// Reading the integer entry, returns the default if the key is absent.
+pub fn get_storage_int_or(
     offset_address: u32,
     length_address: u32,
     offset_key: u32,
     length_key: u32,
+    default: i64,
     mut caller: Caller<Runtime>,
-) -> (i32, i32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0),
-    };
+) -> Result<i64> {
+    let entry = get_storage_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    match entry {
+        Some(DataEntry::Integer(integer)) => Ok(integer),
+        None => Ok(default),
+        _ => Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    }
+}
 
-    let address = if length_address != 0 {
-        memory[offset_address as usize..offset_address as usize + length_address as usize].to_vec()
-    } else {
-        ctx.vm.top_frame().contract_id()
-    };
+/// Reading the boolean entry, returns the default if the key is absent.
+pub fn get_storage_bool_or(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    default: i32,
+    mut caller: Caller<Runtime>,
+) -> Result<bool> {
+    let entry = get_storage_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    match entry {
+        Some(DataEntry::Boolean(boolean)) => Ok(boolean != 0),
+        None => Ok(default != 0),
+        _ => Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    }
+}
 
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
+pub fn get_storage_int(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<i64> {
+    let entry = get_storage_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    match entry {
+        Some(DataEntry::Integer(integer)) => Ok(integer),
+        _ => Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    }
+}
 
-    match ctx.vm.get_storage(address.as_slice(), key) {
-        Ok(bytes) => match DataEntry::deserialize(bytes.as_slice()) {
-            Ok(DataEntry::Boolean(boolean)) => (0, boolean),
-            _ => (ExecutableError::FailedDeserialize.as_i32(), 0),
-        },
-        Err(error) => (error.as_i32(), 0),
+pub fn get_storage_bool(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<bool> {
+    let entry = get_storage_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    match entry {
+        Some(DataEntry::Boolean(boolean)) => Ok(boolean != 0),
+        _ => Err(Error::Executable(ExecutableError::FailedDeserialize)),
     }
 }
 
@@ -95,31 +152,57 @@ pub fn get_storage_binary(
     offset_key: u32,
     length_key: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
+) -> Result<(u32, u32)> {
+    let entry = get_storage_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    let result = match entry {
+        Some(DataEntry::Binary(bytes)) => bytes,
+        _ => return Err(Error::Executable(ExecutableError::FailedDeserialize)),
     };
+
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+/// Reading the binary value into the buffer of the contract, returns the length of the value.
+/// The buffer is left unchanged if the value is longer than its capacity, so the contract
+/// must compare the length with the capacity before reading the buffer.
+pub fn get_storage_binary_into(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    offset_buffer: u32,
+    capacity: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<u32> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
     let address = if length_address != 0 {
-        memory[offset_address as usize..offset_address as usize + length_address as usize].to_vec()
+        crate::env::read_memory(memory, offset_address, length_address)?.to_vec()
     } else {
-        ctx.vm.top_frame().contract_id()
+        ctx.vm.top_frame().storage_id()
     };
 
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
-
-    match ctx.vm.get_storage(address.as_slice(), key) {
-        Ok(bytes) => {
-            let result = match DataEntry::deserialize(bytes.as_slice()) {
-                Ok(DataEntry::Binary(bytes)) => bytes,
-                _ => return (ExecutableError::FailedDeserialize.as_i32(), 0, 0),
-            };
-            crate::env::write_memory(ctx, memory, offset_memory, result)
-        }
-        Err(error) => (error.as_i32(), 0, 0),
-    }
+    let key = crate::env::read_memory(memory, offset_key, length_key)?.to_vec();
+
+    let start = offset_buffer as usize;
+    let buffer = memory
+        .get_mut(start..start + capacity as usize)
+        .ok_or(Error::Runtime(RuntimeError::MemoryOutOfBounds))?;
+
+    let length = ctx
+        .vm
+        .node
+        .get_storage_binary_into(address.as_slice(), key.as_slice(), buffer)?;
+    Ok(length as u32)
 }
 
 pub fn get_storage_string(
@@ -128,31 +211,30 @@ pub fn get_storage_string(
     offset_key: u32,
     length_key: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
+) -> Result<(u32, u32)> {
+    let entry = get_storage_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    let result = match entry {
+        Some(DataEntry::String(bytes)) => bytes,
+        _ => return Err(Error::Executable(ExecutableError::FailedDeserialize)),
     };
-    let offset_memory = ctx.heap_base() as usize;
 
-    let address = if length_address != 0 {
-        memory[offset_address as usize..offset_address as usize + length_address as usize].to_vec()
-    } else {
-        ctx.vm.top_frame().contract_id()
-    };
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
 
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
-
-    match ctx.vm.get_storage(address.as_slice(), key) {
-        Ok(bytes) => {
-            let result = match DataEntry::deserialize(bytes.as_slice()) {
-                Ok(DataEntry::String(bytes)) => bytes,
-                _ => return (ExecutableError::FailedDeserialize.as_i32(), 0, 0),
-            };
-            crate::env::write_memory(ctx, memory, offset_memory, result)
-        }
-        Err(error) => (error.as_i32(), 0, 0),
-    }
+/// Checking the key and the value of a written data entry against the limits of the execution.
+fn check_entry(ctx: &Runtime, key: &[u8], value: &[u8]) -> Result<()> {
+    let limits = ctx.vm.limits();
+    ExecutionLimits::check("Key length", key.len(), limits.key_length)?;
+    ExecutionLimits::check("Value size", value.len(), limits.value_size)
 }
 
 pub fn set_storage_int(
@@ -160,23 +242,19 @@ pub fn set_storage_int(
     length_key: u32,
     value: i64,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
-
-    let contract_id = ctx.vm.top_frame().contract_id();
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Storage write")?;
+
+    let contract_id = ctx.vm.top_frame().storage_id();
+    ctx.vm.check_paused(&contract_id)?;
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
+    check_entry(ctx, key, &[])?;
     let data_entry = DataEntry::Integer(value).serialize(Some(key));
 
-    match ctx
-        .vm
+    ctx.vm
+        .node
         .set_storage(contract_id.as_slice(), data_entry.as_slice())
-    {
-        Ok(_) => 0,
-        Err(error) => error.as_i32(),
-    }
 }
 
 pub fn set_storage_bool(
@@ -184,23 +262,19 @@ pub fn set_storage_bool(
     length_key: u32,
     value: i32,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
-
-    let contract_id = ctx.vm.top_frame().contract_id();
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Storage write")?;
+
+    let contract_id = ctx.vm.top_frame().storage_id();
+    ctx.vm.check_paused(&contract_id)?;
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
+    check_entry(ctx, key, &[])?;
     let data_entry = DataEntry::Boolean(value).serialize(Some(key));
 
-    match ctx
-        .vm
+    ctx.vm
+        .node
         .set_storage(contract_id.as_slice(), data_entry.as_slice())
-    {
-        Ok(_) => 0,
-        Err(error) => error.as_i32(),
-    }
 }
 
 pub fn set_storage_binary(
@@ -209,24 +283,20 @@ pub fn set_storage_binary(
     offset_value: u32,
     length_value: u32,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
-
-    let contract_id = ctx.vm.top_frame().contract_id();
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
-    let value = &memory[offset_value as usize..offset_value as usize + length_value as usize];
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Storage write")?;
+
+    let contract_id = ctx.vm.top_frame().storage_id();
+    ctx.vm.check_paused(&contract_id)?;
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
+    let value = crate::env::read_memory(memory, offset_value, length_value)?;
+    check_entry(ctx, key, value)?;
     let data_entry = DataEntry::Binary(value.to_vec()).serialize(Some(key));
 
-    match ctx
-        .vm
+    ctx.vm
+        .node
         .set_storage(contract_id.as_slice(), data_entry.as_slice())
-    {
-        Ok(_) => 0,
-        Err(error) => error.as_i32(),
-    }
 }
 
 pub fn set_storage_string(
@@ -235,22 +305,398 @@ pub fn set_storage_string(
     offset_value: u32,
     length_value: u32,
     mut caller: Caller<Runtime>,
-) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
-
-    let contract_id = ctx.vm.top_frame().contract_id();
-    let key = &memory[offset_key as usize..offset_key as usize + length_key as usize];
-    let value = &memory[offset_value as usize..offset_value as usize + length_value as usize];
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Storage write")?;
+
+    let contract_id = ctx.vm.top_frame().storage_id();
+    ctx.vm.check_paused(&contract_id)?;
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
+    let value = crate::env::read_memory(memory, offset_value, length_value)?;
+    check_entry(ctx, key, value)?;
     let data_entry = DataEntry::String(value.to_vec()).serialize(Some(key));
 
-    match ctx
-        .vm
+    ctx.vm
+        .node
         .set_storage(contract_id.as_slice(), data_entry.as_slice())
-    {
-        Ok(_) => 0,
-        Err(error) => error.as_i32(),
+}
+
+/// Removing the entry of the contract, returns `false` if the key is absent.
+/// Removing an existing entry refunds `ExecutionLimits::deletion_refund` of the fuel.
+pub fn delete_storage(
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<bool> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    ctx.mode.check_change("Storage write")?;
+
+    let contract_id = ctx.vm.top_frame().storage_id();
+    ctx.vm.check_paused(&contract_id)?;
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
+
+    let deleted = ctx.vm.node.delete_storage(contract_id.as_slice(), key)?;
+    if deleted {
+        let refund = ctx
+            .vm
+            .fuel_refund()
+            .saturating_add(ctx.vm.limits().deletion_refund);
+        ctx.vm.set_fuel_refund(refund);
+    }
+
+    Ok(deleted)
+}
+
+/// Reading the entry published by a data transaction of the account.
+fn get_account_data_entry(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    caller: &mut Caller<Runtime>,
+) -> Result<DataEntry> {
+    let (memory, ctx) = crate::env::memory(caller)?;
+
+    let address = crate::env::read_memory(memory, offset_address, length_address)?;
+    let key = crate::env::read_memory(memory, offset_key, length_key)?;
+
+    let bytes = ctx.vm.node.get_account_data(address, key)?;
+    DataEntry::deserialize(bytes.as_slice())
+        .map_err(|_| Error::Executable(ExecutableError::FailedDeserialize))
+}
+
+pub fn get_account_data_int(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<i64> {
+    let entry = get_account_data_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    match entry {
+        DataEntry::Integer(integer) => Ok(integer),
+        _ => Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    }
+}
+
+pub fn get_account_data_bool(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<bool> {
+    let entry = get_account_data_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    match entry {
+        DataEntry::Boolean(boolean) => Ok(boolean != 0),
+        _ => Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    }
+}
+
+pub fn get_account_data_binary(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<(u32, u32)> {
+    let entry = get_account_data_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    let result = match entry {
+        DataEntry::Binary(bytes) => bytes,
+        _ => return Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    };
+
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+pub fn get_account_data_string(
+    offset_address: u32,
+    length_address: u32,
+    offset_key: u32,
+    length_key: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<(u32, u32)> {
+    let entry = get_account_data_entry(
+        offset_address,
+        length_address,
+        offset_key,
+        length_key,
+        &mut caller,
+    )?;
+
+    let result = match entry {
+        DataEntry::String(bytes) => bytes,
+        _ => return Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    };
+
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
+    let offset_memory = ctx.heap_base() as usize;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_env, CONTRACT_ID};
+
+    #[test]
+    fn test_get_storage_binary_into() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_storage_binary_into" (func $get_storage_binary_into (param i32 i32 i32 i32 i32 i32) (result i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "copy") (param $capacity i64) (result i32 i32)
+                (local $error i32) (local $length i32)
+                (call $get_storage_binary_into
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 4) ;; Key length
+                    (i32.const 16) ;; Buffer offset
+                    (i32.wrap_i64 (local.get $capacity))
+                )
+                (local.set $length)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error) (i32.const 0)))
+                )
+                (if (i32.le_u (local.get $length) (i32.wrap_i64 (local.get $capacity)))
+                    (then
+                        (local.set $error
+                            (call $set_storage_binary
+                                (i32.const 4) ;; Key offset
+                                (i32.const 4) ;; Key length
+                                (i32.const 16) ;; Value offset
+                                (local.get $length)
+                            )
+                        )
+                    )
+                )
+                (local.get $error)
+                (local.get $length)
+            )
+
+            (func (export "peek") (result i32)
+                (local $error i32)
+                (call $get_storage_binary_into
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 4) ;; Key length
+                    (i32.const 16) ;; Buffer offset
+                    (i32.const 2) ;; Capacity
+                )
+                ;; The length isn't compared with the capacity
+                (drop)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_binary
+                    (i32.const 4) ;; Key offset
+                    (i32.const 4) ;; Key length
+                    (i32.const 16) ;; Value offset
+                    (i32.const 2) ;; Value length
+                )
+            )
+
+            (data (i32.const 0) "blob")
+            (data (i32.const 4) "copy")
+            (data (i32.const 16) "\ff\ff")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 64))
+        )
+        "#;
+
+        let env = test_env(wat);
+        env.mock().set_storage_entry(
+            &CONTRACT_ID,
+            b"blob",
+            DataEntry::Binary(vec![1, 2, 3, 4, 5]),
+        );
+
+        env.call(&CONTRACT_ID, "copy", &[DataEntry::Integer(8)])
+            .assert_results(&[DataEntry::Integer(5)])
+            .assert_write(
+                &CONTRACT_ID,
+                b"copy",
+                DataEntry::Binary(vec![1, 2, 3, 4, 5]),
+            );
+
+        // The length is returned if the value doesn't fit
+        let execution = env.call(&CONTRACT_ID, "copy", &[DataEntry::Integer(2)]);
+        execution.assert_results(&[DataEntry::Integer(5)]);
+        assert!(execution.write_set.is_empty());
+
+        // The buffer is left unchanged, the contract must check the length
+        env.call(&CONTRACT_ID, "peek", &[])
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"copy", DataEntry::Binary(vec![0xff, 0xff]));
+
+        env.mock()
+            .set_storage_entry(&CONTRACT_ID, b"blob", DataEntry::Integer(5));
+        env.call(&CONTRACT_ID, "copy", &[DataEntry::Integer(8)])
+            .assert_error(ExecutableError::FailedDeserialize.as_i32());
+    }
+
+    #[test]
+    fn test_storage_getters_with_defaults() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env1" "get_storage_int_or" (func $get_storage_int_or (param i32 i32 i32 i32 i64) (result i32 i64)))
+            (import "env1" "get_storage_bool_or" (func $get_storage_bool_or (param i32 i32 i32 i32 i32) (result i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "get_int") (result i32 i64)
+                (call $get_storage_int_or
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                    (i64.const 42)
+                )
+            )
+
+            (func (export "get_bool") (result i32 i32)
+                (call $get_storage_bool_or
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 7) ;; Key offset
+                    (i32.const 4) ;; Key length
+                    (i32.const 1)
+                )
+            )
+
+            (data (i32.const 0) "counter")
+            (data (i32.const 7) "flag")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 11))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        // Absent keys return the default
+        env.call(&CONTRACT_ID, "get_int", &[])
+            .assert_results(&[DataEntry::Integer(42)]);
+        env.call(&CONTRACT_ID, "get_bool", &[])
+            .assert_results(&[DataEntry::Integer(1)]);
+
+        env.mock()
+            .set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(7));
+        env.mock()
+            .set_storage_entry(&CONTRACT_ID, b"flag", DataEntry::Boolean(0));
+
+        env.call(&CONTRACT_ID, "get_int", &[])
+            .assert_results(&[DataEntry::Integer(7)]);
+        env.call(&CONTRACT_ID, "get_bool", &[])
+            .assert_results(&[DataEntry::Integer(0)]);
+
+        // Entries of other types aren't replaced by the default
+        env.mock()
+            .set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Boolean(1));
+        env.call(&CONTRACT_ID, "get_int", &[])
+            .assert_error(ExecutableError::FailedDeserialize.as_i32());
+    }
+
+    #[test]
+    fn test_account_data() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env1" "get_account_data_int" (func $get_account_data_int (param i32 i32 i32 i32) (result i32 i64)))
+            (import "env1" "get_account_data_string" (func $get_account_data_string (param i32 i32 i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "get_price") (result i32 i64)
+                (call $get_account_data_int
+                    (i32.const 0) ;; Address offset
+                    (i32.const 6) ;; Address length
+                    (i32.const 6) ;; Key offset
+                    (i32.const 5) ;; Key length
+                )
+            )
+
+            (func (export "get_label_length") (result i32 i32)
+                (local $length i32)
+                (call $get_account_data_string
+                    (i32.const 0) ;; Address offset
+                    (i32.const 6) ;; Address length
+                    (i32.const 11) ;; Key offset
+                    (i32.const 5) ;; Key length
+                )
+                (local.set $length)
+                (drop)
+                (local.get $length)
+            )
+
+            (data (i32.const 0) "oracle")
+            (data (i32.const 6) "price")
+            (data (i32.const 11) "label")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 16))
+        )
+        "#;
+
+        let env = test_env(wat);
+
+        // The entry isn't published yet
+        env.call(&CONTRACT_ID, "get_price", &[])
+            .assert_error(ExecutableError::FailedDeserialize.as_i32());
+
+        env.mock()
+            .set_account_data(b"oracle", b"price", DataEntry::Integer(1250));
+        env.mock()
+            .set_account_data(b"oracle", b"label", DataEntry::String(b"USD/WEST".to_vec()));
+
+        env.call(&CONTRACT_ID, "get_price", &[])
+            .assert_results(&[DataEntry::Integer(1250)]);
+        env.call(&CONTRACT_ID, "get_label_length", &[])
+            .assert_results(&[DataEntry::Integer(8)]);
+
+        // Storage of contracts isn't shared with the account data
+        env.mock()
+            .set_storage_entry(b"oracle", b"price", DataEntry::Integer(1));
+        env.call(&CONTRACT_ID, "get_price", &[])
+            .assert_results(&[DataEntry::Integer(1250)]);
     }
 }
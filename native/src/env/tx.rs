@@ -1,75 +1,132 @@
-use crate::{env::Field, error::RuntimeError, node::Node, runtime::Runtime};
-use log::error;
+use crate::{
+    env::Field,
+    error::{Error, Result, RuntimeError},
+    runtime::Runtime,
+};
 use wasmi::Caller;
 
-pub fn get_payments(caller: Caller<Runtime>) -> (i32, i64) {
+pub fn get_payments(caller: Caller<Runtime>) -> Result<i64> {
     let payment_id = caller.data().vm.top_frame().payment_id();
 
-    match caller.data().vm.get_tx_payments(payment_id.as_slice()) {
-        Ok(result) => (0, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0)
-        }
-    }
+    caller.data().vm.node.get_tx_payments(payment_id.as_slice())
 }
 
-pub fn get_payment_asset_id(number: i64, mut caller: Caller<Runtime>) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+pub fn get_payment_asset_id(number: i64, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let payment_id = ctx.vm.top_frame().payment_id();
 
-    match ctx
+    let result = ctx
         .vm
-        .get_tx_payment_asset_id(payment_id.as_slice(), number)
-    {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0, 0)
-        }
-    }
+        .node
+        .get_tx_payment_asset_id(payment_id.as_slice(), number)?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
-pub fn get_payment_amount(number: i64, caller: Caller<Runtime>) -> (i32, i64) {
+pub fn get_payment_amount(number: i64, caller: Caller<Runtime>) -> Result<i64> {
     let payment_id = caller.data().vm.top_frame().payment_id();
 
-    match caller
+    caller
         .data()
         .vm
+        .node
         .get_tx_payment_amount(payment_id.as_slice(), number)
-    {
-        Ok(result) => (0, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0)
-        }
-    }
 }
 
-pub fn tx(field: Field, mut caller: Caller<Runtime>) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+pub fn tx(field: Field, mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let field = match field {
         Field::String(name) => name.into_bytes(),
-        Field::Binary(offset, length) => {
-            memory[offset as usize..offset as usize + length as usize].to_vec()
-        }
+        Field::Binary(offset, length) => crate::env::read_memory(memory, offset, length)?.to_vec(),
     };
 
-    match ctx.vm.tx(field.as_slice()) {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(error) => {
-            error!("{}", error);
-            (error.as_i32(), 0, 0)
-        }
+    let result = ctx.vm.node.tx(field.as_slice())?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
+}
+
+pub fn get_tx_fee(caller: Caller<Runtime>) -> Result<i64> {
+    let bytes = caller.data().vm.node.tx(b"fee")?;
+    <[u8; 8]>::try_from(bytes.as_slice())
+        .map(i64::from_be_bytes)
+        .map_err(|_| Error::Runtime(RuntimeError::InvalidResult("Expected 8 bytes".into())))
+}
+
+/// Checking whether the fee is paid in a sponsored asset, i.e. by its issuer.
+pub fn is_tx_fee_sponsored(caller: Caller<Runtime>) -> Result<bool> {
+    let bytes = caller.data().vm.node.tx(b"feeSponsored")?;
+    match bytes.as_slice() {
+        [value] => Ok(*value != 0),
+        _ => Err(Error::Runtime(RuntimeError::InvalidResult(
+            "Expected 1 byte".into(),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtime::data_entry::DataEntry,
+        tests::{test_env, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_tx_fee() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "set_storage_binary" (func $set_storage_binary (param i32 i32 i32 i32) (result i32)))
+            (import "env1" "get_tx_fee" (func $get_tx_fee (result i32 i64)))
+            (import "env1" "get_tx_fee_asset_id" (func $get_tx_fee_asset_id (result i32 i32 i32)))
+            (import "env1" "is_tx_fee_sponsored" (func $is_tx_fee_sponsored (result i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "fee") (result i32 i64)
+                (call $get_tx_fee)
+            )
+
+            (func (export "sponsored") (result i32 i32)
+                (call $is_tx_fee_sponsored)
+            )
+
+            (func (export "asset") (result i32)
+                (local $error i32) (local $offset i32) (local $length i32)
+                (call $get_tx_fee_asset_id)
+                (local.set $length)
+                (local.set $offset)
+                (local.tee $error)
+                (if
+                    (then (return (local.get $error)))
+                )
+                (call $set_storage_binary
+                    (i32.const 0) ;; Key offset
+                    (i32.const 5) ;; Key length
+                    (local.get $offset)
+                    (local.get $length)
+                )
+            )
+
+            (data (i32.const 0) "asset")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 5))
+        )
+        "#;
+
+        let env = test_env(wat);
+        env.mock().set_tx_fee(1000, &[3; 32], true);
+
+        env.call(&CONTRACT_ID, "fee", &[])
+            .assert_results(&[DataEntry::Integer(1000)]);
+        env.call(&CONTRACT_ID, "sponsored", &[])
+            .assert_results(&[DataEntry::Integer(1)]);
+        env.call(&CONTRACT_ID, "asset", &[])
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"asset", DataEntry::Binary(vec![3; 32]));
     }
 }
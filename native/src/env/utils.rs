@@ -1,4 +1,7 @@
-use crate::{error::RuntimeError, node::Node, runtime::Runtime};
+use crate::{
+    error::{Error, Result, RuntimeError},
+    runtime::Runtime,
+};
 use base58::{FromBase58, ToBase58};
 use std::str;
 use wasmi::Caller;
@@ -7,38 +10,34 @@ pub fn base58(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
-    let value = match str::from_utf8(
-        &memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize],
-    ) {
-        Ok(string) => string,
-        Err(_) => return (RuntimeError::Utf8Error.as_i32(), 0, 0),
-    };
+    // Each character is decoded into at most one byte
+    crate::env::check_output(ctx, memory, length_bytes as usize)?;
 
-    match value.from_base58() {
-        Ok(result) => crate::env::write_memory(ctx, memory, offset_memory, result),
-        Err(_) => (RuntimeError::Base58Error.as_i32(), 0, 0),
-    }
+    let value = str::from_utf8(crate::env::read_memory(memory, offset_bytes, length_bytes)?)
+        .map_err(|_| Error::Runtime(RuntimeError::Utf8Error))?;
+
+    let result = value
+        .from_base58()
+        .map_err(|_| Error::Runtime(RuntimeError::Base58Error))?;
+    crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
 pub fn to_base58_string(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
-    let value = &memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize];
+    // Each byte is encoded into at most log(256) / log(58) < 1.38 characters
+    crate::env::check_output(ctx, memory, length_bytes as usize * 138 / 100 + 1)?;
+
+    let value = crate::env::read_memory(memory, offset_bytes, length_bytes)?;
 
     let result = value.to_base58().as_bytes().to_vec();
     crate::env::write_memory(ctx, memory, offset_memory, result)
@@ -48,25 +47,21 @@ pub fn to_le_bytes(
     offset_bytes: u32,
     length_bytes: u32,
     mut caller: Caller<Runtime>,
-) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
-    let mut result =
-        memory[offset_bytes as usize..offset_bytes as usize + length_bytes as usize].to_vec();
+    // The bytes are written in reverse order
+    crate::env::check_output(ctx, memory, length_bytes as usize)?;
+
+    let mut result = crate::env::read_memory(memory, offset_bytes, length_bytes)?.to_vec();
     result.reverse();
 
     crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
-pub fn caller(mut caller: Caller<Runtime>) -> (i32, u32, u32) {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return (RuntimeError::MemoryNotFound.as_i32(), 0, 0),
-    };
+pub fn caller(mut caller: Caller<Runtime>) -> Result<(u32, u32)> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
     let offset_memory = ctx.heap_base() as usize;
 
     let result = ctx.vm.get_caller_current_frame();
@@ -74,21 +69,133 @@ pub fn caller(mut caller: Caller<Runtime>) -> (i32, u32, u32) {
     crate::env::write_memory(ctx, memory, offset_memory, result)
 }
 
-pub fn require(offset_message: u32, length_message: u32, mut caller: Caller<Runtime>) -> i32 {
-    let (memory, ctx) = match caller.data().memory() {
-        Some(memory) => memory.data_and_store_mut(&mut caller),
-        None => return RuntimeError::MemoryNotFound.as_i32(),
-    };
+pub fn require(
+    offset_message: u32,
+    length_message: u32,
+    mut caller: Caller<Runtime>,
+) -> Result<()> {
+    let (memory, ctx) = crate::env::memory(&mut caller)?;
 
-    let message =
-        &memory[offset_message as usize..offset_message as usize + length_message as usize];
+    let message = crate::env::read_memory(memory, offset_message, length_message)?;
 
     if str::from_utf8(message).is_err() {
-        return RuntimeError::Utf8Error.as_i32();
+        return Err(Error::Runtime(RuntimeError::Utf8Error));
     }
 
-    match ctx.vm.require(message) {
-        Ok(_) => 0,
-        Err(error) => error.as_i32(),
+    ctx.vm.node.require(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RuntimeError,
+        runtime::data_entry::DataEntry,
+        testing::TestEnv,
+        tests::{deploy, CONTRACT_ID},
+    };
+
+    #[test]
+    fn test_base58_limits() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "base_58" (func $base_58 (param i32 i32) (result i32 i32 i32)))
+            (import "env0" "to_base_58_string" (func $to_base_58_string (param i32 i32) (result i32 i32 i32)))
+            (import "env0" "to_le_bytes" (func $to_le_bytes (param i32 i32) (result i32 i32 i32)))
+            (import "env1" "to_base_58_string" (func $to_base_58_string_v1 (param i32 i32) (result i32 i32 i32)))
+            (import "env1" "to_le_bytes" (func $to_le_bytes_v1 (param i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "encode") (param $length i64) (result i32)
+                (call $to_base_58_string
+                    (i32.const 0)
+                    (i32.wrap_i64 (local.get $length))
+                )
+                (drop)
+                (drop)
+            )
+
+            (func (export "encode_v1") (param $length i64) (result i32)
+                (call $to_base_58_string_v1
+                    (i32.const 0)
+                    (i32.wrap_i64 (local.get $length))
+                )
+                (drop)
+                (drop)
+            )
+
+            (func (export "decode") (param $length i64) (result i32)
+                (call $base_58
+                    (i32.const 0)
+                    (i32.wrap_i64 (local.get $length))
+                )
+                (drop)
+                (drop)
+            )
+
+            (func (export "reverse") (param $length i64) (result i32)
+                (call $to_le_bytes
+                    (i32.const 0)
+                    (i32.wrap_i64 (local.get $length))
+                )
+                (drop)
+                (drop)
+            )
+
+            (func (export "reverse_v1") (param $length i64) (result i32)
+                (call $to_le_bytes_v1
+                    (i32.const 0)
+                    (i32.wrap_i64 (local.get $length))
+                )
+                (drop)
+                (drop)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 1024))
+        )
+        "#;
+
+        let env = TestEnv::new().with_fuel_limit(10_000_000);
+        deploy(&env, &CONTRACT_ID, wat);
+
+        // The costs of `env0` are kept, fuel is charged for each byte of the input by `env1`
+        let short = env.call(&CONTRACT_ID, "encode", &[DataEntry::Integer(16)]);
+        let long = env.call(&CONTRACT_ID, "encode", &[DataEntry::Integer(64)]);
+        short.assert_success();
+        long.assert_success();
+        assert_eq!(long.fuel_consumed, short.fuel_consumed);
+
+        let short = env.call(&CONTRACT_ID, "encode_v1", &[DataEntry::Integer(16)]);
+        let long = env.call(&CONTRACT_ID, "encode_v1", &[DataEntry::Integer(64)]);
+        short.assert_success();
+        long.assert_success();
+        assert!(long.fuel_consumed >= short.fuel_consumed + 48 * 20);
+
+        // Results larger than the memory above the heap base are not computed
+        env.call(&CONTRACT_ID, "encode", &[DataEntry::Integer(100_000)])
+            .assert_error(RuntimeError::MemoryLimitExceeded.as_i32());
+        env.call(&CONTRACT_ID, "decode", &[DataEntry::Integer(131_072)])
+            .assert_error(RuntimeError::MemoryLimitExceeded.as_i32());
+
+        let short = env.call(&CONTRACT_ID, "reverse", &[DataEntry::Integer(16)]);
+        let long = env.call(&CONTRACT_ID, "reverse", &[DataEntry::Integer(64)]);
+        short.assert_success();
+        long.assert_success();
+        assert_eq!(long.fuel_consumed, short.fuel_consumed);
+
+        let short = env.call(&CONTRACT_ID, "reverse_v1", &[DataEntry::Integer(16)]);
+        let long = env.call(&CONTRACT_ID, "reverse_v1", &[DataEntry::Integer(64)]);
+        short.assert_success();
+        long.assert_success();
+        assert!(long.fuel_consumed >= short.fuel_consumed + 48 * 5);
+
+        env.call(&CONTRACT_ID, "reverse", &[DataEntry::Integer(131_072)])
+            .assert_error(RuntimeError::MemoryLimitExceeded.as_i32());
+        env.call(&CONTRACT_ID, "reverse_v1", &[DataEntry::Integer(131_072)])
+            .assert_error(RuntimeError::MemoryLimitExceeded.as_i32());
     }
 }
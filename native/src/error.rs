@@ -1,7 +1,12 @@
+use base58::ToBase58;
 use jni::sys::jint;
 
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
+/// Version of the table of error codes.
+/// Codes are never changed or reused, the version is increased when codes are added.
+pub const CODES_VERSION: u32 = 18;
+
 macro_rules! error {
     (
         enum $name:ident {
@@ -12,7 +17,7 @@ macro_rules! error {
             )+
         }
     ) => {
-        #[derive(Debug, PartialEq)]
+        #[derive(Clone, Debug, PartialEq)]
         pub enum $name {
             $($variant $( ($_type) )? ,)+
         }
@@ -29,7 +34,42 @@ macro_rules! error {
             }
         }
 
+        impl std::error::Error for $name {}
+
+        // Codes are checked to be unique at compile time
+        const _: () = {
+            let codes = [$($code),+];
+            let mut i = 0;
+            while i < codes.len() {
+                let mut j = i + 1;
+                while j < codes.len() {
+                    assert!(codes[i] != codes[j], "Duplicate error code");
+                    j += 1;
+                }
+                i += 1;
+            }
+        };
+
+        impl From<&$name> for i32 {
+            fn from(error: &$name) -> i32 {
+                error.as_i32()
+            }
+        }
+
+        /// Restoring the error from its code, the reason is left empty.
+        /// The unknown code is returned as the error.
+        impl TryFrom<i32> for $name {
+            type Error = i32;
+
+            fn try_from(code: i32) -> core::result::Result<Self, i32> {
+                Self::from_code(code, "").ok_or(code)
+            }
+        }
+
         impl $name {
+            /// Codes and names of all errors.
+            pub const CODES: &'static [(i32, &'static str)] = &[$(($code, stringify!($variant))),+];
+
             pub fn as_i32(&self) -> i32 {
                 match self {
                     $($name::$variant $( ($value) )? => $code,)+
@@ -41,12 +81,32 @@ macro_rules! error {
                     $($name::$variant $( ($value) )? => $code,)+
                 }
             }
+
+            /// Getting the reason of the error, if any.
+            #[allow(unreachable_code)]
+            pub fn reason(&self) -> Option<&str> {
+                match self {
+                    $($name::$variant $( ($value) )? => {
+                        $( return Some($value.as_str()); )?
+                        None
+                    },)+
+                }
+            }
+
+            /// Restoring the error from its code and reason.
+            pub fn from_code(code: i32, reason: &str) -> Option<Self> {
+                let _ = reason;
+                match code {
+                    $($code => Some($name::$variant $( ({ let $value = reason.to_string(); $value }) )?),)+
+                    _ => None,
+                }
+            }
         }
     }
 }
 
 /// Enumeration of errors possible as a result of VM operation.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Error {
     Executable(ExecutableError),
     Jvm(JvmError),
@@ -63,6 +123,25 @@ impl core::fmt::Display for Error {
     }
 }
 
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Executable(error) => Some(error),
+            Error::Jvm(error) => Some(error),
+            Error::Runtime(error) => Some(error),
+        }
+    }
+}
+
+// Allows host functions to trap with an error.
+impl wasmi::core::HostError for Error {}
+
+impl From<Error> for wasmi::Error {
+    fn from(error: Error) -> Self {
+        wasmi::Error::host(error)
+    }
+}
+
 impl Error {
     /// Converting an error to a numeric code of `jint` type.
     pub fn as_jint(&self) -> jint {
@@ -81,6 +160,85 @@ impl Error {
             Error::Runtime(error) => error.as_i32(),
         }
     }
+
+    /// Getting the reason of the error, if any.
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Error::Executable(error) => error.reason(),
+            Error::Jvm(error) => error.reason(),
+            Error::Runtime(error) => error.reason(),
+        }
+    }
+
+    /// Restoring the error from its code and reason.
+    pub fn from_code(code: i32, reason: &str) -> Option<Self> {
+        ExecutableError::from_code(code, reason)
+            .map(Error::Executable)
+            .or_else(|| JvmError::from_code(code, reason).map(Error::Jvm))
+            .or_else(|| RuntimeError::from_code(code, reason).map(Error::Runtime))
+    }
+}
+
+/// Place of the execution where an error occurred.
+#[derive(Debug, PartialEq)]
+pub struct Context {
+    /// ID of the contract on top of the call stack.
+    pub contract_id: Vec<u8>,
+    /// Name of the called contract function.
+    pub func_name: String,
+    /// Last host function called by the contract.
+    pub host_function: Option<&'static str>,
+    /// Index of the frame in the call stack.
+    pub frame: usize,
+}
+
+impl core::fmt::Display for Context {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Contract: {}. Function: {}. Frame: {}.",
+            self.contract_id.to_base58(),
+            self.func_name,
+            self.frame
+        )?;
+
+        if let Some(host_function) = self.host_function {
+            write!(f, " Host function: {}.", host_function)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An error together with the place of the execution where it occurred.
+#[derive(Debug, PartialEq)]
+pub struct ContextError {
+    pub error: Error,
+    pub context: Context,
+}
+
+impl core::fmt::Display for ContextError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} {}", self.error, self.context)
+    }
+}
+
+impl std::error::Error for ContextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl ContextError {
+    /// Converting an error to a numeric code of `jint` type.
+    pub fn as_jint(&self) -> jint {
+        self.error.as_jint()
+    }
+
+    /// Converting an error to a numeric code of `i32` type.
+    pub fn as_i32(&self) -> i32 {
+        self.error.as_i32()
+    }
 }
 
 // Enumeration of errors that may occur as a result of
@@ -132,6 +290,15 @@ error! {
         #[error("Fuel metering is disabled")]
         #[code(114)]
         FuelMeteringDisabled(_message: String),
+        #[error("Invalid host function")]
+        #[code(115)]
+        InvalidHostFunction(_message: String),
+        #[error("Signature of the function doesn't match the call")]
+        #[code(116)]
+        SignatureMismatch(_message: String),
+        #[error("Invalid configuration of the engine")]
+        #[code(117)]
+        InvalidEngineConfig(_message: String),
     }
 }
 
@@ -180,6 +347,18 @@ error! {
         #[error("Failed to receive boolean")]
         #[code(213)]
         ReceiveBoolean,
+        #[error("Failed to install the logger")]
+        #[code(214)]
+        SetLogger,
+        #[error("Session not found")]
+        #[code(215)]
+        SessionNotFound,
+        #[error("Couldn't create java object")]
+        #[code(216)]
+        NewObject,
+        #[error("Invalid argument passed by the node")]
+        #[code(217)]
+        InvalidArgument(_message: String),
     }
 }
 
@@ -213,6 +392,63 @@ error! {
         #[error("Value parsing error")]
         #[code(308)]
         ParseError,
+        #[error("Contract is paused")]
+        #[code(309)]
+        ContractPaused,
+        #[error("Host function is not activated")]
+        #[code(310)]
+        FeatureNotActivated(_feature: String),
+        #[error("Memory access out of bounds")]
+        #[code(311)]
+        MemoryOutOfBounds,
+        #[error("Frame not found")]
+        #[code(312)]
+        FrameNotFound,
+        #[error("Invalid payment")]
+        #[code(313)]
+        InvalidPayment,
+        #[error("Payment amount overflow")]
+        #[code(314)]
+        PaymentOverflow,
+        #[error("Too many payments")]
+        #[code(315)]
+        TooManyPayments,
+        #[error("Insufficient balance for payment")]
+        #[code(316)]
+        InsufficientBalance,
+        #[error("Invalid delegate call")]
+        #[code(317)]
+        InvalidDelegateCall,
+        #[error("Execution is cancelled")]
+        #[code(319)]
+        Cancelled,
+        #[error("Limit of the execution exceeded")]
+        #[code(320)]
+        LimitExceeded(_message: String),
+        #[error("Result doesn't fit into the memory above the heap base")]
+        #[code(321)]
+        MemoryLimitExceeded,
+        #[error("Block isn't available at this height")]
+        #[code(322)]
+        BlockNotFound,
+        #[error("Out of fuel")]
+        #[code(323)]
+        OutOfFuel(_message: String),
+        #[error("Stack height limit exceeded")]
+        #[code(324)]
+        StackOverflow,
+        #[error("State change is forbidden in the execution mode")]
+        #[code(325)]
+        ForbiddenInMode(_message: String),
+        #[error("Source of randomness isn't available")]
+        #[code(326)]
+        RandomnessUnavailable,
+        #[error("Time of the execution is out")]
+        #[code(327)]
+        Timeout,
+        #[error("Public key has an invalid length")]
+        #[code(328)]
+        InvalidPublicKey,
     }
 }
 
@@ -238,4 +474,142 @@ mod tests {
         assert_eq!(JvmError::JvmNotFound.as_i32(), 200);
         assert_eq!(RuntimeError::Exception(message.clone()).as_i32(), 300);
     }
+
+    #[test]
+    fn test_from_code() {
+        let error = Error::Runtime(RuntimeError::Exception(String::from("Error message")));
+        assert_eq!(error.reason(), Some("Error message"));
+        assert_eq!(Error::from_code(300, "Error message"), Some(error));
+
+        let error = Error::Jvm(JvmError::JvmNotFound);
+        assert_eq!(error.reason(), None);
+        assert_eq!(Error::from_code(200, ""), Some(error));
+
+        assert_eq!(Error::from_code(0, ""), None);
+    }
+
+    #[test]
+    fn test_context_error() {
+        let error = ContextError {
+            error: Error::Runtime(RuntimeError::MemoryNotFound),
+            context: Context {
+                contract_id: vec![1, 2, 3],
+                func_name: String::from("_constructor"),
+                host_function: Some("env0::get_balance"),
+                frame: 1,
+            },
+        };
+
+        assert_eq!(error.as_jint(), 301);
+        assert_eq!(
+            error.to_string(),
+            "Code: 301. Error: Failed receiving Memory. \
+            Contract: Ldp. Function: _constructor. Frame: 1. Host function: env0::get_balance."
+        );
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn test_codes_table() {
+        // Codes are frozen, a new variant doesn't compile until its code is added here
+        fn executable(error: &ExecutableError) -> i32 {
+            match error {
+                ExecutableError::InvalidBytecode(_) => 100,
+                ExecutableError::ConstructorNotFound => 101,
+                ExecutableError::MemoryError(_) => 102,
+                ExecutableError::MemoryLimits(_) => 103,
+                ExecutableError::LinkerError(_) => 104,
+                ExecutableError::InstantiateFailed(_) => 105,
+                ExecutableError::HeapBaseNotFound => 106,
+                ExecutableError::FuncNotFound => 107,
+                ExecutableError::InvalidNumArgs => 108,
+                ExecutableError::FailedParseFuncArgs(_) => 109,
+                ExecutableError::FailedDeserialize => 110,
+                ExecutableError::FailedExec(_) => 111,
+                ExecutableError::StackOverflow => 112,
+                ExecutableError::ModuleNotFound => 113,
+                ExecutableError::FuelMeteringDisabled(_) => 114,
+                ExecutableError::InvalidHostFunction(_) => 115,
+                ExecutableError::SignatureMismatch(_) => 116,
+                ExecutableError::InvalidEngineConfig(_) => 117,
+            }
+        }
+
+        fn jvm(error: &JvmError) -> i32 {
+            match error {
+                JvmError::JvmNotFound => 200,
+                JvmError::JvmCallbackNotFound => 201,
+                JvmError::AttachCurrentThread => 202,
+                JvmError::MethodCall(_) => 203,
+                JvmError::ByteArrayConversion => 204,
+                JvmError::GetJavaVM => 205,
+                JvmError::NewGlobalRef => 206,
+                JvmError::NewByteArray => 207,
+                JvmError::NewString => 208,
+                JvmError::ReceiveObject => 209,
+                JvmError::ReceiveByte => 210,
+                JvmError::ReceiveInt => 211,
+                JvmError::ReceiveLong => 212,
+                JvmError::ReceiveBoolean => 213,
+                JvmError::SetLogger => 214,
+                JvmError::SessionNotFound => 215,
+                JvmError::NewObject => 216,
+                JvmError::InvalidArgument(_) => 217,
+            }
+        }
+
+        fn runtime(error: &RuntimeError) -> i32 {
+            match error {
+                RuntimeError::Exception(_) => 300,
+                RuntimeError::MemoryNotFound => 301,
+                RuntimeError::Utf8Error => 302,
+                RuntimeError::InvalidResult(_) => 303,
+                RuntimeError::Base58Error => 304,
+                RuntimeError::ConvertingNumericTypes => 305,
+                RuntimeError::AssetHolderTypeNotFound => 306,
+                RuntimeError::AddressVersionNotFound => 307,
+                RuntimeError::ParseError => 308,
+                RuntimeError::ContractPaused => 309,
+                RuntimeError::FeatureNotActivated(_) => 310,
+                RuntimeError::MemoryOutOfBounds => 311,
+                RuntimeError::FrameNotFound => 312,
+                RuntimeError::InvalidPayment => 313,
+                RuntimeError::PaymentOverflow => 314,
+                RuntimeError::TooManyPayments => 315,
+                RuntimeError::InsufficientBalance => 316,
+                RuntimeError::InvalidDelegateCall => 317,
+                RuntimeError::Cancelled => 319,
+                RuntimeError::LimitExceeded(_) => 320,
+                RuntimeError::MemoryLimitExceeded => 321,
+                RuntimeError::BlockNotFound => 322,
+                RuntimeError::OutOfFuel(_) => 323,
+                RuntimeError::StackOverflow => 324,
+                RuntimeError::ForbiddenInMode(_) => 325,
+                RuntimeError::RandomnessUnavailable => 326,
+                RuntimeError::Timeout => 327,
+                RuntimeError::InvalidPublicKey => 328,
+            }
+        }
+
+        for (code, _) in ExecutableError::CODES {
+            let error = ExecutableError::try_from(*code).expect("Unknown code");
+            assert_eq!(executable(&error), *code);
+            assert_eq!(i32::from(&error), *code);
+            assert!((100..200).contains(code));
+        }
+        for (code, _) in JvmError::CODES {
+            let error = JvmError::try_from(*code).expect("Unknown code");
+            assert_eq!(jvm(&error), *code);
+            assert_eq!(i32::from(&error), *code);
+            assert!((200..300).contains(code));
+        }
+        for (code, _) in RuntimeError::CODES {
+            let error = RuntimeError::try_from(*code).expect("Unknown code");
+            assert_eq!(runtime(&error), *code);
+            assert_eq!(i32::from(&error), *code);
+            assert!((300..400).contains(code));
+        }
+
+        assert_eq!(RuntimeError::try_from(0), Err(0));
+    }
 }
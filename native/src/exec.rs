@@ -1,15 +1,89 @@
 use crate::{
+    bytecode,
+    coverage::Coverage,
     error::{Error, ExecutableError, Result, RuntimeError},
+    external::ExternalFunction,
+    interrupt,
     modules::Module as M,
     runtime::{data_entry::DataEntry, Runtime},
     vm::Vm,
 };
-use std::{fmt, str::FromStr};
+use base58::{FromBase58, ToBase58};
+use std::{fmt, str::FromStr, sync::Arc};
 use wasmi::{
-    core::ValueType, Config, Engine, Func, FuncType, Memory, MemoryType, Module, StackLimits,
-    Store, Value,
+    core::{TrapCode, ValType},
+    Caller, Config, Engine, Func, FuncType, Instance, Linker, Memory, MemoryType, Module,
+    StackLimits, Store, Val,
 };
 
+/// Length of the identifier of a contract imported as a library.
+const CONTRACT_ID_LENGTH: usize = 32;
+
+/// Magic number at the start of binary WASM modules.
+#[cfg(feature = "dev")]
+pub(crate) const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Options of the engine compiling the contracts.
+///
+/// The stack limits bound the recursion of contracts, they change the results
+/// and must be the same on all nodes. Other options only affect the throughput,
+/// except the compilation mode, see `CompilationMode`.
+///
+/// Caching the modules keeps a contract called several times from being compiled again.
+/// The configurations are compared by `cargo run --release --features testing --example engine`,
+/// the numbers of cached stacks show no difference there, so the default of `wasmi` is kept.
+/// Lazy translation only pays off when the modules are not cached, and it changes
+/// the consumed fuel, so the functions are translated eagerly by default.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EngineConfig {
+    /// Translation of the functions of a module.
+    pub compilation_mode: CompilationMode,
+    /// Execution stacks kept by the engine for the following calls.
+    pub cached_stacks: usize,
+    /// Compiling the bytecode of a contract once for all its calls made by the virtual machine.
+    pub cache_modules: bool,
+    /// Values on the stack of an instance, including the locals of the called functions.
+    pub max_value_stack_height: usize,
+    /// Nested calls of the functions of an instance.
+    pub max_recursion_depth: usize,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            compilation_mode: CompilationMode::Eager,
+            cache_modules: true,
+            // Defaults of `wasmi`
+            cached_stacks: 2,
+            max_value_stack_height: 128 * 1024,
+            max_recursion_depth: 1024,
+        }
+    }
+}
+
+/// Modes of the translation of the bytecode to the instructions of the engine.
+///
+/// The bytecode is always validated when a module is compiled, lazy validation
+/// of `wasmi` isn't deterministic and is not available.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompilationMode {
+    /// All functions are translated when a module is compiled.
+    Eager,
+    /// Functions are translated on their first call, which consumes fuel of the call.
+    /// Modules shared between calls are translated once, so the consumed fuel depends
+    /// on the previous calls and the mode must not be used when it is reported to the node.
+    LazyTranslation,
+}
+
+impl From<CompilationMode> for wasmi::CompilationMode {
+    fn from(mode: CompilationMode) -> Self {
+        match mode {
+            CompilationMode::Eager => wasmi::CompilationMode::Eager,
+            CompilationMode::LazyTranslation => wasmi::CompilationMode::LazyTranslation,
+        }
+    }
+}
+
 /// Enumeration of possible executable functions of a WASM contract.
 pub enum LoadableFunction {
     Constructor,
@@ -38,7 +112,7 @@ impl fmt::Display for LoadableFunction {
 
 #[derive(Debug)]
 pub struct Executable {
-    module: Option<Module>,
+    module: Option<Arc<Module>>,
     /// Initial memory size of a contract's sandbox.
     initial: u32,
     /// The maximum memory size of a contract's sandbox.
@@ -59,24 +133,59 @@ impl Executable {
     }
 
     /// Load bytecode for execution.
-    pub fn load_bytecode(&mut self, bytecode: &[u8]) -> Result<()> {
-        self.module = Some(Self::create_module(bytecode)?);
+    pub fn load_bytecode(&mut self, bytecode: &[u8], engine_config: &EngineConfig) -> Result<()> {
+        let engine = Self::engine(false, engine_config)?;
+        self.module = Some(Arc::new(Self::compile(&engine, bytecode)?));
         Ok(())
     }
 
+    /// Load bytecode instrumented for coverage.
+    /// Counters of the coverage are exported as mutable globals.
+    pub fn load_instrumented_bytecode(
+        &mut self,
+        bytecode: &[u8],
+        engine_config: &EngineConfig,
+    ) -> Result<()> {
+        let engine = Self::engine(true, engine_config)?;
+        self.module = Some(Arc::new(Self::compile(&engine, bytecode)?));
+        Ok(())
+    }
+
+    /// Load a module compiled earlier, see `Executable::compile`.
+    pub(crate) fn load_module(&mut self, module: Arc<Module>) {
+        self.module = Some(module);
+    }
+
     /// Validate bytecode contract.
     pub fn validate_bytecode(bytecode: &[u8]) -> Result<Module> {
-        Self::create_module(bytecode)
+        Self::compile(&Self::engine(false, &EngineConfig::default())?, bytecode)
     }
 
-    /// Initializing `Engine` and `Module`.
-    fn create_module(bytecode: &[u8]) -> Result<Module> {
-        let stack_limits = StackLimits::default();
+    /// Initializing `Engine` with the configuration of contracts.
+    /// Modules compiled by the same engine share its execution stacks.
+    pub(crate) fn engine(mutable_global: bool, engine_config: &EngineConfig) -> Result<Engine> {
+        let max_value_stack_height = engine_config.max_value_stack_height;
+        // The engine can't start with an empty value stack
+        if max_value_stack_height == 0 {
+            return Err(Error::Executable(ExecutableError::InvalidEngineConfig(
+                String::from("Maximum value stack height must be positive"),
+            )));
+        }
+        let stack_limits = StackLimits::new(
+            max_value_stack_height.min(128),
+            max_value_stack_height,
+            engine_config.max_recursion_depth,
+        )
+        .map_err(|error| {
+            Error::Executable(ExecutableError::InvalidEngineConfig(error.to_string()))
+        })?;
 
         let mut config = Config::default();
         config
             .set_stack_limits(stack_limits)
-            .wasm_mutable_global(false)
+            .set_cached_stacks(engine_config.cached_stacks)
+            .compilation_mode(engine_config.compilation_mode.into())
+            .wasm_mutable_global(mutable_global)
             .wasm_sign_extension(true)
             .wasm_saturating_float_to_int(false)
             .wasm_multi_value(true)
@@ -87,8 +196,16 @@ impl Executable {
             .floats(false)
             .consume_fuel(true);
 
-        let engine = Engine::new(&config);
-        let module = Module::new(&engine, &mut &bytecode[..]).map_err(|error| {
+        Ok(Engine::new(&config))
+    }
+
+    /// Parsing and validating the bytecode by the engine.
+    /// The checkpoints of the interruption are injected before, see `interrupt::instrument`.
+    pub(crate) fn compile(engine: &Engine, bytecode: &[u8]) -> Result<Module> {
+        let bytecode = interrupt::instrument(bytecode).map_err(|error| {
+            Error::Executable(ExecutableError::InvalidBytecode(format!("{:?}", error)))
+        })?;
+        let module = Module::new(engine, &bytecode[..]).map_err(|error| {
             Error::Executable(ExecutableError::InvalidBytecode(format!("{:?}", error)))
         })?;
 
@@ -111,10 +228,11 @@ impl Executable {
         params: &[u8],
         modules: Vec<M>,
         vm: &mut Vm,
-    ) -> Result<Vec<Value>> {
+    ) -> Result<Vec<Val>> {
+        let external = vm.external_functions().to_vec();
         let runtime = Runtime::new(vm);
 
-        let (func, mut store) = Self::load_wasm_func(
+        let (instance, func, mut store) = Self::load_wasm_func(
             self.module
                 .as_ref()
                 .ok_or(Error::Executable(ExecutableError::ModuleNotFound))?,
@@ -123,6 +241,7 @@ impl Executable {
             (self.initial, self.maximum),
             self.fuel_limit,
             modules,
+            &external,
         )?;
 
         let memory = match store.data().memory() {
@@ -138,18 +257,67 @@ impl Executable {
         store.data_mut().set_heap_base(offset_memory as i32);
 
         let func_type = func.ty(&store);
+        // The error code is read from the first result, see `DataEntry::from_results`
+        if func_type.results().first() != Some(&ValType::I32) {
+            return Err(Error::Executable(ExecutableError::SignatureMismatch(
+                String::from("Function must return an i32 error code first"),
+            )));
+        }
         let func_args = Self::type_check_arguments(&func_type, func_args.as_slice())?;
 
         let mut results = Self::prepare_results_buffer(&func_type);
 
-        func.call(&mut store, &func_args, &mut results)
-            .map_err(|error| {
-                Error::Executable(ExecutableError::FailedExec(format!("{:?}", error)))
-            })?;
+        store.data_mut().vm.enter_profile(&func_name.to_string());
+        let result = func.call(&mut store, &func_args, &mut results);
+
+        // Fuel is accounted even if the execution has failed
+        let fuel_consumed = self.fuel_limit - store.get_fuel().unwrap_or(self.fuel_limit);
+        store.data_mut().vm.add_fuel_consumed(fuel_consumed);
+        store.data_mut().vm.exit_profile(fuel_consumed);
+
+        if store.data().vm.coverage().is_some() {
+            let counters = Self::read_counters(&instance, &mut store);
+            store.data_mut().vm.record_coverage(counters);
+        }
+
+        let fuel_limit = self.fuel_limit;
+        result.map_err(|error| match error.as_trap_code() {
+            // Errors of host functions are returned as is
+            _ if error.downcast_ref::<Error>().is_some() => error
+                .downcast::<Error>()
+                .expect("Trap contains a host function error"),
+            Some(TrapCode::StackOverflow) => Error::Runtime(RuntimeError::StackOverflow),
+            // The node reports the consumption to the sender of the transaction
+            Some(TrapCode::OutOfFuel) => Error::Runtime(RuntimeError::OutOfFuel(format!(
+                "limit {}, consumed {}",
+                fuel_limit, fuel_consumed
+            ))),
+            _ => Error::Executable(ExecutableError::FailedExec(format!("{:?}", error))),
+        })?;
 
         Ok(results)
     }
 
+    /// Reading the counters injected by the coverage instrumentation.
+    fn read_counters(instance: &Instance, store: &mut Store<Runtime>) -> Vec<(String, u64)> {
+        let names: Vec<String> = instance
+            .exports(&*store)
+            .map(|export| export.name().to_string())
+            .filter(|name| Coverage::is_counter(name))
+            .collect();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let global = instance.get_global(&*store, &name)?;
+                match global.get(&*store) {
+                    Val::I64(hits) => Some((name, hits as u64)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
     /// Loads the Wasm [`Func`] from the given Wasm bytecode.
     ///
     /// Returns the [`Func`] together with its [`Store`] for further processing.
@@ -166,18 +334,41 @@ impl Executable {
         memory: (u32, u32),
         fuel_limit: u64,
         modules: Vec<M>,
-    ) -> Result<(Func, Store<Runtime<'a>>)> {
+        external: &[ExternalFunction],
+    ) -> Result<(Instance, Func, Store<Runtime<'a>>)> {
         let engine = module.engine();
         let mut linker = <wasmi::Linker<Runtime>>::new(engine);
         let mut store = wasmi::Store::new(engine, runtime);
+        store.limiter(|runtime| runtime);
 
         for item in modules {
-            let (module, name, func) = item(&mut store);
+            let (module, name, func) = item(&mut store)?;
             linker.define(&module, &name, func).map_err(|error| {
                 Error::Executable(ExecutableError::LinkerError(format!("{:?}", error)))
             })?;
         }
 
+        let interrupt = Func::wrap(
+            &mut store,
+            |caller: Caller<Runtime>| -> core::result::Result<(), wasmi::Error> {
+                Ok(caller.data().vm.check_interrupted()?)
+            },
+        );
+        linker
+            .define(interrupt::MODULE, interrupt::NAME, interrupt)
+            .map_err(|error| {
+                Error::Executable(ExecutableError::LinkerError(format!("{:?}", error)))
+            })?;
+
+        for function in external {
+            let func = function.func(&mut store);
+            linker
+                .define(function.module(), function.name(), func)
+                .map_err(|error| {
+                    Error::Executable(ExecutableError::LinkerError(format!("{:?}", error)))
+                })?;
+        }
+
         let memory = Memory::new(
             &mut store,
             MemoryType::new(memory.0, Some(memory.1)).map_err(|error| {
@@ -192,6 +383,15 @@ impl Executable {
             Error::Executable(ExecutableError::LinkerError(format!("{:?}", error)))
         })?;
 
+        let contract_id = store.data().vm.top_frame().contract_id().to_base58();
+        Self::link_libraries(
+            module,
+            &mut linker,
+            &mut store,
+            &mut vec![contract_id],
+            &mut vec![],
+        )?;
+
         let instance = linker
             .instantiate(&mut store, module)
             .and_then(|pre| pre.start(&mut store))
@@ -200,7 +400,7 @@ impl Executable {
             })?;
 
         store.data_mut().set_memory(memory);
-        store.add_fuel(fuel_limit).map_err(|error| {
+        store.set_fuel(fuel_limit).map_err(|error| {
             Error::Executable(ExecutableError::FuelMeteringDisabled(format!(
                 "{:?}",
                 error
@@ -209,7 +409,7 @@ impl Executable {
 
         let heap_base = match instance.get_global(&mut store, "__heap_base") {
             Some(global) => match global.get(&mut store) {
-                Value::I32(value) => value,
+                Val::I32(value) => value,
                 _ => return Err(Error::Executable(ExecutableError::HeapBaseNotFound)),
             },
             None => return Err(Error::Executable(ExecutableError::HeapBaseNotFound)),
@@ -222,7 +422,111 @@ impl Executable {
             .and_then(|ext| ext.into_func())
             .ok_or(Error::Executable(ExecutableError::FuncNotFound))?;
 
-        Ok((func, store))
+        Ok((instance, func, store))
+    }
+
+    /// Linking the deployed contracts imported by the module by their base58 identifiers.
+    ///
+    /// Libraries are instantiated in the store of the contract, share its memory and runtime,
+    /// so their exports are called without the overhead of `call_contract`. Libraries can't
+    /// import each other cyclically and can't keep memory apart from the contract: data segments,
+    /// a heap base above zero and mutable globals of a stack or an allocator are rejected.
+    fn link_libraries<'a>(
+        module: &Module,
+        linker: &mut Linker<Runtime<'a>>,
+        store: &mut Store<Runtime<'a>>,
+        path: &mut Vec<String>,
+        linked: &mut Vec<String>,
+    ) -> Result<()> {
+        let linker_error =
+            |message: String| Error::Executable(ExecutableError::LinkerError(message));
+
+        let mut names: Vec<String> = module
+            .imports()
+            .map(|import| import.module().to_string())
+            .filter(|name| {
+                name.from_base58()
+                    .is_ok_and(|contract_id| contract_id.len() == CONTRACT_ID_LENGTH)
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+
+        for name in names {
+            if linked.contains(&name) {
+                continue;
+            }
+
+            if path.contains(&name) {
+                return Err(linker_error(format!(
+                    "Cyclic import of the library {}",
+                    name
+                )));
+            }
+
+            if linked.len() >= store.data().vm.limits().libraries {
+                return Err(linker_error(String::from("Too many libraries")));
+            }
+
+            let contract_id = name.from_base58().unwrap_or_default();
+            let bytecode = store.data().vm.node.get_bytecode(&contract_id)?;
+
+            if !bytecode::inspect(&bytecode, false)?
+                .data_segments
+                .is_empty()
+            {
+                return Err(linker_error(format!(
+                    "The library {} has data segments",
+                    name
+                )));
+            }
+
+            if bytecode::has_mutable_globals(&bytecode)? {
+                return Err(linker_error(format!(
+                    "The library {} has mutable globals",
+                    name
+                )));
+            }
+
+            let library = Self::compile(module.engine(), &bytecode)?;
+
+            path.push(name.clone());
+            Self::link_libraries(&library, linker, store, path, linked)?;
+            path.pop();
+
+            let instance = linker
+                .instantiate(&mut *store, &library)
+                .and_then(|pre| pre.start(&mut *store))
+                .map_err(|error| {
+                    Error::Executable(ExecutableError::InstantiateFailed(format!("{:?}", error)))
+                })?;
+
+            // Memory below the heap base of the library belongs to the contract
+            let heap_base = instance
+                .get_global(&*store, "__heap_base")
+                .map(|global| global.get(&*store));
+            if !matches!(heap_base, None | Some(Val::I32(0))) {
+                return Err(linker_error(format!(
+                    "The library {} reserves memory below its heap base",
+                    name
+                )));
+            }
+
+            let exports: Vec<(String, Func)> = instance
+                .exports(&*store)
+                .filter_map(|export| Some((export.name().to_string(), export.into_func()?)))
+                .collect();
+
+            for (export, func) in exports {
+                linker
+                    .define(&name, &export, func)
+                    .map_err(|error| linker_error(format!("{:?}", error)))?;
+            }
+
+            linked.push(name);
+        }
+
+        Ok(())
     }
 
     /// Type checks the given function arguments and returns them decoded into [`Value`]s.
@@ -231,7 +535,7 @@ impl Executable {
     ///
     /// - If the number of given arguments is not equal to the number of function parameters.
     /// - If an argument cannot be properly parsed to its expected parameter type.
-    fn type_check_arguments(func_type: &FuncType, func_args: &[String]) -> Result<Vec<Value>> {
+    fn type_check_arguments(func_type: &FuncType, func_args: &[String]) -> Result<Vec<Val>> {
         if func_type.params().len() != func_args.len() {
             return Err(Error::Executable(ExecutableError::InvalidNumArgs));
         }
@@ -240,8 +544,7 @@ impl Executable {
             .params()
             .iter()
             .zip(func_args)
-            .enumerate()
-            .map(|(_, (param_type, arg))| {
+            .map(|(param_type, arg)| {
                 macro_rules! make_err {
                     () => {
                         |error| {
@@ -254,8 +557,8 @@ impl Executable {
                 }
 
                 match param_type {
-                    ValueType::I32 => arg.parse::<i32>().map(Value::from).map_err(make_err!()),
-                    ValueType::I64 => arg.parse::<i64>().map(Value::from).map_err(make_err!()),
+                    ValType::I32 => arg.parse::<i32>().map(Val::from).map_err(make_err!()),
+                    ValType::I64 => arg.parse::<i64>().map(Val::from).map_err(make_err!()),
                     _ => Err(Error::Executable(ExecutableError::FailedParseFuncArgs(
                         "Not known or inappropriate argument type".to_string(),
                     ))),
@@ -267,12 +570,12 @@ impl Executable {
     }
 
     /// Returns a [`Value`] buffer capable of holding the return values.
-    fn prepare_results_buffer(func_type: &FuncType) -> Vec<Value> {
+    fn prepare_results_buffer(func_type: &FuncType) -> Vec<Val> {
         func_type
             .results()
             .iter()
             .copied()
-            .map(Value::default)
+            .map(Val::default)
             .collect::<Vec<_>>()
     }
 }
@@ -280,7 +583,7 @@ impl Executable {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::wat2wasm;
+    use crate::{tests::wat2wasm, vm::ExecutionMode};
 
     #[test]
     fn test_executable_valid_bytecode() {
@@ -325,4 +628,131 @@ mod tests {
             Error::Executable(ExecutableError::ConstructorNotFound)
         );
     }
+
+    #[test]
+    fn test_stack_limits() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (func $recurse (param $depth i32) (result i32)
+                (if (result i32) (i32.eqz (local.get $depth))
+                    (then (i32.const 0))
+                    (else (call $recurse (i32.sub (local.get $depth) (i32.const 1))))
+                )
+            )
+
+            (func (export "_constructor") (result i32)
+                (call $recurse (i32.const 100))
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat2wasm(wat).expect("WAT code parsing failed");
+
+        let run = |engine_config: EngineConfig| {
+            let mut vm = Vm::new(
+                vec![],
+                bytecode.clone(),
+                crate::limits::ExecutionLimits {
+                    fuel: 100_000,
+                    ..Default::default()
+                },
+                crate::modules::all(),
+                Box::new(crate::mock::Mock::new()),
+                ExecutionMode::Apply,
+            )
+            .expect("Call stack creation failed");
+            vm.set_engine_config(engine_config);
+
+            vm.run("_constructor", &[0, 0])
+                .map(|values| values[0].i32())
+                .map_err(|error| error.error)
+        };
+
+        assert_eq!(run(EngineConfig::default()), Ok(Some(0)));
+
+        // Limits are applied with and without the module cache
+        for cache_modules in [true, false] {
+            let result = run(EngineConfig {
+                cache_modules,
+                max_recursion_depth: 50,
+                ..Default::default()
+            });
+            assert_eq!(result, Err(Error::Runtime(RuntimeError::StackOverflow)));
+        }
+
+        // An empty value stack is rejected instead of failing the engine
+        for cache_modules in [true, false] {
+            let result = run(EngineConfig {
+                cache_modules,
+                max_value_stack_height: 0,
+                ..Default::default()
+            });
+            assert!(matches!(
+                result,
+                Err(Error::Executable(ExecutableError::InvalidEngineConfig(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_compilation_mode() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (func $sum (param $n i64) (result i64)
+                (local $i i64) (local $sum i64)
+                (loop $loop
+                    (local.set $sum (i64.add (local.get $sum) (local.get $i)))
+                    (local.set $i (i64.add (local.get $i) (i64.const 1)))
+                    (br_if $loop (i64.lt_u (local.get $i) (local.get $n)))
+                )
+                (local.get $sum)
+            )
+
+            (func (export "_constructor") (result i32)
+                (i32.wrap_i64 (i64.sub (call $sum (i64.const 10)) (i64.const 45)))
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat2wasm(wat).expect("WAT code parsing failed");
+
+        let run = |compilation_mode: CompilationMode| {
+            let mut vm = Vm::new(
+                vec![],
+                bytecode.clone(),
+                crate::limits::ExecutionLimits {
+                    fuel: 100_000,
+                    ..Default::default()
+                },
+                crate::modules::all(),
+                Box::new(crate::mock::Mock::new()),
+                ExecutionMode::Apply,
+            )
+            .expect("Call stack creation failed");
+            vm.set_engine_config(EngineConfig {
+                compilation_mode,
+                ..Default::default()
+            });
+
+            let result = vm
+                .run("_constructor", &[0, 0])
+                .map(|values| values[0].i32())
+                .map_err(|error| error.error);
+            (result, vm.fuel_consumed())
+        };
+
+        let (eager, eager_fuel) = run(CompilationMode::Eager);
+        let (lazy, lazy_fuel) = run(CompilationMode::LazyTranslation);
+
+        assert_eq!(eager, Ok(Some(0)));
+        assert_eq!(lazy, eager);
+        // Translation of the called functions is paid by the call
+        assert!(lazy_fuel > eager_fuel);
+    }
 }
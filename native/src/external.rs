@@ -0,0 +1,234 @@
+//! Host functions defined outside of the crate.
+//!
+//! The node registers them at runtime, so plugins can extend the functions available
+//! to contracts without recompiling the virtual machine. Registered functions are linked
+//! next to the env modules and can't replace them.
+//!
+//! Only integer parameters and results are supported, the memory of the contract
+//! is not accessible to external functions.
+
+use crate::{
+    error::{Error, ExecutableError, Result, RuntimeError},
+    modules,
+    runtime::Runtime,
+};
+use std::sync::{Arc, Mutex};
+use wasmi::{core::ValType, Caller, Func, FuncType, Store, Val};
+
+/// Implementation of an external function receiving and returning integers widened to `i64`.
+pub type Callback = Arc<dyn Fn(&[i64]) -> Result<Vec<i64>> + Send + Sync>;
+
+/// Functions registered by the node.
+static REGISTRY: Mutex<Vec<ExternalFunction>> = Mutex::new(Vec::new());
+
+/// Host function implemented by the embedder.
+#[derive(Clone)]
+pub struct ExternalFunction {
+    module: String,
+    name: String,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// Fuel consumed by each call.
+    fuel: u64,
+    callback: Callback,
+}
+
+impl ExternalFunction {
+    /// Creating the function from its signature in the form `i32 i64 -> i64`.
+    /// Functions of the env modules can't be redefined.
+    pub fn new(
+        module: &str,
+        name: &str,
+        signature: &str,
+        fuel: u64,
+        callback: Callback,
+    ) -> Result<Self> {
+        let invalid =
+            |reason: &str| Error::Executable(ExecutableError::InvalidHostFunction(reason.into()));
+
+        if module.is_empty() || name.is_empty() || module == "env" {
+            return Err(invalid("Invalid module or name"));
+        }
+
+        if modules::host_functions().any(|function| function.module == module) {
+            return Err(invalid("Env modules can't be extended"));
+        }
+
+        let (params, results) = signature
+            .split_once("->")
+            .ok_or_else(|| invalid("Signature must be in the form `params -> results`"))?;
+
+        let parse = |types: &str| {
+            types
+                .split_whitespace()
+                .map(|type_| match type_ {
+                    "i32" => Ok(ValType::I32),
+                    "i64" => Ok(ValType::I64),
+                    _ => Err(invalid("Only i32 and i64 types are supported")),
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            module: module.to_string(),
+            name: name.to_string(),
+            params: parse(params)?,
+            results: parse(results)?,
+            fuel,
+            callback,
+        })
+    }
+
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Creating the function in the store of the instance.
+    pub(crate) fn func(&self, store: &mut Store<Runtime>) -> Func {
+        let ty = FuncType::new(self.params.clone(), self.results.clone());
+        let fuel = self.fuel;
+        let callback = self.callback.clone();
+
+        Func::new(
+            store,
+            ty,
+            move |mut caller: Caller<Runtime>, inputs: &[Val], outputs: &mut [Val]| {
+                crate::runtime::consume_fuel(&mut caller, fuel)?;
+                caller.data().vm.check_interrupted()?;
+
+                let args: Vec<i64> = inputs
+                    .iter()
+                    .map(|value| match value {
+                        Val::I32(value) => *value as i64,
+                        Val::I64(value) => *value,
+                        _ => 0,
+                    })
+                    .collect();
+
+                let values = callback(&args)?;
+                if values.len() != outputs.len() {
+                    return Err(Error::Runtime(RuntimeError::InvalidResult(format!(
+                        "Expected {} values, received {}",
+                        outputs.len(),
+                        values.len()
+                    )))
+                    .into());
+                }
+
+                for (output, value) in outputs.iter_mut().zip(values) {
+                    *output = match output.ty() {
+                        ValType::I32 => Val::I32(value as i32),
+                        _ => Val::I64(value),
+                    };
+                }
+
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Registering the function for all following executions.
+/// A function with the same module and name is replaced.
+pub fn register(function: ExternalFunction) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|error| error.into_inner());
+    registry.retain(|item| item.module != function.module || item.name != function.name);
+    registry.push(function);
+}
+
+/// Removing the function, returns `false` if it isn't registered.
+pub fn unregister(module: &str, name: &str) -> bool {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|error| error.into_inner());
+    let len = registry.len();
+    registry.retain(|item| item.module != module || item.name != name);
+    registry.len() != len
+}
+
+/// Getting the registered functions.
+pub fn registered() -> Vec<ExternalFunction> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        limits::ExecutionLimits,
+        mock::Mock,
+        vm::{ExecutionMode, Vm},
+    };
+
+    #[test]
+    fn test_external_function() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "plugin" "add" (func $add (param i32 i64) (result i64)))
+
+            (func (export "_constructor") (result i32)
+                (i64.ne (call $add (i32.const 2) (i64.const 40)) (i64.const 42))
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let add = ExternalFunction::new(
+            "plugin",
+            "add",
+            "i32 i64 -> i64",
+            100,
+            Arc::new(|args| Ok(vec![args[0] + args[1]])),
+        )
+        .expect("Invalid function");
+
+        let mut vm = Vm::new(
+            vec![],
+            bytecode.clone(),
+            ExecutionLimits {
+                fuel: 1024,
+                ..Default::default()
+            },
+            modules::all(),
+            Box::new(Mock::new()),
+            ExecutionMode::Apply,
+        )
+        .expect("Call stack creation failed");
+        vm.set_external_functions(vec![add]);
+
+        let result = vm.run("_constructor", &[0, 0]).expect("Execution failed");
+        assert_eq!(result[0].i32(), Some(0));
+        assert!(vm.fuel_consumed() > 100);
+
+        // Errors of the function are returned as is
+        let fail = ExternalFunction::new(
+            "plugin",
+            "add",
+            "i32 i64 -> i64",
+            0,
+            Arc::new(|_| Err(Error::Runtime(RuntimeError::ParseError))),
+        )
+        .expect("Invalid function");
+        vm.set_external_functions(vec![fail]);
+
+        let error = vm
+            .run("_constructor", &[0, 0])
+            .map(|_| ())
+            .map_err(|error| error.error);
+        assert_eq!(error, Err(Error::Runtime(RuntimeError::ParseError)));
+
+        let callback: Callback = Arc::new(|_| Ok(vec![]));
+        assert!(ExternalFunction::new("env0", "add", "->", 0, callback.clone()).is_err());
+        assert!(ExternalFunction::new("plugin", "add", "f32 -> i32", 0, callback.clone()).is_err());
+        assert!(ExternalFunction::new("plugin", "add", "i32", 0, callback).is_err());
+    }
+}
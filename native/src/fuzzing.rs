@@ -0,0 +1,106 @@
+//! Entry points for fuzzing, available with `--cfg fuzzing` set by `cargo fuzz`.
+//! Every entry point must return an error on malformed input instead of panicking.
+
+use crate::{
+    error::Result,
+    runtime::{data_entry::DataEntry, Runtime},
+    MEMORY,
+};
+
+/// Size of a page of WASM linear memory.
+const PAGE_SIZE: usize = 65536;
+
+/// Decoding the params of a contract function into the initial linear memory.
+pub fn decode_params(input: &[u8]) -> Result<Vec<String>> {
+    let mut memory = vec![0u8; MEMORY.0 as usize * PAGE_SIZE];
+    let mut offset = 0;
+    DataEntry::deserialize_params(input, &mut memory, &mut offset)
+}
+
+/// Decoding a data entry received from the node.
+pub fn decode_data_entry(input: &[u8]) -> Result<DataEntry> {
+    DataEntry::deserialize(input)
+}
+
+/// Reading a slice of linear memory requested by a contract.
+pub fn read_memory(memory: &[u8], offset: u32, length: u32) -> Result<Vec<u8>> {
+    crate::env::read_memory(memory, offset, length).map(|bytes| bytes.to_vec())
+}
+
+/// Writing the result of a host function to linear memory at the heap base.
+pub fn write_memory(memory: &mut [u8], heap_base: u32, result: Vec<u8>) -> Result<(u32, u32)> {
+    let mut vm = crate::vm::Vm::new(
+        vec![],
+        vec![],
+        crate::limits::ExecutionLimits {
+            fuel: 0,
+            ..Default::default()
+        },
+        vec![],
+        Box::new(crate::mock::Mock::new()),
+        crate::vm::ExecutionMode::Apply,
+    )?;
+    let mut runtime = Runtime::new(&mut vm);
+    crate::env::write_memory(&mut runtime, memory, heap_base as usize, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::RuntimeError, vm::Vm};
+
+    #[test]
+    fn test_memory_out_of_bounds() {
+        let memory = [0u8; 16];
+
+        assert!(read_memory(&memory, 8, 8).is_ok());
+        assert!(read_memory(&memory, 8, 9).is_err());
+        assert!(read_memory(&memory, u32::MAX, u32::MAX).is_err());
+
+        let mut memory = [0u8; 16];
+        assert!(write_memory(&mut memory, 15, vec![1, 2]).is_err());
+        assert_eq!(
+            write_memory(&mut memory, 14, vec![1, 2]).ok(),
+            Some((14, 2))
+        );
+    }
+
+    #[test]
+    fn test_malformed_params() {
+        assert!(decode_params(&[0, 1, 0]).is_err());
+        // Binary value longer than the input
+        assert!(decode_params(&[0, 1, 0, 0, 2, 0, 2, 0, 0]).is_err());
+        assert!(decode_data_entry(&[0, 0, 42]).is_err());
+    }
+
+    #[test]
+    fn test_run_untrusted() {
+        assert!(Vm::run_untrusted(&[0, 97, 115, 109], &[]).is_err());
+
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+            (import "env0" "base_58" (func $base_58 (param i32 i32) (result i32 i32 i32)))
+
+            (func (export "_constructor") (result i32)
+                (call $base_58
+                    (i32.const -16) ;; Offset outside of the memory
+                    (i32.const 32)
+                )
+                (drop)
+                (drop)
+            )
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let result = Vm::run_untrusted(&bytecode, &[]).expect("Execution failed");
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].i32(),
+            Some(RuntimeError::MemoryOutOfBounds.as_i32())
+        );
+    }
+}
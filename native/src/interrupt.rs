@@ -0,0 +1,319 @@
+//! Checkpoints of the interruption injected into the contract code.
+//!
+//! The interpreter runs a guest loop without returning to the host, so the cancellation and
+//! the timeout of the execution are checked by the code of the contract. A countdown is
+//! decremented at the entry of every function and at the start of every loop, every
+//! `INTERVAL` checkpoints it calls the host function checking the interruption.
+//! The checkpoints consume the same fuel on every node.
+
+use crate::bytecode::{order, write_i32, write_section, write_u32, SECTION_CODE, SECTION_GLOBAL};
+use std::ops::Range;
+use wasmparser::{BinaryReader, ElementItems, ExternalKind, Operator, Parser, Payload, TypeRef};
+
+/// Module and name of the host function checking the interruption.
+pub(crate) const MODULE: &str = "__wevm";
+pub(crate) const NAME: &str = "interrupt";
+
+/// Checkpoints passed between the checks of the interruption.
+const INTERVAL: i32 = 10_000;
+
+const SECTION_TYPE: u8 = 1;
+const SECTION_IMPORT: u8 = 2;
+
+/// Replacement of the bytes of the range by the encoded bytes, empty ranges are insertions.
+type Edit = (Range<usize>, Vec<u8>);
+
+/// Injecting the checkpoints into the module.
+///
+/// The host function is imported after the other imports, so the indexes of the functions
+/// defined by the module are shifted in the calls, exports, start and element sections.
+pub(crate) fn instrument(bytecode: &[u8]) -> wasmparser::Result<Vec<u8>> {
+    let mut types = 0;
+    let mut imported_funcs = 0;
+    let mut imported_globals = 0;
+    let mut defined_globals = 0;
+    let mut bodies: Vec<Range<usize>> = vec![];
+    let mut edits: Vec<Edit> = vec![];
+    // Offsets of the function indexes shifted by the import
+    let mut functions = vec![];
+
+    for payload in Parser::new(0).parse_all(bytecode) {
+        match payload? {
+            Payload::TypeSection(reader) => types = reader.count(),
+            Payload::ImportSection(reader) => {
+                for import in reader.into_imports() {
+                    match import?.ty {
+                        TypeRef::Func(_) | TypeRef::FuncExact(_) => imported_funcs += 1,
+                        TypeRef::Global(_) => imported_globals += 1,
+                        _ => (),
+                    }
+                }
+            }
+            Payload::GlobalSection(reader) => defined_globals = reader.count(),
+            Payload::ExportSection(reader) => {
+                for export in reader.into_iter_with_offsets() {
+                    let (offset, export) = export?;
+                    if matches!(export.kind, ExternalKind::Func | ExternalKind::FuncExact) {
+                        // Name and kind precede the index
+                        let mut reader = BinaryReader::new(&bytecode[offset..], offset);
+                        reader.read_string()?;
+                        reader.read_u8()?;
+                        functions.push(reader.original_position());
+                    }
+                }
+            }
+            Payload::StartSection { range, .. } => functions.push(range.start),
+            Payload::ElementSection(reader) => {
+                for element in reader {
+                    match element?.items {
+                        ElementItems::Functions(items) => {
+                            for item in items.into_iter_with_offsets() {
+                                functions.push(item?.0);
+                            }
+                        }
+                        ElementItems::Expressions(_, items) => {
+                            for item in items {
+                                let mut reader = item?.get_operators_reader();
+                                while !reader.eof() {
+                                    let (operator, offset) = reader.read_with_offset()?;
+                                    if let Operator::RefFunc { .. } = operator {
+                                        // The opcode precedes the index
+                                        functions.push(offset + 1);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut reader = body.get_operators_reader()?;
+                edits.push((
+                    reader.original_position()..reader.original_position(),
+                    vec![],
+                ));
+
+                while !reader.eof() {
+                    let (operator, offset) = reader.read_with_offset()?;
+                    match operator {
+                        Operator::Call { .. } | Operator::RefFunc { .. } => {
+                            functions.push(offset + 1)
+                        }
+                        Operator::Loop { .. } => {
+                            let position = reader.original_position();
+                            edits.push((position..position, vec![]));
+                        }
+                        _ => (),
+                    }
+                }
+
+                bodies.push(body.range());
+            }
+            _ => (),
+        }
+    }
+
+    if bodies.is_empty() {
+        return Ok(bytecode.to_vec());
+    }
+
+    let interrupt = imported_funcs;
+    let countdown = imported_globals + defined_globals;
+    let checkpoint = checkpoint(interrupt, countdown);
+    for edit in edits.iter_mut() {
+        edit.1 = checkpoint.clone();
+    }
+
+    for offset in functions {
+        let mut reader = BinaryReader::new(&bytecode[offset..], offset);
+        let index = reader.read_var_u32()?;
+        if index >= imported_funcs {
+            let mut encoded = vec![];
+            write_u32(&mut encoded, index + 1);
+            edits.push((offset..reader.original_position(), encoded));
+        }
+    }
+    edits.sort_by_key(|edit| (edit.0.start, edit.0.end));
+
+    // (func) type of the host function
+    let func_type = [0x60, 0x00, 0x00];
+    let mut import = vec![];
+    write_name(&mut import, MODULE);
+    write_name(&mut import, NAME);
+    import.push(0x00);
+    write_u32(&mut import, types);
+    // (global (mut i32) (i32.const INTERVAL))
+    let mut global = vec![0x7f, 0x01, 0x41];
+    write_i32(&mut global, INTERVAL);
+    global.push(0x0b);
+
+    let mut code = vec![];
+    write_u32(&mut code, bodies.len() as u32);
+    for range in bodies {
+        let body = apply(bytecode, range, &edits);
+        write_u32(&mut code, body.len() as u32);
+        code.extend(body);
+    }
+
+    let mut result = bytecode[..8].to_vec();
+    let mut reader = BinaryReader::new(bytecode, 0);
+    reader.read_bytes(8)?;
+    let mut added = vec![
+        (SECTION_TYPE, func_type.to_vec()),
+        (SECTION_IMPORT, import),
+        (SECTION_GLOBAL, global),
+    ];
+
+    while !reader.eof() {
+        let id = reader.read_u8()?;
+        let size = reader.read_var_u32()? as usize;
+        let start = reader.original_position();
+        let content = reader.read_bytes(size)?;
+
+        // Sections missing in the module are added with the single entry
+        while let Some((missing, entry)) = added.first() {
+            if order(*missing) >= order(id) {
+                break;
+            }
+            write_section(&mut result, *missing, &extend(&[], entry));
+            added.remove(0);
+        }
+
+        match id {
+            _ if added.first().is_some_and(|(added, _)| *added == id) => {
+                let (_, entry) = added.remove(0);
+                write_section(&mut result, id, &extend(content, &entry));
+            }
+            SECTION_CODE => write_section(&mut result, id, &code),
+            // Names of the functions would be shifted
+            0 if BinaryReader::new(content, 0).read_string()? == "name" => (),
+            _ => write_section(
+                &mut result,
+                id,
+                &apply(bytecode, start..start + size, &edits),
+            ),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Decrementing the countdown, the interruption is checked when it reaches zero.
+fn checkpoint(interrupt: u32, countdown: u32) -> Vec<u8> {
+    let mut result = vec![];
+    // global.get, i32.eqz, if
+    result.push(0x23);
+    write_u32(&mut result, countdown);
+    result.extend_from_slice(&[0x45, 0x04, 0x40]);
+    // call, i32.const INTERVAL, global.set, end
+    result.push(0x10);
+    write_u32(&mut result, interrupt);
+    result.push(0x41);
+    write_i32(&mut result, INTERVAL);
+    result.push(0x24);
+    write_u32(&mut result, countdown);
+    result.push(0x0b);
+    // global.get, i32.const 1, i32.sub, global.set
+    result.push(0x23);
+    write_u32(&mut result, countdown);
+    result.extend_from_slice(&[0x41, 0x01, 0x6b, 0x24]);
+    write_u32(&mut result, countdown);
+    result
+}
+
+/// Copying the range of the bytecode with the edits inside of it.
+fn apply(bytecode: &[u8], range: Range<usize>, edits: &[Edit]) -> Vec<u8> {
+    let mut result = vec![];
+    let mut position = range.start;
+    for (edited, bytes) in edits
+        .iter()
+        .filter(|(edited, _)| edited.start >= range.start && edited.end <= range.end)
+    {
+        result.extend_from_slice(&bytecode[position..edited.start]);
+        result.extend_from_slice(bytes);
+        position = edited.end;
+    }
+    result.extend_from_slice(&bytecode[position..range.end]);
+    result
+}
+
+/// Appending the entry to the content of a vector section.
+fn extend(content: &[u8], entry: &[u8]) -> Vec<u8> {
+    let mut reader = BinaryReader::new(content, 0);
+    let count = match content.is_empty() {
+        true => 0,
+        false => reader.read_var_u32().unwrap_or_default(),
+    };
+
+    let mut result = vec![];
+    write_u32(&mut result, count + 1);
+    result.extend_from_slice(&content[reader.current_position()..]);
+    result.extend_from_slice(entry);
+    result
+}
+
+fn write_name(output: &mut Vec<u8>, name: &str) {
+    write_u32(output, name.len() as u32);
+    output.extend_from_slice(name.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instrument() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+            (import "env0" "get_block_timestamp" (func $get_block_timestamp (result i32 i64)))
+
+            (table 2 funcref)
+            (elem (i32.const 0) $sum $get_block_timestamp)
+
+            (func $sum (param $n i32) (result i32)
+                (local $i i32) (local $sum i32)
+                (loop $loop
+                    (local.set $sum (i32.add (local.get $sum) (local.get $i)))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $loop (i32.lt_u (local.get $i) (local.get $n)))
+                )
+                (local.get $sum)
+            )
+
+            (func $start)
+
+            (func (export "_constructor") (result i32)
+                (i32.sub
+                    (call_indirect (param i32) (result i32) (i32.const 10) (i32.const 0))
+                    (call $sum (i32.const 10))
+                )
+            )
+
+            (start $start)
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let instrumented = instrument(&bytecode).expect("Instrumentation failed");
+        wasmparser::Validator::new()
+            .validate_all(&instrumented)
+            .expect("Invalid instrumented module");
+
+        let wat = wasmprinter::print_bytes(&instrumented).expect("Printing failed");
+        // The host function is imported last, the defined functions are shifted
+        assert!(wat.contains(r#"(import "__wevm" "interrupt" (func (;1;) (type"#));
+        assert!(wat.contains("(elem (;0;) (i32.const 0) func 2 0)"));
+        assert!(wat.contains("(start 3)"));
+        assert!(wat.contains("call 2"));
+        assert!(wat.contains(r#"(export "_constructor" (func 4))"#));
+        // Entries of three functions and a loop
+        assert_eq!(wat.matches("call 1\n").count(), 4);
+        assert!(wat.contains("(global (;0;) (mut i32) i32.const 10000)"));
+
+        // Modules without code are kept
+        let empty = wat::parse_str("(module)").expect("WAT code parsing failed");
+        let instrumented = instrument(&empty).expect("Instrumentation failed");
+        assert_eq!(instrumented, empty);
+    }
+}
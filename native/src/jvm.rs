@@ -1,10 +1,14 @@
 use crate::{
-    error::{Error, JvmError, Result},
+    error::{Error, ExecutableError, JvmError, Result},
+    limits::ExecutionLimits,
     node::Node,
-    vm::Vm,
 };
-use jni::objects::{JByteArray, JObject, JValue};
+use jni::{
+    objects::{GlobalRef, JByteArray, JLongArray, JObject, JValue},
+    AttachGuard, JavaVM,
+};
 use log::error;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 // A primitive java type.
 // L - Object
@@ -16,12 +20,7 @@ use log::error;
 
 macro_rules! env {
     ($self:expr) => {{
-        match &$self.jvm {
-            Some(jvm) => jvm
-                .attach_current_thread()
-                .map_err(|_| Error::Jvm(JvmError::AttachCurrentThread))?,
-            None => return Err(Error::Jvm(JvmError::JvmNotFound)),
-        }
+        attach(&$self.jvm)?
     }};
 }
 
@@ -32,21 +31,122 @@ macro_rules! byte_array {
     }};
 }
 
-macro_rules! jvm_callback {
-    ($jvm_callback:expr) => {
-        match $jvm_callback {
-            Some(jvm_callback) => jvm_callback,
-            None => return Err(Error::Jvm(JvmError::JvmCallbackNotFound)),
-        }
-    };
+/// How the native threads making callbacks are attached to the JVM.
+/// Threads of the node are already attached and are never detached by the callbacks.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Attachment {
+    /// Each callback attaches the thread and detaches it before returning.
+    Scoped,
+    /// The first callback attaches the thread until the thread exits, so the worker threads
+    /// pay for the attachment once and are detached when their pool shuts down.
+    #[default]
+    Permanent,
+}
+
+static PERMANENT: AtomicBool = AtomicBool::new(true);
+
+/// Setting the attachment of the threads for all following callbacks.
+pub fn set_attachment(attachment: Attachment) {
+    PERMANENT.store(attachment == Attachment::Permanent, Ordering::Relaxed);
+}
+
+static DELETION_REFUND: AtomicU64 = AtomicU64::new(0);
+
+/// Setting `ExecutionLimits::deletion_refund` of all following executions.
+pub fn set_deletion_refund(fuel: u64) {
+    DELETION_REFUND.store(fuel, Ordering::Relaxed);
+}
+
+/// Default limits of the executions requested by the node.
+pub(crate) fn limits() -> ExecutionLimits {
+    ExecutionLimits {
+        deletion_refund: DELETION_REFUND.load(Ordering::Relaxed),
+        ..Default::default()
+    }
+}
+
+/// Getting the environment of the current thread, attaching it if it isn't attached yet.
+pub(crate) fn attach(jvm: &JavaVM) -> Result<AttachGuard<'_>> {
+    let attach_error = |_| Error::Jvm(JvmError::AttachCurrentThread);
+
+    // The permanent attachment is kept by the thread, so the guard below doesn't detach it
+    if PERMANENT.load(Ordering::Relaxed) {
+        jvm.attach_current_thread_permanently()
+            .map_err(attach_error)?;
+    }
+
+    jvm.attach_current_thread().map_err(attach_error)
+}
+
+/// Node interface implemented by calling the `WASMService` of the node through JNI.
+///
+/// The node is shared by the threads running the calls of a session. Each callback uses
+/// the thread it is made from, the thread is attached to the JVM according to `Attachment`.
+pub struct Jvm {
+    jvm: JavaVM,
+    jvm_callback: GlobalRef,
+}
+
+impl Jvm {
+    pub fn new(jvm: JavaVM, jvm_callback: GlobalRef) -> Self {
+        Self { jvm, jvm_callback }
+    }
+}
+
+/// External host function implemented by a Java object with the `long[] call(long[] args)` method.
+pub struct JvmFunction {
+    jvm: JavaVM,
+    callback: GlobalRef,
+}
+
+impl JvmFunction {
+    pub fn new(jvm: JavaVM, callback: GlobalRef) -> Self {
+        Self { jvm, callback }
+    }
+
+    pub fn call(&self, args: &[i64]) -> Result<Vec<i64>> {
+        let mut env = env!(self);
+
+        let method_call = |error: jni::errors::Error| {
+            let err = JvmError::MethodCall(format!("{:?}", error));
+            error!("{}", error);
+            Error::Jvm(err)
+        };
+
+        let array = env.new_long_array(args.len() as i32).map_err(method_call)?;
+        env.set_long_array_region(&array, 0, args)
+            .map_err(method_call)?;
+
+        let result = env
+            .call_method(
+                &self.callback,
+                "call",
+                "([J)[J",
+                &[JValue::Object(&array.into())],
+            )
+            .map_err(method_call)?
+            .l()
+            .map_err(|_| Error::Jvm(JvmError::ReceiveObject))?;
+
+        let result = JLongArray::from(result);
+        let length = env
+            .get_array_length(&result)
+            .map_err(|_| Error::Jvm(JvmError::ReceiveLong))?;
+
+        let mut values = vec![0; length as usize];
+        env.get_long_array_region(&result, 0, &mut values)
+            .map_err(|_| Error::Jvm(JvmError::ReceiveLong))?;
+
+        Ok(values)
+    }
 }
 
 // Implementing the JVM call
-impl Node for Vm {
+impl Node for Jvm {
     fn get_chain_id(&self) -> Result<i8> {
         let mut env = env!(self);
 
-        env.call_method(jvm_callback!(&self.jvm_callback), "getChainId", "()B", &[])
+        env.call_method(&self.jvm_callback, "getChainId", "()B", &[])
             .map_err(|error| {
                 let err = JvmError::MethodCall(format!("{:?}", error));
                 error!("{}", error);
@@ -62,7 +162,7 @@ impl Node for Vm {
         let message = byte_array!(env, message);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "require",
             "([B)V",
             &[JValue::Object(&message.into())],
@@ -83,7 +183,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "getBytecode",
                 "([B)[B",
                 &[JValue::Object(&contract_id.into())],
@@ -111,7 +211,7 @@ impl Node for Vm {
         let payments = byte_array!(env, payments);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "addPayments",
             "([B[B[B)V",
             &[
@@ -129,6 +229,46 @@ impl Node for Vm {
         Ok(())
     }
 
+    fn is_paused(&self, contract_id: &[u8]) -> Result<bool> {
+        let mut env = env!(self);
+
+        let contract_id = byte_array!(env, contract_id);
+
+        env.call_method(
+            &self.jvm_callback,
+            "isPaused",
+            "([B)Z",
+            &[JValue::Object(&contract_id.into())],
+        )
+        .map_err(|error| {
+            let err = JvmError::MethodCall(format!("{:?}", error));
+            error!("{}", error);
+            Error::Jvm(err)
+        })?
+        .z()
+        .map_err(|_| Error::Jvm(JvmError::ReceiveBoolean))
+    }
+
+    fn is_feature_activated(&self, feature: &[u8]) -> Result<bool> {
+        let mut env = env!(self);
+
+        let feature = byte_array!(env, feature);
+
+        env.call_method(
+            &self.jvm_callback,
+            "isFeatureActivated",
+            "([B)Z",
+            &[JValue::Object(&feature.into())],
+        )
+        .map_err(|error| {
+            let err = JvmError::MethodCall(format!("{:?}", error));
+            error!("{}", error);
+            Error::Jvm(err)
+        })?
+        .z()
+        .map_err(|_| Error::Jvm(JvmError::ReceiveBoolean))
+    }
+
     // Asset
     fn get_balance(&self, asset_id: &[u8], address: &[u8]) -> Result<i64> {
         let mut env = env!(self);
@@ -137,7 +277,7 @@ impl Node for Vm {
         let address = byte_array!(env, address);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "getBalance",
             "([B[B)J",
             &[
@@ -168,7 +308,7 @@ impl Node for Vm {
         let recipient = byte_array!(env, recipient);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "transfer",
             "([B[B[BJ)V",
             &[
@@ -204,7 +344,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "issue",
                 "([B[B[BJJZ)[B",
                 &[
@@ -238,7 +378,7 @@ impl Node for Vm {
         let asset_id = byte_array!(env, asset_id);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "burn",
             "([B[BJ)V",
             &[
@@ -269,7 +409,7 @@ impl Node for Vm {
         let asset_id = byte_array!(env, asset_id);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "reissue",
             "([B[BJZ)V",
             &[
@@ -296,7 +436,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "block",
                 "([B)[B",
                 &[JValue::Object(&field.into())],
@@ -316,6 +456,31 @@ impl Node for Vm {
         Ok(bytes.to_vec())
     }
 
+    fn get_block_at(&self, height: i64) -> Result<Vec<u8>> {
+        let mut env = env!(self);
+
+        let result = env
+            .call_method(
+                &self.jvm_callback,
+                "getBlockAt",
+                "(J)[B",
+                &[JValue::Long(height)],
+            )
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?
+            .l()
+            .map_err(|_| Error::Jvm(JvmError::ReceiveObject))?;
+
+        let bytes = env
+            .convert_byte_array(<JObject<'_> as Into<JByteArray>>::into(result))
+            .map_err(|_| Error::Jvm(JvmError::ByteArrayConversion))?;
+
+        Ok(bytes.to_vec())
+    }
+
     // Crypto
     fn fast_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         let mut env = env!(self);
@@ -324,7 +489,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "fastHash",
                 "([B)[B",
                 &[JValue::Object(&bytes.into())],
@@ -351,7 +516,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "secureHash",
                 "([B)[B",
                 &[JValue::Object(&bytes.into())],
@@ -379,7 +544,7 @@ impl Node for Vm {
         let public_key = byte_array!(env, public_key);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "sigVerify",
             "([B[B[B)Z",
             &[
@@ -406,7 +571,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "lease",
                 "([B[BJ)[B",
                 &[
@@ -437,7 +602,7 @@ impl Node for Vm {
         let lease_id = byte_array!(env, lease_id);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "cancelLease",
             "([B[B)V",
             &[
@@ -454,6 +619,54 @@ impl Node for Vm {
         Ok(())
     }
 
+    fn get_lease_info(&self, lease_id: &[u8]) -> Result<Vec<u8>> {
+        let mut env = env!(self);
+
+        let lease_id = byte_array!(env, lease_id);
+
+        let result = env
+            .call_method(
+                &self.jvm_callback,
+                "getLeaseInfo",
+                "([B)[B",
+                &[JValue::Object(&lease_id.into())],
+            )
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?
+            .l()
+            .map_err(|_| Error::Jvm(JvmError::ReceiveObject))?;
+
+        let bytes = env
+            .convert_byte_array(<JObject<'_> as Into<JByteArray>>::into(result))
+            .map_err(|_| Error::Jvm(JvmError::ByteArrayConversion))?;
+
+        Ok(bytes.to_vec())
+    }
+
+    // Permission
+    fn check_permission(&self, address: &[u8], role_id: i32) -> Result<bool> {
+        let mut env = env!(self);
+
+        let address = byte_array!(env, address);
+
+        env.call_method(
+            &self.jvm_callback,
+            "checkPermission",
+            "([BI)Z",
+            &[JValue::Object(&address.into()), role_id.into()],
+        )
+        .map_err(|error| {
+            let err = JvmError::MethodCall(format!("{:?}", error));
+            error!("{}", error);
+            Error::Jvm(err)
+        })?
+        .z()
+        .map_err(|_| Error::Jvm(JvmError::ReceiveBoolean))
+    }
+
     // Storage
     fn contains_key(&self, address: &[u8], key: &[u8]) -> Result<bool> {
         let mut env = env!(self);
@@ -462,7 +675,7 @@ impl Node for Vm {
         let key = byte_array!(env, key);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "containsKey",
             "([B[B)Z",
             &[JValue::Object(&address.into()), JValue::Object(&key.into())],
@@ -484,7 +697,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "getStorage",
                 "([B[B)[B",
                 &[JValue::Object(&address.into()), JValue::Object(&key.into())],
@@ -504,6 +717,43 @@ impl Node for Vm {
         Ok(bytes.to_vec())
     }
 
+    fn get_storage_binary_into(
+        &self,
+        address: &[u8],
+        key: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        let mut env = env!(self);
+
+        let address = byte_array!(env, address);
+        let key = byte_array!(env, key);
+        // SAFETY: The buffer outlives the call and the node doesn't keep it after returning
+        let buffer = unsafe { env.new_direct_byte_buffer(buffer.as_mut_ptr(), buffer.len()) }
+            .map_err(|_| Error::Jvm(JvmError::NewObject))?;
+
+        let result = env
+            .call_method(
+                &self.jvm_callback,
+                "getStorageBinaryInto",
+                "([B[BLjava/nio/ByteBuffer;)I",
+                &[
+                    JValue::Object(&address.into()),
+                    JValue::Object(&key.into()),
+                    JValue::Object(&buffer.into()),
+                ],
+            )
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?
+            .i()
+            .map_err(|_| Error::Jvm(JvmError::ReceiveInt))?;
+
+        // The node returns a negative length if the entry isn't binary
+        usize::try_from(result).map_err(|_| Error::Executable(ExecutableError::FailedDeserialize))
+    }
+
     fn set_storage(&self, contract_id: &[u8], value: &[u8]) -> Result<()> {
         let mut env = env!(self);
 
@@ -511,7 +761,7 @@ impl Node for Vm {
         let value = byte_array!(env, value);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "setStorage",
             "([B[B)V",
             &[
@@ -528,6 +778,58 @@ impl Node for Vm {
         Ok(())
     }
 
+    fn delete_storage(&self, contract_id: &[u8], key: &[u8]) -> Result<bool> {
+        let mut env = env!(self);
+
+        let contract_id = byte_array!(env, contract_id);
+        let key = byte_array!(env, key);
+
+        env.call_method(
+            &self.jvm_callback,
+            "deleteStorage",
+            "([B[B)Z",
+            &[
+                JValue::Object(&contract_id.into()),
+                JValue::Object(&key.into()),
+            ],
+        )
+        .map_err(|error| {
+            let err = JvmError::MethodCall(format!("{:?}", error));
+            error!("{}", error);
+            Error::Jvm(err)
+        })?
+        .z()
+        .map_err(|_| Error::Jvm(JvmError::ReceiveBoolean))
+    }
+
+    fn get_account_data(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        let mut env = env!(self);
+
+        let address = byte_array!(env, address);
+        let key = byte_array!(env, key);
+
+        let result = env
+            .call_method(
+                &self.jvm_callback,
+                "getAccountData",
+                "([B[B)[B",
+                &[JValue::Object(&address.into()), JValue::Object(&key.into())],
+            )
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?
+            .l()
+            .map_err(|_| Error::Jvm(JvmError::ReceiveObject))?;
+
+        let bytes = env
+            .convert_byte_array(<JObject<'_> as Into<JByteArray>>::into(result))
+            .map_err(|_| Error::Jvm(JvmError::ByteArrayConversion))?;
+
+        Ok(bytes.to_vec())
+    }
+
     // Tx
     fn get_tx_payments(&self, payment_id: &[u8]) -> Result<i64> {
         let mut env = env!(self);
@@ -535,7 +837,7 @@ impl Node for Vm {
         let payment_id = byte_array!(env, payment_id);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "getTxPayments",
             "([B)J",
             &[JValue::Object(&payment_id.into())],
@@ -556,7 +858,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "getTxPaymentAssetId",
                 "([BJ)[B",
                 &[JValue::Object(&payment_id.into()), number.into()],
@@ -582,7 +884,7 @@ impl Node for Vm {
         let payment_id = byte_array!(env, payment_id);
 
         env.call_method(
-            jvm_callback!(&self.jvm_callback),
+            &self.jvm_callback,
             "getTxPaymentAmount",
             "([BJ)J",
             &[JValue::Object(&payment_id.into()), number.into()],
@@ -603,7 +905,7 @@ impl Node for Vm {
 
         let result = env
             .call_method(
-                jvm_callback!(&self.jvm_callback),
+                &self.jvm_callback,
                 "tx",
                 "([B)[B",
                 &[JValue::Object(&field.into())],
@@ -622,4 +924,43 @@ impl Node for Vm {
 
         Ok(bytes.to_vec())
     }
+
+    fn snapshot(&self) -> Result<i32> {
+        let mut env = env!(self);
+
+        env.call_method(&self.jvm_callback, "snapshot", "()I", &[])
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?
+            .i()
+            .map_err(|_| Error::Jvm(JvmError::ReceiveInt))
+    }
+
+    fn restore(&self, snapshot: i32) -> Result<()> {
+        let mut env = env!(self);
+
+        env.call_method(&self.jvm_callback, "restore", "(I)V", &[snapshot.into()])
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?;
+
+        Ok(())
+    }
+
+    fn discard(&self, snapshot: i32) -> Result<()> {
+        let mut env = env!(self);
+
+        env.call_method(&self.jvm_callback, "discard", "(I)V", &[snapshot.into()])
+            .map_err(|error| {
+                let err = JvmError::MethodCall(format!("{:?}", error));
+                error!("{}", error);
+                Error::Jvm(err)
+            })?;
+
+        Ok(())
+    }
 }
@@ -1,50 +1,117 @@
+#[cfg(feature = "jvm")]
+pub mod bytecode;
+
+#[cfg(feature = "jvm")]
+pub mod cancel;
+
+#[cfg(feature = "jvm")]
+pub mod coverage;
+
 #[cfg(feature = "jvm")]
 mod env;
 
 #[cfg(feature = "jvm")]
-mod error;
+pub mod error;
 
 #[cfg(feature = "jvm")]
 mod exec;
 
+#[cfg(feature = "jvm")]
+pub mod external;
+
+#[cfg(all(feature = "jvm", any(fuzzing, test)))]
+pub mod fuzzing;
+
+#[cfg(feature = "jvm")]
+mod interrupt;
+
 #[cfg(feature = "jvm")]
 mod jvm;
 
-mod modules;
+#[cfg(feature = "jvm")]
+pub mod limits;
+
+#[cfg(feature = "jvm")]
+mod logger;
+
+#[cfg(all(feature = "jvm", any(test, feature = "testing")))]
+pub mod mock;
+
+pub mod modules;
+
+#[cfg(feature = "jvm")]
+pub mod node;
 
 #[cfg(feature = "jvm")]
-mod node;
+pub mod pool;
+
+#[cfg(feature = "jvm")]
+pub mod profile;
 
 #[cfg(feature = "jvm")]
 mod runtime;
 
+#[cfg(feature = "jvm")]
+pub mod session;
+
+#[cfg(all(feature = "jvm", any(test, feature = "testing")))]
+pub mod testing;
+
+#[cfg(feature = "jvm")]
+pub mod trace;
+
 #[cfg(all(test, feature = "jvm"))]
 mod tests;
 
 #[cfg(feature = "jvm")]
-mod vm;
+pub mod view;
 
+#[cfg(feature = "jvm")]
+pub mod vm;
+
+#[cfg(feature = "jvm")]
+pub use exec::{CompilationMode, EngineConfig};
+#[cfg(feature = "jvm")]
+pub use limits::ExecutionLimits;
 pub use modules::v0;
 pub use modules::v1;
+pub use modules::{manifest, HostFunction};
+#[cfg(feature = "jvm")]
+pub use runtime::data_entry::DataEntry;
+#[cfg(feature = "jvm")]
+pub use runtime::payments::Payments;
 
 #[cfg(feature = "jvm")]
-use crate::{error::JvmError, exec::Executable, vm::Vm};
+use crate::{
+    error::JvmError,
+    exec::Executable,
+    external::ExternalFunction,
+    jvm::{Jvm, JvmFunction},
+    logger::JvmLogger,
+    session::{Call, Session},
+    vm::{ExecutionMode, Vm},
+};
 #[cfg(feature = "jvm")]
 use base58::ToBase58;
 #[cfg(feature = "jvm")]
 use jni::{
     objects::{JByteArray, JClass, JObject, JString},
-    sys::{jint, jlong},
+    sys::{jboolean, jint, jlong},
     JNIEnv,
 };
 #[cfg(feature = "jvm")]
-use log::{debug, error};
+use log::{debug, error, LevelFilter};
 #[cfg(feature = "jvm")]
-use wasmi::Value;
+use std::time::Duration;
+#[cfg(feature = "jvm")]
+use wasmi::Val;
 
 /// Size of allocated linear memory.
 pub const MEMORY: (u32, u32) = (2, 16);
 
+/// Pages of linear memory available to all contracts of the call stack at once.
+pub const MEMORY_LIMIT: u32 = 256;
+
 // This `#[no_mangle]` keeps rust from "mangling" the name and making it unique
 // for this crate. The name follow a strict naming convention so that the
 // JNI implementation will be able to automatically find the implementation
@@ -58,6 +125,7 @@ pub const MEMORY: (u32, u32) = (2, 16);
 // local frame lifetime it is associated with.
 
 /// External Java function to execute bytecode contract.
+/// Returns the error code of the function.
 #[cfg(feature = "jvm")]
 #[no_mangle]
 pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContract<'local>(
@@ -70,12 +138,307 @@ pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContra
     fuel_limit: jlong,
     callback: JObject<'local>,
 ) -> jint {
-    let _ = env_logger::try_init();
+    let values = match run_contract(
+        &mut env,
+        contract_id,
+        bytecode,
+        func_name,
+        params,
+        fuel_limit,
+        callback,
+        ExecutionMode::Apply,
+    )
+    .0
+    {
+        Ok(values) => values,
+        Err(code) => return code,
+    };
+
+    error_code(&values)
+}
+
+/// External Java function to execute bytecode contract in the mode restricting the state changes,
+/// `0` applies the transaction, `1` validates it and `2` queries the state.
+/// Returns the error code of the function.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContractInMode<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    contract_id: JByteArray<'local>,
+    bytecode: JByteArray<'local>,
+    func_name: JString<'local>,
+    params: JByteArray<'local>,
+    fuel_limit: jlong,
+    callback: JObject<'local>,
+    mode: jint,
+) -> jint {
+    let mode = match ExecutionMode::from_i32(mode) {
+        Ok(mode) => mode,
+        Err(error) => {
+            error!("{}", error);
+            return error.as_jint();
+        }
+    };
+
+    let values = match run_contract(
+        &mut env,
+        contract_id,
+        bytecode,
+        func_name,
+        params,
+        fuel_limit,
+        callback,
+        mode,
+    )
+    .0
+    {
+        Ok(values) => values,
+        Err(code) => return code,
+    };
+
+    error_code(&values)
+}
+
+/// Getting the error code from the values returned by the function.
+#[cfg(feature = "jvm")]
+fn error_code(values: &[Val]) -> jint {
+    match DataEntry::from_results(values) {
+        Ok((code, _)) => code as jint,
+        Err(error) => {
+            error!("{}", error);
+            error.as_jint()
+        }
+    }
+}
+
+/// External Java function to execute bytecode contract and get the values returned by the function.
+/// Returns the `ExecutionResult` with the error code and the results serialized like the params
+/// or null if the object can't be created.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContractWithResults<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    contract_id: JByteArray<'local>,
+    bytecode: JByteArray<'local>,
+    func_name: JString<'local>,
+    params: JByteArray<'local>,
+    fuel_limit: jlong,
+    callback: JObject<'local>,
+) -> JObject<'local> {
+    let mut results = runtime::params::Params::new();
+
+    let (result, fuel_consumed) = run_contract(
+        &mut env,
+        contract_id,
+        bytecode,
+        func_name,
+        params,
+        fuel_limit,
+        callback,
+        ExecutionMode::Apply,
+    );
+    let code = match result {
+        Ok(values) => match DataEntry::from_results(&values) {
+            Ok((code, values)) => {
+                if code == 0 {
+                    for value in values {
+                        results.push(value);
+                    }
+                }
+                code as jint
+            }
+            Err(error) => {
+                error!("{}", error);
+                error.as_jint()
+            }
+        },
+        Err(code) => code,
+    };
+
+    new_execution_result(&mut env, code, results, fuel_consumed)
+}
+
+/// External Java function to execute the view function of a contract deployed on the node.
+/// State-changing host functions fail and the fuel is limited by `view::FUEL_LIMIT`.
+/// Returns the `ExecutionResult` like `runContractWithResults`.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runView<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    contract_id: JByteArray<'local>,
+    func_name: JString<'local>,
+    params: JByteArray<'local>,
+    callback: JObject<'local>,
+) -> JObject<'local> {
+    let (contract_id, params) = match (
+        env.convert_byte_array(contract_id),
+        env.convert_byte_array(params),
+    ) {
+        (Ok(contract_id), Ok(params)) => (contract_id, params),
+        _ => {
+            error!("{}", JvmError::ByteArrayConversion);
+            return JObject::null();
+        }
+    };
+
+    let func_name: String = match env.get_string(&func_name) {
+        Ok(string) => string.into(),
+        Err(_) => {
+            error!("{}", JvmError::NewString);
+            return JObject::null();
+        }
+    };
+
+    let jvm = match env.get_java_vm() {
+        Ok(jvm) => jvm,
+        Err(_) => {
+            error!("{}", JvmError::GetJavaVM);
+            return JObject::null();
+        }
+    };
+
+    let callback = match env.new_global_ref(callback) {
+        Ok(callback) => callback,
+        Err(_) => {
+            error!("{}", JvmError::NewGlobalRef);
+            return JObject::null();
+        }
+    };
+
+    debug!(
+        "Started WEVM to view the contract: {}",
+        contract_id.to_base58()
+    );
+
+    let mut results = runtime::params::Params::new();
+    let code = match view::run(
+        &contract_id,
+        &func_name,
+        &params,
+        Box::new(Jvm::new(jvm, callback)),
+    ) {
+        Ok((code, values)) => {
+            if code == 0 {
+                for value in values {
+                    results.push(value);
+                }
+            }
+            code as jint
+        }
+        Err(error) => {
+            error!("{}", error);
+            error.as_jint()
+        }
+    };
+
+    // The fuel of views isn't counted against the block
+    new_execution_result(&mut env, code, results, 0)
+}
+
+/// Creating the `ExecutionResult` with the error code, the serialized results and the consumed fuel.
+#[cfg(feature = "jvm")]
+fn new_execution_result<'local>(
+    env: &mut JNIEnv<'local>,
+    code: jint,
+    results: runtime::params::Params,
+    fuel_consumed: u64,
+) -> JObject<'local> {
+    let results = match env.byte_array_from_slice(&results.as_bytes()) {
+        Ok(array) => array,
+        Err(_) => {
+            error!("{}", JvmError::NewByteArray);
+            return JObject::null();
+        }
+    };
+
+    match env.new_object(
+        "com/wavesenterprise/wasm/core/ExecutionResult",
+        "(I[BJ)V",
+        &[
+            code.into(),
+            (&results).into(),
+            (fuel_consumed as jlong).into(),
+        ],
+    ) {
+        Ok(object) => object,
+        Err(_) => {
+            error!("{}", JvmError::NewObject);
+            JObject::null()
+        }
+    }
+}
+
+/// Executing the contract for the JNI functions, the error code is returned on failure.
+/// The fuel consumed by all frames of the call stack is returned in both cases,
+/// the refunds are deducted from it only if the call succeeds.
+#[cfg(feature = "jvm")]
+#[allow(clippy::too_many_arguments)]
+fn run_contract(
+    env: &mut JNIEnv,
+    contract_id: JByteArray,
+    bytecode: JByteArray,
+    func_name: JString,
+    params: JByteArray,
+    fuel_limit: jlong,
+    callback: JObject,
+    mode: ExecutionMode,
+) -> (std::result::Result<Vec<Val>, jint>, u64) {
+    let (mut vm, func_name, params) = match load_contract(
+        env,
+        contract_id,
+        bytecode,
+        func_name,
+        params,
+        fuel_limit,
+        callback,
+        mode,
+    ) {
+        Ok(loaded) => loaded,
+        Err(code) => return (Err(code), 0),
+    };
+
+    let result = vm.run(&func_name, &params).map_err(|error| {
+        error!("{}", error);
+        error.as_jint()
+    });
+
+    // The node discards the changes of a failed call, its removals aren't refunded
+    let success = matches!(
+        result.as_deref().map(DataEntry::from_results),
+        Ok(Ok((0, _)))
+    );
+    let fuel_consumed = match success {
+        true => vm.fuel_consumed() - vm.fuel_refunded(),
+        false => vm.fuel_consumed(),
+    };
+
+    (result, fuel_consumed)
+}
+
+/// Creating the virtual machine of the contract and reading the arguments of the call.
+#[cfg(feature = "jvm")]
+#[allow(clippy::too_many_arguments)]
+fn load_contract(
+    env: &mut JNIEnv,
+    contract_id: JByteArray,
+    bytecode: JByteArray,
+    func_name: JString,
+    params: JByteArray,
+    fuel_limit: jlong,
+    callback: JObject,
+    mode: ExecutionMode,
+) -> std::result::Result<(Vm, String, Vec<u8>), jint> {
     let contract_id = match env.convert_byte_array(contract_id) {
         Ok(bytes) => bytes,
         Err(_) => {
             error!("{}", JvmError::ByteArrayConversion);
-            return JvmError::ByteArrayConversion.as_jint();
+            return Err(JvmError::ByteArrayConversion.as_jint());
         }
     };
 
@@ -88,15 +451,24 @@ pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContra
         Ok(bytes) => bytes,
         Err(_) => {
             error!("{}", JvmError::ByteArrayConversion);
-            return JvmError::ByteArrayConversion.as_jint();
+            return Err(JvmError::ByteArrayConversion.as_jint());
         }
     };
 
+    // The text format of the `dev` feature is never accepted from the node
+    #[cfg(feature = "dev")]
+    if !bytecode.starts_with(exec::WASM_MAGIC) {
+        let error =
+            error::ExecutableError::InvalidBytecode(String::from("Text format is not allowed"));
+        error!("{}", error);
+        return Err(error.as_jint());
+    }
+
     let jvm = match env.get_java_vm() {
         Ok(jvm) => jvm,
         Err(_) => {
             error!("{}", JvmError::GetJavaVM);
-            return JvmError::GetJavaVM.as_jint();
+            return Err(JvmError::GetJavaVM.as_jint());
         }
     };
 
@@ -104,35 +476,57 @@ pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContra
         Ok(callback) => callback,
         Err(_) => {
             error!("{}", JvmError::NewGlobalRef);
-            return JvmError::NewGlobalRef.as_jint();
+            return Err(JvmError::NewGlobalRef.as_jint());
         }
     };
 
     let mut vm = match Vm::new(
         contract_id,
         bytecode,
-        MEMORY,
-        fuel_limit as u64,
-        modules(),
-        Some(jvm),
-        Some(callback),
+        ExecutionLimits {
+            fuel: fuel_limit as u64,
+            ..jvm::limits()
+        },
+        modules::all(),
+        Box::new(Jvm::new(jvm, callback)),
+        mode,
     ) {
         Ok(vm) => vm,
         Err(error) => {
             error!("{}", error);
-            return error.as_jint();
+            return Err(error.as_jint());
         }
     };
+    vm.set_external_functions(external::registered());
 
     let func_name: String = match env.get_string(&func_name) {
         Ok(string) => string.into(),
         Err(_) => {
             error!("{}", JvmError::NewString);
-            return JvmError::NewString.as_jint();
+            return Err(JvmError::NewString.as_jint());
         }
     };
 
     let params = match env.convert_byte_array(params) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            error!("{}", JvmError::ByteArrayConversion);
+            return Err(JvmError::ByteArrayConversion.as_jint());
+        }
+    };
+
+    Ok((vm, func_name, params))
+}
+
+/// External Java function to validate bytecode contract.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_validateBytecode<'local>(
+    env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    bytecode: JByteArray<'local>,
+) -> jint {
+    let bytecode = match env.convert_byte_array(bytecode) {
         Ok(bytes) => bytes,
         Err(_) => {
             error!("{}", JvmError::ByteArrayConversion);
@@ -140,28 +534,296 @@ pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runContra
         }
     };
 
-    let result = match vm.run(&func_name, &params) {
-        Ok(result) => result,
+    match Executable::validate_bytecode(&bytecode) {
+        Ok(_) => 0,
         Err(error) => {
             error!("{}", error);
-            return error.as_jint();
+            error.as_jint()
+        }
+    }
+}
+
+/// External Java function to forward logs to the SLF4J logger of the node.
+/// Level: 0 - off, 1 - error, 2 - warn, 3 - info, 4 - debug, 5 - trace.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_setLogger<'local>(
+    env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    logger: JObject<'local>,
+    level: jint,
+) -> jint {
+    let jvm = match env.get_java_vm() {
+        Ok(jvm) => jvm,
+        Err(_) => {
+            error!("{}", JvmError::GetJavaVM);
+            return JvmError::GetJavaVM.as_jint();
         }
     };
 
-    match result[0] {
-        Value::I32(value) => value as jint,
-        _ => 0 as jint,
+    let logger = match env.new_global_ref(logger) {
+        Ok(logger) => logger,
+        Err(_) => {
+            error!("{}", JvmError::NewGlobalRef);
+            return JvmError::NewGlobalRef.as_jint();
+        }
+    };
+
+    let level = match level {
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        5 => LevelFilter::Trace,
+        _ => LevelFilter::Off,
+    };
+
+    match JvmLogger::new(jvm, logger, level).init() {
+        Ok(()) => 0,
+        Err(_) => {
+            error!("{}", JvmError::SetLogger);
+            JvmError::SetLogger.as_jint()
+        }
     }
 }
 
-/// External Java function to validate bytecode contract.
+/// External Java function to choose how the native threads making callbacks are attached to the JVM.
+/// Permanently attached threads stay attached until they exit, the others are detached after each callback.
 #[cfg(feature = "jvm")]
 #[no_mangle]
-pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_validateBytecode<'local>(
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_setThreadAttachment<
+    'local,
+>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    permanent: jboolean,
+) {
+    jvm::set_attachment(match permanent != 0 {
+        true => jvm::Attachment::Permanent,
+        false => jvm::Attachment::Scoped,
+    });
+}
+
+/// External Java function to set the fuel refunded for each existing data entry removed by a contract.
+/// The refund must be the same on all nodes, negative values are zero.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_setDeletionRefund<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    fuel: jlong,
+) {
+    jvm::set_deletion_refund(fuel.max(0) as u64);
+}
+
+/// External Java function to normalize bytecode contract before it is stored.
+/// Returns `null` if the bytecode is invalid.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_normalizeBytecode<'local>(
+    env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    bytecode: JByteArray<'local>,
+) -> JByteArray<'local> {
+    let bytecode = match env.convert_byte_array(bytecode) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            error!("{}", JvmError::ByteArrayConversion);
+            return JByteArray::default();
+        }
+    };
+
+    let normalized = match bytecode::normalize(&bytecode) {
+        Ok(normalized) => normalized,
+        Err(error) => {
+            error!("{}", error);
+            return JByteArray::default();
+        }
+    };
+
+    match env.byte_array_from_slice(&normalized.bytecode) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            error!("{}", JvmError::NewByteArray);
+            JByteArray::default()
+        }
+    }
+}
+
+/// External Java function to inspect bytecode contract.
+/// Returns the JSON description of the contract or `null` if the bytecode is malformed.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_inspectBytecode<'local>(
+    env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    bytecode: JByteArray<'local>,
+    disassemble: jboolean,
+) -> JString<'local> {
+    let bytecode = match env.convert_byte_array(bytecode) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            error!("{}", JvmError::ByteArrayConversion);
+            return JString::default();
+        }
+    };
+
+    let inspection = match Vm::inspect(&bytecode, disassemble != 0) {
+        Ok(inspection) => inspection,
+        Err(error) => {
+            error!("{}", error);
+            return JString::default();
+        }
+    };
+
+    match env.new_string(inspection.to_json()) {
+        Ok(inspection) => inspection,
+        Err(_) => {
+            error!("{}", JvmError::NewString);
+            JString::default()
+        }
+    }
+}
+
+/// External Java function to get the JSON manifest of host functions.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_manifest<'local>(
+    env: JNIEnv<'local>,
+    _class: JClass<'local>,
+) -> JString<'local> {
+    match env.new_string(modules::manifest()) {
+        Ok(manifest) => manifest,
+        Err(_) => {
+            error!("{}", JvmError::NewString);
+            JString::default()
+        }
+    }
+}
+
+/// External Java function to open the execution session of a block.
+/// Returns the handle of the session or `0` on failure.
+/// The handle can be used from any thread, calls to the same session are executed one by one.
+/// The session must be closed by `closeSession`.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_openSession<'local>(
     env: JNIEnv<'local>,
     _class: JClass<'local>,
+    callback: JObject<'local>,
+) -> jlong {
+    open_session(env, callback, jvm::limits())
+}
+
+/// External Java function to open the session of a block with the `ExecutionLimits` of the node.
+/// The fuel of the calls is passed to `runInSession`.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_openSessionWithLimits<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    callback: JObject<'local>,
+    limits: JObject<'local>,
+) -> jlong {
+    match execution_limits(&mut env, &limits) {
+        Ok(limits) => open_session(env, callback, limits),
+        Err(error) => {
+            error!("{}", error);
+            0
+        }
+    }
+}
+
+/// Reading the fields of the `ExecutionLimits` of the node, negative values are zero.
+#[cfg(feature = "jvm")]
+fn execution_limits(
+    env: &mut JNIEnv,
+    limits: &JObject,
+) -> std::result::Result<ExecutionLimits, JvmError> {
+    let mut int = |name: &str| {
+        env.call_method(limits, name, "()I", &[])
+            .and_then(|value| value.i())
+            .map(|value| value.max(0) as u32)
+            .map_err(|_| JvmError::ReceiveInt)
+    };
+
+    let memory = (int("memoryInitial")?, int("memoryMaximum")?);
+    let memory_limit = int("memoryLimit")?;
+    let call_depth = int("callDepth")? as usize;
+    let bytecode_size = int("bytecodeSize")? as usize;
+    let key_length = int("keyLength")? as usize;
+    let value_size = int("valueSize")? as usize;
+    let payments = int("payments")? as usize;
+    let libraries = int("libraries")? as usize;
+    let block_lookback = int("blockLookback")? as usize;
+
+    let timeout = env
+        .call_method(limits, "timeoutMillis", "()J", &[])
+        .and_then(|value| value.j())
+        .map_err(|_| JvmError::ReceiveLong)?;
+
+    Ok(ExecutionLimits {
+        memory,
+        memory_limit,
+        call_depth,
+        bytecode_size,
+        key_length,
+        value_size,
+        payments,
+        libraries,
+        block_lookback,
+        timeout: (timeout > 0).then(|| Duration::from_millis(timeout as u64)),
+        ..jvm::limits()
+    })
+}
+
+#[cfg(feature = "jvm")]
+fn open_session(env: JNIEnv, callback: JObject, limits: ExecutionLimits) -> jlong {
+    let jvm = match env.get_java_vm() {
+        Ok(jvm) => jvm,
+        Err(_) => {
+            error!("{}", JvmError::GetJavaVM);
+            return 0;
+        }
+    };
+
+    let callback = match env.new_global_ref(callback) {
+        Ok(callback) => callback,
+        Err(_) => {
+            error!("{}", JvmError::NewGlobalRef);
+            return 0;
+        }
+    };
+
+    let session = Session::new(Box::new(Jvm::new(jvm, callback)))
+        .with_limits(limits)
+        .with_external_functions(external::registered());
+    pool::open(session) as jlong
+}
+
+/// External Java function to execute bytecode contract in the session.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_runInSession<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    session: jlong,
+    contract_id: JByteArray<'local>,
     bytecode: JByteArray<'local>,
+    func_name: JString<'local>,
+    params: JByteArray<'local>,
+    fuel_limit: jlong,
 ) -> jint {
+    let contract_id = match env.convert_byte_array(contract_id) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            error!("{}", JvmError::ByteArrayConversion);
+            return JvmError::ByteArrayConversion.as_jint();
+        }
+    };
+
     let bytecode = match env.convert_byte_array(bytecode) {
         Ok(bytes) => bytes,
         Err(_) => {
@@ -170,8 +832,58 @@ pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_validateB
         }
     };
 
-    match Executable::validate_bytecode(&bytecode) {
-        Ok(_) => 0,
+    // The text format of the `dev` feature is never accepted from the node
+    #[cfg(feature = "dev")]
+    if !bytecode.starts_with(exec::WASM_MAGIC) {
+        let error =
+            error::ExecutableError::InvalidBytecode(String::from("Text format is not allowed"));
+        error!("{}", error);
+        return error.as_jint();
+    }
+
+    let func_name: String = match env.get_string(&func_name) {
+        Ok(string) => string.into(),
+        Err(_) => {
+            error!("{}", JvmError::NewString);
+            return JvmError::NewString.as_jint();
+        }
+    };
+
+    let params = match env.convert_byte_array(params) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            error!("{}", JvmError::ByteArrayConversion);
+            return JvmError::ByteArrayConversion.as_jint();
+        }
+    };
+
+    debug!(
+        "Started WEVM to execute the contract in the session: {}",
+        contract_id.to_base58()
+    );
+
+    let call = Call {
+        contract_id,
+        bytecode,
+        func_name,
+        params,
+        fuel_limit: fuel_limit as u64,
+    };
+
+    let result = match pool::with(session as pool::Handle, |session| session.execute(&call)) {
+        Ok(Ok(result)) => result,
+        Ok(Err(error)) => {
+            error!("{}", error);
+            return error.as_jint();
+        }
+        Err(error) => {
+            error!("{}", error);
+            return error.as_jint();
+        }
+    };
+
+    match DataEntry::from_results(&result) {
+        Ok((code, _)) => code as jint,
         Err(error) => {
             error!("{}", error);
             error.as_jint()
@@ -179,10 +891,125 @@ pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_validateB
     }
 }
 
+/// External Java function to cancel the running and following calls of the session,
+/// e.g. on the abort of the block or the shutdown of the node. Can be called from any thread,
+/// cancelled calls fail with `RuntimeError::Cancelled`.
 #[cfg(feature = "jvm")]
-fn modules() -> Vec<modules::Module> {
-    let mut vec = vec![];
-    vec.extend(v0::modules::modules());
-    vec.extend(v1::modules::modules());
-    vec
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_cancelSession<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    session: jlong,
+) -> jint {
+    match pool::cancel(session as pool::Handle) {
+        Ok(()) => 0,
+        Err(error) => {
+            error!("{}", error);
+            error.as_jint()
+        }
+    }
+}
+
+/// External Java function to close the session opened by `openSession`.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_closeSession<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    session: jlong,
+) -> jint {
+    match pool::close(session as pool::Handle) {
+        Ok(session) => {
+            session.finish();
+            0
+        }
+        Err(error) => {
+            error!("{}", error);
+            error.as_jint()
+        }
+    }
+}
+
+/// External Java function to register a host function available to contracts.
+/// The callback implements `long[] call(long[] args)`, the signature is in the form `i32 i64 -> i64`.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_registerHostFunction<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    module: JString<'local>,
+    name: JString<'local>,
+    signature: JString<'local>,
+    fuel: jlong,
+    callback: JObject<'local>,
+) -> jint {
+    let mut strings = vec![];
+    for string in [module, name, signature] {
+        match env.get_string(&string) {
+            Ok(string) => strings.push(String::from(string)),
+            Err(_) => {
+                error!("{}", JvmError::NewString);
+                return JvmError::NewString.as_jint();
+            }
+        }
+    }
+
+    let jvm = match env.get_java_vm() {
+        Ok(jvm) => jvm,
+        Err(_) => {
+            error!("{}", JvmError::GetJavaVM);
+            return JvmError::GetJavaVM.as_jint();
+        }
+    };
+
+    let callback = match env.new_global_ref(callback) {
+        Ok(callback) => callback,
+        Err(_) => {
+            error!("{}", JvmError::NewGlobalRef);
+            return JvmError::NewGlobalRef.as_jint();
+        }
+    };
+
+    let function = JvmFunction::new(jvm, callback);
+    match ExternalFunction::new(
+        &strings[0],
+        &strings[1],
+        &strings[2],
+        fuel as u64,
+        std::sync::Arc::new(move |args| function.call(args)),
+    ) {
+        Ok(function) => {
+            external::register(function);
+            0
+        }
+        Err(error) => {
+            error!("{}", error);
+            error.as_jint()
+        }
+    }
+}
+
+/// External Java function to remove a registered host function.
+/// Returns `1` if the function was registered, `0` otherwise.
+#[cfg(feature = "jvm")]
+#[no_mangle]
+pub extern "system" fn Java_com_wavesenterprise_wasm_core_WASMExecutor_unregisterHostFunction<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    module: JString<'local>,
+    name: JString<'local>,
+) -> jint {
+    let (module, name): (String, String) = match (env.get_string(&module), env.get_string(&name)) {
+        (Ok(module), Ok(name)) => (module.into(), name.into()),
+        _ => {
+            error!("{}", JvmError::NewString);
+            return JvmError::NewString.as_jint();
+        }
+    };
+
+    external::unregister(&module, &name) as jint
 }
@@ -0,0 +1,172 @@
+//! Limits of the execution passed by the node for each block.
+
+use crate::{
+    error::{Error, Result, RuntimeError},
+    runtime::payments::Payments,
+    MEMORY, MEMORY_LIMIT,
+};
+use std::time::Duration;
+
+/// Refunds of the fuel are bounded by this fraction of the fuel consumed by the call.
+pub const REFUND_QUOTIENT: u64 = 5;
+
+/// Limits of the calls executed by the `Vm`.
+///
+/// The defaults keep the limits used before the node passed them,
+/// the sizes that were not limited are unbounded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExecutionLimits {
+    /// Fuel available to each contract of the call stack, nested calls are given the full amount.
+    pub fuel: u64,
+    /// Initial and maximum pages of linear memory of an instance.
+    pub memory: (u32, u32),
+    /// Pages of linear memory available to all contracts of the call stack at once.
+    pub memory_limit: u32,
+    /// Contracts called by other contracts on the call stack.
+    pub call_depth: usize,
+    /// Size of the bytecode of a called contract in bytes.
+    pub bytecode_size: usize,
+    /// Length of the key of a data entry written by a contract.
+    pub key_length: usize,
+    /// Size of the value of a binary or string data entry written by a contract.
+    pub value_size: usize,
+    /// Assets paid by one call of another contract.
+    pub payments: usize,
+    /// Libraries linked to a contract, including the libraries of libraries.
+    pub libraries: usize,
+    /// Past blocks available to contracts, counted back from the current height.
+    pub block_lookback: usize,
+    /// Wall-clock time of the execution. The time differs between nodes,
+    /// so exceeding it must fail the block rather than the transaction.
+    pub timeout: Option<Duration>,
+    /// Fuel refunded for each existing data entry removed by a contract,
+    /// credited only if the call succeeds, see `Vm::fuel_refunded`.
+    pub deletion_refund: u64,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory: MEMORY,
+            memory_limit: MEMORY_LIMIT,
+            call_depth: 64,
+            bytecode_size: usize::MAX,
+            key_length: usize::MAX,
+            value_size: usize::MAX,
+            payments: Payments::LIMIT,
+            libraries: 16,
+            block_lookback: 1000,
+            timeout: None,
+            deletion_refund: 0,
+        }
+    }
+}
+
+impl ExecutionLimits {
+    /// Checking a size against its limit, fails with `RuntimeError::LimitExceeded`.
+    pub(crate) fn check(name: &str, size: usize, limit: usize) -> Result<()> {
+        match size > limit {
+            true => Err(Error::Runtime(RuntimeError::LimitExceeded(format!(
+                "{}: {} > {}",
+                name, size, limit
+            )))),
+            false => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::Mock,
+        modules,
+        vm::{ExecutionMode, Vm},
+    };
+
+    #[test]
+    fn test_execution_limits() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "get_block_timestamp" (func $get_block_timestamp (result i32 i64)))
+            (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (call $set_storage_int
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                    (i64.const 42)
+                )
+            )
+
+            (func (export "spin") (result i32)
+                (loop $loop
+                    (drop (call $get_block_timestamp))
+                    (drop)
+                    (br $loop)
+                )
+                (i32.const 0)
+            )
+
+            (data (i32.const 0) "counter")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let run = |limits: ExecutionLimits, func_name: &str| {
+            let mut vm = Vm::new(
+                vec![],
+                bytecode.clone(),
+                limits,
+                modules::all(),
+                Box::new(Mock::new()),
+                ExecutionMode::Apply,
+            )
+            .expect("Call stack creation failed");
+
+            vm.run(func_name, &[0, 0])
+                .map(|values| values[0].i32())
+                .map_err(|error| error.error)
+        };
+
+        let limits = ExecutionLimits::default();
+        assert_eq!(run(limits, "_constructor"), Ok(Some(0)));
+
+        // Errors of host functions are returned to the contract
+        let result = run(
+            ExecutionLimits {
+                key_length: 6,
+                ..limits
+            },
+            "_constructor",
+        );
+        assert_eq!(result, Ok(Some(320)));
+
+        let result = run(
+            ExecutionLimits {
+                bytecode_size: bytecode.len() - 1,
+                ..limits
+            },
+            "_constructor",
+        );
+        assert!(matches!(
+            result,
+            Err(Error::Runtime(RuntimeError::LimitExceeded(_)))
+        ));
+
+        let result = run(
+            ExecutionLimits {
+                fuel: u64::MAX,
+                timeout: Some(Duration::from_millis(10)),
+                ..limits
+            },
+            "spin",
+        );
+        assert_eq!(result, Err(Error::Runtime(RuntimeError::Timeout)));
+    }
+}
@@ -0,0 +1,223 @@
+use jni::{
+    objects::{GlobalRef, JValue},
+    JNIEnv, JavaVM,
+};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{cell::Cell, fmt::Write, sync::Arc, time::Instant};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+thread_local! {
+    // Set while a record is forwarded, so the logs of the JNI calls are not forwarded again
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Logger forwarding records to the SLF4J logger of the node.
+/// Clones share the reference to the logger of the node.
+#[derive(Clone)]
+pub struct JvmLogger(Arc<Bridge>);
+
+struct Bridge {
+    jvm: JavaVM,
+    logger: GlobalRef,
+    level: LevelFilter,
+}
+
+impl JvmLogger {
+    pub fn new(jvm: JavaVM, logger: GlobalRef, level: LevelFilter) -> Self {
+        Self(Arc::new(Bridge { jvm, logger, level }))
+    }
+
+    /// Installing the logger as the global logger of `log` records
+    /// and the global subscriber of `tracing` spans.
+    /// Fails if a global logger or subscriber is already installed.
+    pub fn init(self) -> Result<(), Box<dyn std::error::Error>> {
+        let level = self.0.level;
+        log::set_boxed_logger(Box::new(self.clone()))?;
+        log::set_max_level(level);
+
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry().with(JvmLayer(self)),
+        )?;
+        Ok(())
+    }
+
+    /// Calling the method of the logger of the node matching the level.
+    fn forward(&self, level: Level, message: &str) {
+        if FORWARDING.with(|forwarding| forwarding.replace(true)) {
+            return;
+        }
+
+        // Errors are ignored since there is nowhere to report them
+        if let Ok(mut env) = crate::jvm::attach(&self.0.jvm) {
+            let _ = self.call_logger(&mut env, level, message);
+        }
+
+        FORWARDING.with(|forwarding| forwarding.set(false));
+    }
+
+    fn call_logger(
+        &self,
+        env: &mut JNIEnv,
+        level: Level,
+        message: &str,
+    ) -> jni::errors::Result<()> {
+        // No JNI calls are allowed while an exception is pending,
+        // it belongs to the failed callback and is reported by it
+        if env.exception_check()? {
+            return Ok(());
+        }
+
+        let method = match level {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+
+        // Local references are freed with the frame, the thread may stay attached for long
+        env.with_local_frame(1, |env| {
+            let message = env.new_string(message)?;
+
+            if env
+                .call_method(
+                    &self.0.logger,
+                    method,
+                    "(Ljava/lang/String;)V",
+                    &[JValue::Object(&message)],
+                )
+                .is_err()
+            {
+                env.exception_clear()?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl Log for JvmLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.0.level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.forward(
+                record.level(),
+                &format!("[{}] {}", record.target(), record.args()),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Layer forwarding the spans and events of `tracing` to the logger of the node.
+/// Entering and exiting spans are logged at the `trace` level.
+struct JvmLayer(JvmLogger);
+
+/// Name and fields of a span, with the time it was entered.
+struct SpanData {
+    name: String,
+    start: Option<Instant>,
+}
+
+/// Formatting the fields of a span or an event as `name=value` pairs.
+#[derive(Default)]
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.0, " {:?}", value),
+            name => write!(self.0, " {}={:?}", name, value),
+        };
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let _ = match field.name() {
+            "message" => write!(self.0, " {}", value),
+            name => write!(self.0, " {}={}", name, value),
+        };
+    }
+}
+
+fn log_level(level: &tracing::Level) -> Level {
+    match *level {
+        tracing::Level::ERROR => Level::Error,
+        tracing::Level::WARN => Level::Warn,
+        tracing::Level::INFO => Level::Info,
+        tracing::Level::DEBUG => Level::Debug,
+        tracing::Level::TRACE => Level::Trace,
+    }
+}
+
+impl<S> Layer<S> for JvmLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, metadata: &tracing::Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        log_level(metadata.level()) <= self.0 .0.level
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanData {
+                name: format!("{}{}", attrs.metadata().name(), fields.0),
+                start: None,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let message = match span.extensions_mut().get_mut::<SpanData>() {
+            Some(data) => {
+                data.start = Some(Instant::now());
+                format!("Enter: {}", data.name)
+            }
+            None => return,
+        };
+
+        self.0.forward(Level::Trace, &message);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let message = match span.extensions().get::<SpanData>() {
+            Some(data) => format!(
+                "Exit: {}. Elapsed: {:?}",
+                data.name,
+                data.start.map(|start| start.elapsed()).unwrap_or_default()
+            ),
+            None => return,
+        };
+
+        self.0.forward(Level::Trace, &message);
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        self.0.forward(
+            log_level(event.metadata().level()),
+            &format!("[{}]{}", event.metadata().target(), fields.0),
+        );
+    }
+}
@@ -0,0 +1,890 @@
+use crate::{
+    error::{Error, Result, RuntimeError},
+    node::Node,
+    runtime::{data_entry::DataEntry, payments::Payments, utils},
+};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use sha3::Keccak256;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const NONCE_LENGTH: usize = 8;
+
+/// Changes of the node state made by the contract, except for storage writes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    Transfer {
+        contract_id: Vec<u8>,
+        asset_id: Vec<u8>,
+        recipient: Vec<u8>,
+        amount: i64,
+    },
+    Issue {
+        contract_id: Vec<u8>,
+        asset_id: Vec<u8>,
+        name: Vec<u8>,
+        description: Vec<u8>,
+        quantity: i64,
+        decimals: i64,
+        is_reissuable: bool,
+    },
+    Burn {
+        contract_id: Vec<u8>,
+        asset_id: Vec<u8>,
+        amount: i64,
+    },
+    Reissue {
+        contract_id: Vec<u8>,
+        asset_id: Vec<u8>,
+        amount: i64,
+        is_reissuable: bool,
+    },
+    Lease {
+        contract_id: Vec<u8>,
+        lease_id: Vec<u8>,
+        recipient: Vec<u8>,
+        amount: i64,
+    },
+    CancelLease {
+        contract_id: Vec<u8>,
+        lease_id: Vec<u8>,
+    },
+    Payments {
+        contract_id: Vec<u8>,
+        payment_id: Vec<u8>,
+        payments: Vec<(Vec<u8>, i64)>,
+    },
+}
+
+/// Storage write made by the contract.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Write {
+    pub contract_id: Vec<u8>,
+    pub key: Vec<u8>,
+    pub value: DataEntry,
+}
+
+/// Lease kept after its cancellation, so its state can still be queried.
+#[derive(Clone)]
+struct Lease {
+    sender: Vec<u8>,
+    recipient: Vec<u8>,
+    amount: i64,
+    active: bool,
+}
+
+// Maps are ordered, so the state is traversed in the same order on every run
+#[derive(Clone)]
+struct State {
+    chain_id: i8,
+    timestamp: i64,
+    height: i64,
+    // Derived from the height if not set
+    vrf: Option<Vec<u8>>,
+    tx_sender: Vec<u8>,
+    tx_fee: i64,
+    tx_fee_asset_id: Vec<u8>,
+    tx_fee_sponsored: bool,
+    error_message: Vec<u8>,
+    bytecodes: BTreeMap<Vec<u8>, Vec<u8>>,
+    // Balances by asset identifier and holder
+    balances: BTreeMap<(Vec<u8>, Vec<u8>), i64>,
+    leases: BTreeMap<Vec<u8>, Lease>,
+    // Timestamps and generators of past blocks by height
+    blocks: BTreeMap<i64, (i64, Vec<u8>)>,
+    // Serialized data entries by contract identifier and key
+    storage: BTreeMap<(Vec<u8>, Vec<u8>), Vec<u8>>,
+    // Serialized data entries of accounts by address and key
+    account_data: BTreeMap<(Vec<u8>, Vec<u8>), Vec<u8>>,
+    payments: BTreeMap<Vec<u8>, Vec<(Vec<u8>, i64)>>,
+    roles: BTreeMap<Vec<u8>, BTreeSet<i32>>,
+    paused: BTreeSet<Vec<u8>>,
+    features: BTreeMap<String, bool>,
+    write_set: Vec<(Vec<u8>, Vec<u8>)>,
+    events: Vec<Event>,
+    snapshots: Vec<State>,
+}
+
+/// In-memory implementation of the node, ported from the Scala simulator.
+/// Allows to execute contracts without a running node.
+///
+/// Clones share the same state, so one clone can be passed to the `Vm`
+/// while the other is used to prepare the state and inspect the results.
+#[derive(Clone)]
+pub struct Mock(Arc<Mutex<State>>);
+
+impl Default for Mock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as i64)
+            .unwrap_or_default();
+
+        Self(Arc::new(Mutex::new(State {
+            chain_id: b'V' as i8,
+            timestamp,
+            height: 1,
+            vrf: None,
+            tx_sender: vec![],
+            tx_fee: 0,
+            tx_fee_asset_id: vec![],
+            tx_fee_sponsored: false,
+            error_message: vec![],
+            bytecodes: Default::default(),
+            balances: Default::default(),
+            leases: Default::default(),
+            blocks: Default::default(),
+            storage: Default::default(),
+            account_data: Default::default(),
+            payments: Default::default(),
+            roles: Default::default(),
+            paused: Default::default(),
+            features: Default::default(),
+            write_set: vec![],
+            events: vec![],
+            snapshots: vec![],
+        })))
+    }
+
+    pub fn set_chain_id(&self, chain_id: i8) {
+        self.state().chain_id = chain_id;
+    }
+
+    pub fn set_timestamp(&self, timestamp: i64) {
+        self.state().timestamp = timestamp;
+    }
+
+    pub fn set_height(&self, height: i64) {
+        self.state().height = height;
+    }
+
+    pub fn set_vrf(&self, vrf: &[u8]) {
+        self.state().vrf = Some(vrf.to_vec());
+    }
+
+    pub fn set_block(&self, height: i64, timestamp: i64, generator: &[u8]) {
+        self.state()
+            .blocks
+            .insert(height, (timestamp, generator.to_vec()));
+    }
+
+    pub fn set_tx_sender(&self, sender: &[u8]) {
+        self.state().tx_sender = sender.to_vec();
+    }
+
+    pub fn set_tx_fee(&self, fee: i64, asset_id: &[u8], sponsored: bool) {
+        let mut state = self.state();
+        state.tx_fee = fee;
+        state.tx_fee_asset_id = asset_id.to_vec();
+        state.tx_fee_sponsored = sponsored;
+    }
+
+    pub fn set_bytecode(&self, contract_id: &[u8], bytecode: Vec<u8>) {
+        self.state()
+            .bytecodes
+            .insert(contract_id.to_vec(), bytecode);
+    }
+
+    pub fn bytecode(&self, contract_id: &[u8]) -> Option<Vec<u8>> {
+        self.state().bytecodes.get(contract_id).cloned()
+    }
+
+    pub fn set_paused(&self, contract_id: &[u8], value: bool) {
+        let mut state = self.state();
+        if value {
+            state.paused.insert(contract_id.to_vec());
+        } else {
+            state.paused.remove(contract_id);
+        }
+    }
+
+    /// Features are considered activated unless stated otherwise.
+    pub fn set_feature_activated(&self, feature: &str, value: bool) {
+        self.state().features.insert(feature.to_string(), value);
+    }
+
+    pub fn add_role(&self, address: &[u8], role_id: i32) {
+        self.state()
+            .roles
+            .entry(address.to_vec())
+            .or_default()
+            .insert(role_id);
+    }
+
+    /// Adding a payment attached to the transaction calling the contract.
+    pub fn add_payment(&self, contract_id: &[u8], asset_id: &[u8], amount: i64) {
+        let mut payment_id = contract_id.to_vec();
+        payment_id.extend_from_slice(&[0; NONCE_LENGTH]);
+
+        self.state()
+            .payments
+            .entry(payment_id)
+            .or_default()
+            .push((asset_id.to_vec(), amount));
+    }
+
+    /// Getting the balance of the holder. The holder is an address or a contract identifier,
+    /// an empty `asset_id` stands for the native token.
+    pub fn balance(&self, asset_id: &[u8], holder: &[u8]) -> i64 {
+        self.state()
+            .balances
+            .get(&(asset_id.to_vec(), holder.to_vec()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_balance(&self, asset_id: &[u8], holder: &[u8], amount: i64) {
+        self.state()
+            .balances
+            .insert((asset_id.to_vec(), holder.to_vec()), amount);
+    }
+
+    pub fn storage(&self, contract_id: &[u8], key: &[u8]) -> Option<DataEntry> {
+        self.state()
+            .storage
+            .get(&(contract_id.to_vec(), key.to_vec()))
+            .and_then(|bytes| DataEntry::deserialize(bytes).ok())
+    }
+
+    pub fn set_storage_entry(&self, contract_id: &[u8], key: &[u8], value: DataEntry) {
+        self.state().storage.insert(
+            (contract_id.to_vec(), key.to_vec()),
+            value.serialize(Some(key)),
+        );
+    }
+
+    /// Publishing the entry by a data transaction of the account.
+    pub fn set_account_data(&self, address: &[u8], key: &[u8], value: DataEntry) {
+        self.state()
+            .account_data
+            .insert((address.to_vec(), key.to_vec()), value.serialize(Some(key)));
+    }
+
+    /// Message passed by the contract to `require`.
+    pub fn error_message(&self) -> Vec<u8> {
+        self.state().error_message.clone()
+    }
+
+    /// Storage writes made since the last reset in the order of execution.
+    pub fn write_set(&self) -> Vec<Write> {
+        self.state()
+            .write_set
+            .iter()
+            .filter_map(|(contract_id, bytes)| {
+                let mut offset = 0;
+                let length = utils::get_u16(bytes, &mut offset).ok()?;
+                let key = utils::get_bytes(bytes, &mut offset, length as usize).ok()?;
+                let value = DataEntry::deserialize(bytes).ok()?;
+
+                Some(Write {
+                    contract_id: contract_id.clone(),
+                    key,
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    /// Events emitted since the last reset in the order of execution.
+    pub fn events(&self) -> Vec<Event> {
+        self.state().events.clone()
+    }
+
+    /// Clearing the write set, the events, the error message and the payments
+    /// of the transaction. The state is kept.
+    pub fn reset(&self) {
+        let mut state = self.state();
+        state.payments.clear();
+        state.write_set.clear();
+        state.events.clear();
+        state.error_message.clear();
+    }
+
+    /// Locking the state, a panicked test doesn't poison the clones.
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn blake2b256(bytes: &[u8]) -> Vec<u8> {
+        let mut hasher: Blake2b<U32> = Blake2b::new();
+        hasher.update(bytes);
+        hasher.finalize().to_vec()
+    }
+
+    fn keccak256(bytes: &[u8]) -> Vec<u8> {
+        let mut hasher = Keccak256::new();
+        hasher.update(bytes);
+        hasher.finalize().to_vec()
+    }
+
+    /// Parsing the asset holder into its type and address or contract identifier.
+    fn parse_asset_holder(&self, bytes: &[u8]) -> Result<(u8, Vec<u8>)> {
+        let chain_id = self.state().chain_id as u8;
+
+        match bytes {
+            [0, 1, chain, ..] if *chain == chain_id => Ok((0, bytes[1..].to_vec())),
+            [0, 2, chain, ..] if *chain == chain_id => Ok((0, bytes[3..].to_vec())),
+            [1, ..] => Ok((1, bytes[1..].to_vec())),
+            _ => Err(exception("Invalid asset holder")),
+        }
+    }
+
+    fn move_balance(
+        &self,
+        asset_id: &[u8],
+        sender: &[u8],
+        recipient: &[u8],
+        amount: i64,
+    ) -> Result<()> {
+        let balance_sender = self.balance(asset_id, sender);
+        if amount < 0 || balance_sender < amount {
+            return Err(exception("Insufficient funds"));
+        }
+
+        self.set_balance(asset_id, sender, balance_sender - amount);
+        let balance_recipient = self.balance(asset_id, recipient);
+        self.set_balance(asset_id, recipient, balance_recipient + amount);
+
+        Ok(())
+    }
+
+    fn payments(&self, payment_id: &[u8], number: i64) -> Result<(Vec<u8>, i64)> {
+        self.state()
+            .payments
+            .get(payment_id)
+            .and_then(|payments| payments.get(usize::try_from(number).ok()?))
+            .cloned()
+            .ok_or_else(|| exception("Payment not found"))
+    }
+
+    fn emit(&self, event: Event) {
+        self.state().events.push(event);
+    }
+}
+
+fn exception(message: &str) -> Error {
+    Error::Runtime(RuntimeError::Exception(message.to_string()))
+}
+
+impl Node for Mock {
+    fn get_chain_id(&self) -> Result<i8> {
+        Ok(self.state().chain_id)
+    }
+
+    fn require(&self, message: &[u8]) -> Result<()> {
+        self.state().error_message = message.to_vec();
+        Ok(())
+    }
+
+    fn get_bytecode(&self, contract_id: &[u8]) -> Result<Vec<u8>> {
+        self.state()
+            .bytecodes
+            .get(contract_id)
+            .cloned()
+            .ok_or_else(|| exception("Bytecode not found"))
+    }
+
+    fn add_payments(&self, contract_id: &[u8], payment_id: &[u8], payments: &[u8]) -> Result<()> {
+        let callable_contract_id = payment_id
+            .get(..payment_id.len().saturating_sub(NONCE_LENGTH))
+            .unwrap_or_default();
+
+        let mut result = vec![];
+        for (asset_id, amount) in Payments::from_bytes(payments)?.iter() {
+            self.move_balance(asset_id, contract_id, callable_contract_id, amount)?;
+            result.push((asset_id.to_vec(), amount));
+        }
+
+        self.state()
+            .payments
+            .entry(payment_id.to_vec())
+            .or_default()
+            .extend(result.clone());
+
+        self.emit(Event::Payments {
+            contract_id: contract_id.to_vec(),
+            payment_id: payment_id.to_vec(),
+            payments: result,
+        });
+
+        Ok(())
+    }
+
+    fn is_paused(&self, contract_id: &[u8]) -> Result<bool> {
+        Ok(self.state().paused.contains(contract_id))
+    }
+
+    fn is_feature_activated(&self, feature: &[u8]) -> Result<bool> {
+        let feature = String::from_utf8_lossy(feature);
+        Ok(self
+            .state()
+            .features
+            .get(feature.as_ref())
+            .copied()
+            .unwrap_or(true))
+    }
+
+    fn get_balance(&self, asset_id: &[u8], address: &[u8]) -> Result<i64> {
+        let (_, holder) = self.parse_asset_holder(address)?;
+        Ok(self.balance(asset_id, &holder))
+    }
+
+    fn transfer(
+        &self,
+        contract_id: &[u8],
+        asset_id: &[u8],
+        recipient: &[u8],
+        amount: i64,
+    ) -> Result<()> {
+        let (_, holder) = self.parse_asset_holder(recipient)?;
+        self.move_balance(asset_id, contract_id, &holder, amount)?;
+
+        self.emit(Event::Transfer {
+            contract_id: contract_id.to_vec(),
+            asset_id: asset_id.to_vec(),
+            recipient: holder,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    fn issue(
+        &self,
+        contract_id: &[u8],
+        name: &[u8],
+        description: &[u8],
+        quantity: i64,
+        decimals: i64,
+        is_reissuable: bool,
+    ) -> Result<Vec<u8>> {
+        let asset_id = self.secure_hash(&[name, description].concat())?;
+        self.set_balance(&asset_id, contract_id, quantity);
+
+        self.emit(Event::Issue {
+            contract_id: contract_id.to_vec(),
+            asset_id: asset_id.clone(),
+            name: name.to_vec(),
+            description: description.to_vec(),
+            quantity,
+            decimals,
+            is_reissuable,
+        });
+
+        Ok(asset_id)
+    }
+
+    fn burn(&self, contract_id: &[u8], asset_id: &[u8], amount: i64) -> Result<()> {
+        let balance = self.balance(asset_id, contract_id);
+        if amount < 0 || balance < amount {
+            return Err(exception("Insufficient funds"));
+        }
+
+        self.set_balance(asset_id, contract_id, balance - amount);
+
+        self.emit(Event::Burn {
+            contract_id: contract_id.to_vec(),
+            asset_id: asset_id.to_vec(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    fn reissue(
+        &self,
+        contract_id: &[u8],
+        asset_id: &[u8],
+        amount: i64,
+        is_reissuable: bool,
+    ) -> Result<()> {
+        let balance = self.balance(asset_id, contract_id);
+        self.set_balance(asset_id, contract_id, balance + amount);
+
+        self.emit(Event::Reissue {
+            contract_id: contract_id.to_vec(),
+            asset_id: asset_id.to_vec(),
+            amount,
+            is_reissuable,
+        });
+
+        Ok(())
+    }
+
+    fn block(&self, field: &[u8]) -> Result<Vec<u8>> {
+        let state = self.state();
+
+        match field {
+            b"timestamp" => Ok(state.timestamp.to_be_bytes().to_vec()),
+            b"height" => Ok(state.height.to_be_bytes().to_vec()),
+            b"vrf" => Ok(state
+                .vrf
+                .clone()
+                .unwrap_or_else(|| Self::blake2b256(&state.height.to_be_bytes()))),
+            b"signature" => Ok(Self::keccak256(&state.height.to_be_bytes())),
+            _ => Err(exception("Unknown block field")),
+        }
+    }
+
+    fn get_block_at(&self, height: i64) -> Result<Vec<u8>> {
+        let state = self.state();
+        let (timestamp, generator) = state
+            .blocks
+            .get(&height)
+            .ok_or_else(|| exception("Block not found"))?;
+
+        Ok([&timestamp.to_be_bytes()[..], generator].concat())
+    }
+
+    fn fast_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(Self::blake2b256(bytes))
+    }
+
+    fn secure_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(Self::keccak256(&Self::blake2b256(bytes)))
+    }
+
+    fn sig_verify(&self, _message: &[u8], _signature: &[u8], _public_key: &[u8]) -> Result<bool> {
+        Err(exception("Signature verification is not supported"))
+    }
+
+    fn lease(&self, contract_id: &[u8], recipient: &[u8], amount: i64) -> Result<Vec<u8>> {
+        let (type_, holder) = self.parse_asset_holder(recipient)?;
+        if type_ == 1 {
+            return Err(exception("Leasing to a contract is not allowed"));
+        }
+
+        let lease_id = self.secure_hash(&[contract_id, recipient].concat())?;
+        self.state().leases.insert(
+            lease_id.clone(),
+            Lease {
+                sender: contract_id.to_vec(),
+                recipient: holder.clone(),
+                amount,
+                active: true,
+            },
+        );
+
+        self.emit(Event::Lease {
+            contract_id: contract_id.to_vec(),
+            lease_id: lease_id.clone(),
+            recipient: holder,
+            amount,
+        });
+
+        Ok(lease_id)
+    }
+
+    fn cancel_lease(&self, contract_id: &[u8], lease_id: &[u8]) -> Result<()> {
+        match self.state().leases.get_mut(lease_id) {
+            Some(lease) if lease.active => lease.active = false,
+            _ => return Err(exception("Lease not found")),
+        }
+
+        self.emit(Event::CancelLease {
+            contract_id: contract_id.to_vec(),
+            lease_id: lease_id.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    fn get_lease_info(&self, lease_id: &[u8]) -> Result<Vec<u8>> {
+        let state = self.state();
+        let lease = state
+            .leases
+            .get(lease_id)
+            .ok_or_else(|| exception("Lease not found"))?;
+
+        let mut result = vec![lease.active as u8];
+        result.extend_from_slice(&lease.amount.to_be_bytes());
+        result.push(lease.sender.len() as u8);
+        result.extend_from_slice(&lease.sender);
+        result.extend_from_slice(&lease.recipient);
+        Ok(result)
+    }
+
+    fn check_permission(&self, address: &[u8], role_id: i32) -> Result<bool> {
+        Ok(self
+            .state()
+            .roles
+            .get(address)
+            .is_some_and(|roles| roles.contains(&role_id)))
+    }
+
+    fn contains_key(&self, address: &[u8], key: &[u8]) -> Result<bool> {
+        if key.is_empty() {
+            return Err(exception("Empty key"));
+        }
+
+        Ok(self
+            .state()
+            .storage
+            .contains_key(&(address.to_vec(), key.to_vec())))
+    }
+
+    fn get_storage(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        if key.is_empty() {
+            return Err(exception("Empty key"));
+        }
+
+        Ok(self
+            .state()
+            .storage
+            .get(&(address.to_vec(), key.to_vec()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn set_storage(&self, contract_id: &[u8], value: &[u8]) -> Result<()> {
+        let mut offset = 0;
+        let length = utils::get_u16(value, &mut offset)?;
+        let key = utils::get_bytes(value, &mut offset, length as usize)?;
+        // Checking that the value is a valid data entry
+        DataEntry::deserialize(value)?;
+
+        let mut state = self.state();
+        state
+            .storage
+            .insert((contract_id.to_vec(), key), value.to_vec());
+        state.write_set.push((contract_id.to_vec(), value.to_vec()));
+
+        Ok(())
+    }
+
+    fn delete_storage(&self, contract_id: &[u8], key: &[u8]) -> Result<bool> {
+        if key.is_empty() {
+            return Err(exception("Empty key"));
+        }
+
+        Ok(self
+            .state()
+            .storage
+            .remove(&(contract_id.to_vec(), key.to_vec()))
+            .is_some())
+    }
+
+    fn get_account_data(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        if key.is_empty() {
+            return Err(exception("Empty key"));
+        }
+
+        Ok(self
+            .state()
+            .account_data
+            .get(&(address.to_vec(), key.to_vec()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_tx_payments(&self, payment_id: &[u8]) -> Result<i64> {
+        Ok(self
+            .state()
+            .payments
+            .get(payment_id)
+            .map(|payments| payments.len() as i64)
+            .unwrap_or_default())
+    }
+
+    fn get_tx_payment_asset_id(&self, payment_id: &[u8], number: i64) -> Result<Vec<u8>> {
+        self.payments(payment_id, number)
+            .map(|(asset_id, _)| asset_id)
+    }
+
+    fn get_tx_payment_amount(&self, payment_id: &[u8], number: i64) -> Result<i64> {
+        self.payments(payment_id, number).map(|(_, amount)| amount)
+    }
+
+    fn tx(&self, field: &[u8]) -> Result<Vec<u8>> {
+        let state = self.state();
+
+        match field {
+            b"sender" => Ok(state.tx_sender.clone()),
+            b"id" => Ok(Self::keccak256(&Self::blake2b256(
+                &state.timestamp.to_be_bytes(),
+            ))),
+            b"fee" => Ok(state.tx_fee.to_be_bytes().to_vec()),
+            b"feeAssetId" => Ok(state.tx_fee_asset_id.clone()),
+            b"feeSponsored" => Ok(vec![state.tx_fee_sponsored as u8]),
+            _ => Err(exception("Unknown transaction field")),
+        }
+    }
+
+    fn snapshot(&self) -> Result<i32> {
+        let mut state = self.state();
+
+        let snapshots = std::mem::take(&mut state.snapshots);
+        let snapshot = state.clone();
+        state.snapshots = snapshots;
+        state.snapshots.push(snapshot);
+
+        Ok(state.snapshots.len() as i32 - 1)
+    }
+
+    fn restore(&self, snapshot: i32) -> Result<()> {
+        let mut state = self.state();
+
+        let index = snapshot as usize;
+        if index >= state.snapshots.len() {
+            return Err(exception("Snapshot not found"));
+        }
+
+        let mut snapshots = std::mem::take(&mut state.snapshots);
+        let mut saved = snapshots.swap_remove(index);
+        snapshots.truncate(index);
+
+        // The message of a failed `require` is kept
+        saved.error_message = std::mem::take(&mut state.error_message);
+        saved.snapshots = snapshots;
+        *state = saved;
+
+        Ok(())
+    }
+
+    fn discard(&self, snapshot: i32) -> Result<()> {
+        let mut state = self.state();
+
+        let index = snapshot as usize;
+        if index >= state.snapshots.len() {
+            return Err(exception("Snapshot not found"));
+        }
+        state.snapshots.truncate(index);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT_ID: [u8; 32] = [1; 32];
+
+    #[test]
+    fn test_storage() {
+        let mock = Mock::new();
+
+        let value = DataEntry::Integer(42).serialize(Some(b"key"));
+        mock.set_storage(&CONTRACT_ID, &value)
+            .expect("Failed set storage");
+
+        assert_eq!(
+            mock.storage(&CONTRACT_ID, b"key"),
+            Some(DataEntry::Integer(42))
+        );
+        assert_eq!(
+            mock.get_storage(&CONTRACT_ID, b"key")
+                .expect("Failed get storage"),
+            value
+        );
+        assert_eq!(
+            mock.write_set(),
+            vec![Write {
+                contract_id: CONTRACT_ID.to_vec(),
+                key: b"key".to_vec(),
+                value: DataEntry::Integer(42),
+            }]
+        );
+
+        mock.reset();
+        assert!(mock.write_set().is_empty());
+        assert!(mock.contains_key(&CONTRACT_ID, b"key").unwrap_or(false));
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mock = Mock::new();
+        let address = [1, b'V', 2, 3];
+        let mut recipient = vec![0];
+        recipient.extend_from_slice(&address);
+
+        mock.set_balance(&[], &CONTRACT_ID, 100);
+        mock.transfer(&CONTRACT_ID, &[], &recipient, 40)
+            .expect("Failed transfer");
+
+        assert_eq!(mock.balance(&[], &CONTRACT_ID), 60);
+        assert_eq!(
+            mock.get_balance(&[], &recipient)
+                .expect("Failed get balance"),
+            40
+        );
+        assert_eq!(
+            mock.events(),
+            vec![Event::Transfer {
+                contract_id: CONTRACT_ID.to_vec(),
+                asset_id: vec![],
+                recipient: address.to_vec(),
+                amount: 40,
+            }]
+        );
+
+        assert!(mock.transfer(&CONTRACT_ID, &[], &recipient, 100).is_err());
+    }
+
+    #[test]
+    fn test_order() {
+        let mock = Mock::new();
+        mock.set_balance(&[], &CONTRACT_ID, 100);
+
+        for key in [b"b", b"a", b"b"] {
+            mock.set_storage(&CONTRACT_ID, &DataEntry::Integer(1).serialize(Some(key)))
+                .expect("Failed set storage");
+        }
+        mock.burn(&CONTRACT_ID, &[], 2).expect("Failed burn");
+        mock.burn(&CONTRACT_ID, &[], 1).expect("Failed burn");
+
+        // Writes and events are kept in the order of execution, repeated writes included
+        let keys: Vec<Vec<u8>> = mock
+            .write_set()
+            .into_iter()
+            .map(|write| write.key)
+            .collect();
+        assert_eq!(keys, vec![b"b".to_vec(), b"a".to_vec(), b"b".to_vec()]);
+
+        let amounts: Vec<i64> = mock
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Burn { amount, .. } => Some(amount),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(amounts, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mock = Mock::new();
+        mock.set_balance(&[], &CONTRACT_ID, 100);
+
+        let snapshot = mock.snapshot().expect("Failed snapshot");
+        mock.set_storage(
+            &CONTRACT_ID,
+            &DataEntry::Integer(42).serialize(Some(b"key")),
+        )
+        .expect("Failed set storage");
+        mock.burn(&CONTRACT_ID, &[], 40).expect("Failed burn");
+
+        let nested = mock.snapshot().expect("Failed snapshot");
+        mock.burn(&CONTRACT_ID, &[], 10).expect("Failed burn");
+        mock.discard(nested).expect("Failed discard");
+        assert_eq!(mock.balance(&[], &CONTRACT_ID), 50);
+
+        mock.restore(snapshot).expect("Failed restore");
+        assert_eq!(mock.balance(&[], &CONTRACT_ID), 100);
+        assert_eq!(mock.storage(&CONTRACT_ID, b"key"), None);
+        assert!(mock.write_set().is_empty());
+        assert!(mock.events().is_empty());
+
+        assert!(mock.restore(snapshot).is_err());
+    }
+}
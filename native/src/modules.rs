@@ -1,10 +1,147 @@
-pub mod v0;
-pub mod v1;
-
-#[cfg(not(feature = "bindings"))]
-use crate::runtime::Runtime;
-#[cfg(not(feature = "bindings"))]
+#[cfg(feature = "jvm")]
+use crate::{error::Result, runtime::Runtime};
+#[cfg(feature = "jvm")]
 use wasmi::{Func, Store};
 
-#[cfg(not(feature = "bindings"))]
-pub type Module = fn(&mut Store<Runtime>) -> (String, String, Func);
+/// Function linking a host function, fails if the node can't be asked about its feature.
+#[cfg(feature = "jvm")]
+pub type Module = fn(&mut Store<Runtime>) -> Result<(String, String, Func)>;
+
+/// Declares the versions of env modules.
+/// Every function of a declared version is linked and added to the manifest.
+macro_rules! versions {
+    ($( $version:ident ),* $(,)?) => {
+        $( pub mod $version; )*
+
+        /// Names of the declared versions of env modules.
+        #[cfg(test)]
+        const VERSIONS: &[&str] = &[$( stringify!($version) ),*];
+
+        /// Getting the functions of all declared env modules.
+        #[cfg(feature = "jvm")]
+        pub fn all() -> Vec<Module> {
+            let mut vec = vec![];
+            $( vec.extend($version::modules::modules()); )*
+            vec
+        }
+
+        /// Getting descriptions of the functions of all declared env modules.
+        pub fn host_functions() -> impl Iterator<Item = &'static HostFunction> {
+            [$( $version::MANIFEST ),*].into_iter().flatten()
+        }
+    };
+}
+
+versions!(v0, v1);
+
+/// Description of a host function available to contracts.
+#[derive(Debug)]
+pub struct HostFunction {
+    /// Name of the import module.
+    pub module: &'static str,
+    /// Name of the imported function.
+    pub name: &'static str,
+    /// Version of the import module.
+    pub version: u32,
+    /// Names and WASM value types of the parameters.
+    pub params: &'static [(&'static str, &'static str)],
+    /// WASM value types of the results.
+    pub results: &'static [&'static str],
+    /// Feature of the node activating the function.
+    pub feature: Option<&'static str>,
+}
+
+/// Getting the JSON manifest of all host functions.
+pub fn manifest() -> String {
+    let functions: Vec<serde_json::Value> = host_functions()
+        .map(|function| {
+            let params: Vec<serde_json::Value> = function
+                .params
+                .iter()
+                .map(|(name, type_)| serde_json::json!({ "name": name, "type": type_ }))
+                .collect();
+
+            serde_json::json!({
+                "module": function.module,
+                "name": function.name,
+                "version": function.version,
+                "params": params,
+                "results": function.results,
+                "feature": function.feature,
+            })
+        })
+        .collect();
+
+    serde_json::Value::Array(functions).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let manifest = manifest();
+
+        assert!(
+            manifest.starts_with("[{\"module\":\"env0\",\"name\":\"get_balance\",\"version\":0,")
+        );
+        assert!(manifest.contains(
+            "{\"module\":\"env1\",\"name\":\"check_permission\",\"version\":1,\
+            \"params\":[{\"name\":\"offset_address\",\"type\":\"i32\"},\
+            {\"name\":\"length_address\",\"type\":\"i32\"},\
+            {\"name\":\"role_id\",\"type\":\"i32\"}],\
+            \"results\":[\"i32\",\"i32\"],\"feature\":null}"
+        ));
+        assert_eq!(
+            manifest.matches("\"module\"").count(),
+            host_functions().count()
+        );
+    }
+
+    #[test]
+    fn test_no_orphans() {
+        // Every module file is declared as a version
+        let mut files: Vec<String> =
+            std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src/modules"))
+                .expect("Failed to read modules directory")
+                .map(|entry| entry.expect("Failed to read entry").path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+                .map(|path| path.file_stem().unwrap().to_string_lossy().to_string())
+                .collect();
+        files.sort();
+        assert_eq!(files, VERSIONS);
+
+        // Every declared function is linked
+        #[cfg(feature = "jvm")]
+        {
+            let mock = Box::new(crate::mock::Mock::new());
+            let mut vm = crate::vm::Vm::new(
+                vec![],
+                vec![],
+                crate::limits::ExecutionLimits {
+                    memory: (1, 1),
+                    fuel: 0,
+                    ..Default::default()
+                },
+                vec![],
+                mock,
+                crate::vm::ExecutionMode::Apply,
+            )
+            .expect("Call stack creation failed");
+            let engine = wasmi::Engine::default();
+            let mut store = Store::new(&engine, Runtime::new(&mut vm));
+            let linked: Vec<(String, String)> = all()
+                .into_iter()
+                .map(|item| {
+                    let (module, name, _) = item(&mut store).expect("Linking failed");
+                    (module, name)
+                })
+                .collect();
+            let declared: Vec<(String, String)> = host_functions()
+                .map(|function| (function.module.to_string(), function.name.to_string()))
+                .collect();
+            assert_eq!(linked, declared);
+        }
+    }
+}
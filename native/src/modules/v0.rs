@@ -8,7 +8,7 @@ mod test {
         length_asset_id: usize,
         offset_address: *const u8,
         length_address: usize,
-    ) -> (i32, i64) {
+    ) -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::asset::get_balance(
                 offset_asset_id,
@@ -28,7 +28,7 @@ mod test {
         offset_recipient: *const u8,
         length_recipient: usize,
         amount: i64,
-    ) -> i32 {
+    ) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::asset::transfer(
                 offset_asset_id,
@@ -51,7 +51,7 @@ mod test {
         quantity: i64,
         decimals: i32,
         is_reissuable: bool,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::asset::issue(
                 offset_name,
@@ -66,7 +66,7 @@ mod test {
         }
     }
 
-    fn burn(offset_asset_id: *const u8, length_asset_id: usize, amount: i64) -> i32 {
+    fn burn(offset_asset_id: *const u8, length_asset_id: usize, amount: i64) -> Result<()> {
         |caller: Caller<Runtime>| env::asset::burn(offset_asset_id, length_asset_id, amount, caller)
     }
 
@@ -75,7 +75,7 @@ mod test {
         length_asset_id: usize,
         amount: i64,
         is_reissuable: bool,
-    ) -> i32 {
+    ) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::asset::reissue(
                 offset_asset_id,
@@ -88,13 +88,13 @@ mod test {
     }
 
     // Block
-    fn get_block_timestamp() -> (i32, i64) {
+    fn get_block_timestamp() -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::block::get_block_field(env::Field::String("timestamp".to_string()), caller)
         }
     }
 
-    fn get_block_height() -> (i32, i64) {
+    fn get_block_height() -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::block::get_block_field(env::Field::String("height".to_string()), caller)
         }
@@ -109,19 +109,19 @@ mod test {
         |caller: Caller<Runtime>| env::call_contract::call_arg_bool(value, caller)
     }
 
-    fn call_arg_binary(offset_value: *const u8, length_value: usize) -> i32 {
+    fn call_arg_binary(offset_value: *const u8, length_value: usize) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::call_contract::call_arg_binary(offset_value, length_value, caller)
         }
     }
 
-    fn call_arg_string(offset_value: *const u8, length_value: usize) -> i32 {
+    fn call_arg_string(offset_value: *const u8, length_value: usize) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::call_contract::call_arg_string(offset_value, length_value, caller)
         }
     }
 
-    fn call_payment(offset_asset_id: *const u8, length_asset_id: usize, amount: i64) -> i32 {
+    fn call_payment(offset_asset_id: *const u8, length_asset_id: usize, amount: i64) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::call_contract::call_payment(offset_asset_id, length_asset_id, amount, caller)
         }
@@ -168,48 +168,48 @@ mod test {
     }
 
     // Converts
-    fn parse_int(offset: *const u8, length: usize) -> (i32, i64) {
+    fn parse_int(offset: *const u8, length: usize) -> Result<i64> {
         |caller: Caller<Runtime>| env::converts::parse_int(offset, length, caller)
     }
 
-    fn parse_bool(offset_string: *const u8, length_string: usize) -> (i32, bool) {
+    fn parse_bool(offset_string: *const u8, length_string: usize) -> Result<bool> {
         |caller: Caller<Runtime>| env::converts::parse_bool(offset_string, length_string, caller)
     }
 
-    fn to_bytes(value: i64) -> (i32, *const u8, usize) {
+    fn to_bytes(value: i64) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::converts::to_bytes(value, caller)
     }
 
-    fn to_int(offset: *const u8, length: usize) -> (i32, i64) {
+    fn to_int(offset: *const u8, length: usize) -> Result<i64> {
         |caller: Caller<Runtime>| env::converts::to_int(offset, length, caller)
     }
 
-    fn to_string_bool(value: bool) -> (i32, *const u8, usize) {
+    fn to_string_bool(value: bool) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::converts::to_string(value != 0, caller)
     }
 
-    fn to_string_int(value: i64) -> (i32, *const u8, usize) {
+    fn to_string_int(value: i64) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::converts::to_string(value, caller)
     }
 
     // Crypto
-    fn fast_hash(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn fast_hash(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::crypto::fast_hash(offset_bytes, length_bytes, caller)
     }
 
-    fn secure_hash(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn secure_hash(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::crypto::secure_hash(offset_bytes, length_bytes, caller)
     }
 
-    fn blake2b256(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn blake2b256(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::crypto::blake2b256(offset_bytes, length_bytes, caller)
     }
 
-    fn keccak256(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn keccak256(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::crypto::keccak256(offset_bytes, length_bytes, caller)
     }
 
-    fn sha256(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn sha256(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::crypto::sha256(offset_bytes, length_bytes, caller)
     }
 
@@ -220,7 +220,7 @@ mod test {
         length_signature: usize,
         offset_public_key: *const u8,
         length_public_key: usize,
-    ) -> (i32, bool) {
+    ) -> Result<bool> {
         |caller: Caller<Runtime>| {
             env::crypto::sig_verify(
                 offset_message,
@@ -239,7 +239,7 @@ mod test {
         offset_address: *const u8,
         length_address: usize,
         amount: i64,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::lease::lease(offset_address, length_address, 1, amount, caller)
         }
@@ -249,11 +249,11 @@ mod test {
         offset_alias: *const u8,
         length_alias: usize,
         amount: i64,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::lease::lease(offset_alias, length_alias, 2, amount, caller)
     }
 
-    fn cancel_lease(offset_lease_id: *const u8, length_lease_id: usize) -> i32 {
+    fn cancel_lease(offset_lease_id: *const u8, length_lease_id: usize) -> Result<()> {
         |caller: Caller<Runtime>| env::lease::cancel_lease(offset_lease_id, length_lease_id, caller)
     }
 
@@ -263,7 +263,7 @@ mod test {
         length_left: usize,
         offset_right: *const u8,
         length_right: usize,
-    ) -> (i32, bool) {
+    ) -> Result<bool> {
         |caller: Caller<Runtime>| {
             env::memory::binary_equals(offset_left, length_left, offset_right, length_right, caller)
         }
@@ -274,7 +274,7 @@ mod test {
         length_left: usize,
         offset_right: *const u8,
         length_right: usize,
-    ) -> (i32, bool) {
+    ) -> Result<bool> {
         |caller: Caller<Runtime>| {
             env::memory::string_equals(offset_left, length_left, offset_right, length_right, caller)
         }
@@ -285,7 +285,7 @@ mod test {
         length_left: usize,
         offset_right: *const u8,
         length_right: usize,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::memory::join(offset_left, length_left, offset_right, length_right, caller)
         }
@@ -296,7 +296,7 @@ mod test {
         length_bytes: usize,
         offset_subbytes: *const u8,
         length_subbytes: usize,
-    ) -> (i32, bool) {
+    ) -> Result<bool> {
         |caller: Caller<Runtime>| {
             env::memory::contains(
                 offset_bytes,
@@ -308,11 +308,15 @@ mod test {
         }
     }
 
-    fn drop(offset_bytes: *const u8, length_bytes: usize, n: i64) -> (i32, *const u8, usize) {
+    fn drop(offset_bytes: *const u8, length_bytes: usize, n: i64) -> Result<(*const u8, usize)> {
         |_caller: Caller<Runtime>| env::memory::drop(offset_bytes, length_bytes, n)
     }
 
-    fn drop_right(offset_bytes: *const u8, length_bytes: usize, n: i64) -> (i32, *const u8, usize) {
+    fn drop_right(
+        offset_bytes: *const u8,
+        length_bytes: usize,
+        n: i64,
+    ) -> Result<(*const u8, usize)> {
         |_caller: Caller<Runtime>| env::memory::drop_right(offset_bytes, length_bytes, n)
     }
 
@@ -321,7 +325,7 @@ mod test {
         length_string: usize,
         offset_substring: *const u8,
         length_substring: usize,
-    ) -> (i32, i64) {
+    ) -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::memory::index_of(
                 false,
@@ -339,7 +343,7 @@ mod test {
         length_string: usize,
         offset_substring: *const u8,
         length_substring: usize,
-    ) -> (i32, i64) {
+    ) -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::memory::index_of(
                 true,
@@ -352,11 +356,15 @@ mod test {
         }
     }
 
-    fn take(offset_bytes: *const u8, length_bytes: usize, n: i64) -> (i32, *const u8, usize) {
+    fn take(offset_bytes: *const u8, length_bytes: usize, n: i64) -> Result<(*const u8, usize)> {
         |_caller: Caller<Runtime>| env::memory::take(offset_bytes, length_bytes, n)
     }
 
-    fn take_right(offset_bytes: *const u8, length_bytes: usize, n: i64) -> (i32, *const u8, usize) {
+    fn take_right(
+        offset_bytes: *const u8,
+        length_bytes: usize,
+        n: i64,
+    ) -> Result<(*const u8, usize)> {
         |_caller: Caller<Runtime>| env::memory::take_right(offset_bytes, length_bytes, n)
     }
 
@@ -366,7 +374,7 @@ mod test {
         length_address: usize,
         offset_key: *const u8,
         length_key: usize,
-    ) -> (i32, bool) {
+    ) -> Result<bool> {
         |caller: Caller<Runtime>| {
             env::storage::contains_key(
                 offset_address,
@@ -383,7 +391,7 @@ mod test {
         length_address: usize,
         offset_key: *const u8,
         length_key: usize,
-    ) -> (i32, i64) {
+    ) -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::storage::get_storage_int(
                 offset_address,
@@ -400,7 +408,7 @@ mod test {
         length_address: usize,
         offset_key: *const u8,
         length_key: usize,
-    ) -> (i32, bool) {
+    ) -> Result<bool> {
         |caller: Caller<Runtime>| {
             env::storage::get_storage_bool(
                 offset_address,
@@ -417,7 +425,7 @@ mod test {
         length_address: usize,
         offset_key: *const u8,
         length_key: usize,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::storage::get_storage_binary(
                 offset_address,
@@ -434,7 +442,7 @@ mod test {
         length_address: usize,
         offset_key: *const u8,
         length_key: usize,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::storage::get_storage_string(
                 offset_address,
@@ -446,13 +454,13 @@ mod test {
         }
     }
 
-    fn set_storage_int(offset_key: *const u8, length_key: usize, value: i64) -> i32 {
+    fn set_storage_int(offset_key: *const u8, length_key: usize, value: i64) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::storage::set_storage_int(offset_key, length_key, value, caller)
         }
     }
 
-    fn set_storage_bool(offset_key: *const u8, length_key: usize, value: bool) -> i32 {
+    fn set_storage_bool(offset_key: *const u8, length_key: usize, value: bool) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::storage::set_storage_bool(offset_key, length_key, value, caller)
         }
@@ -463,7 +471,7 @@ mod test {
         length_key: usize,
         offset_value: *const u8,
         length_value: usize,
-    ) -> i32 {
+    ) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::storage::set_storage_binary(
                 offset_key,
@@ -480,7 +488,7 @@ mod test {
         length_key: usize,
         offset_value: *const u8,
         length_value: usize,
-    ) -> i32 {
+    ) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::storage::set_storage_string(
                 offset_key,
@@ -493,49 +501,47 @@ mod test {
     }
 
     // Tx
-    fn get_tx_sender() -> (i32, *const u8, usize) {
+    fn get_tx_sender() -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::tx::tx(env::Field::String("sender".to_string()), caller)
     }
 
-    fn get_payments() -> (i32, i32) {
+    fn get_payments() -> Result<i32> {
         |caller: Caller<Runtime>| {
-            let (err, num) = env::tx::get_payments(caller);
-            match i32::try_from(num) {
-                Ok(value) => (err, value),
-                Err(_) => (
-                    Error::Runtime(RuntimeError::ConvertingNumericTypes).as_i32(),
-                    0,
-                ),
-            }
+            env::tx::get_payments(caller).and_then(|num| {
+                i32::try_from(num).map_err(|_| Error::Runtime(RuntimeError::ConvertingNumericTypes))
+            })
         }
     }
 
-    fn get_payment_asset_id(number: i32) -> (i32, *const u8, usize) {
+    fn get_payment_asset_id(number: i32) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::tx::get_payment_asset_id(number as i64, caller)
     }
 
-    fn get_payment_amount(number: i32) -> (i32, i64) {
+    fn get_payment_amount(number: i32) -> Result<i64> {
         |caller: Caller<Runtime>| env::tx::get_payment_amount(number as i64, caller)
     }
 
     // Utils
-    fn base_58(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn base_58(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::utils::base58(offset_bytes, length_bytes, caller)
     }
 
-    fn to_base_58_string(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn to_base_58_string(
+        offset_bytes: *const u8,
+        length_bytes: usize,
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::utils::to_base58_string(offset_bytes, length_bytes, caller)
     }
 
-    fn to_le_bytes(offset_bytes: *const u8, length_bytes: usize) -> (i32, *const u8, usize) {
+    fn to_le_bytes(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::utils::to_le_bytes(offset_bytes, length_bytes, caller)
     }
 
-    fn caller() -> (i32, *const u8, usize) {
+    fn caller() -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::utils::caller(caller)
     }
 
-    fn require(offset_message: *const u8, length_message: usize) -> i32 {
+    fn require(offset_message: *const u8, length_message: usize) -> Result<()> {
         |caller: Caller<Runtime>| env::utils::require(offset_message, length_message, caller)
     }
 }
@@ -10,7 +10,7 @@ mod test {
         length_holder: usize,
         type_: u32,
         version: u32,
-    ) -> (i32, i64) {
+    ) -> Result<i64> {
         |caller: Caller<Runtime>| {
             env::asset::get_balance(
                 offset_asset_id,
@@ -32,7 +32,7 @@ mod test {
         type_: u32,
         version: u32,
         amount: i64,
-    ) -> i32 {
+    ) -> Result<()> {
         |caller: Caller<Runtime>| {
             env::asset::transfer(
                 offset_asset_id,
@@ -55,7 +55,7 @@ mod test {
         quantity: i64,
         decimals: i64,
         is_reissuable: bool,
-    ) -> (i32, *const u8, usize) {
+    ) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::asset::issue(
                 offset_name,
@@ -71,28 +71,325 @@ mod test {
     }
 
     // Block
-    fn block(offset_field: *const u8, length_field: usize) -> (i32, *const u8, usize) {
+    fn block(offset_field: *const u8, length_field: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::block::block(env::Field::Binary(offset_field, length_field), caller)
         }
     }
 
+    fn get_block_at(height: i64) -> Result<(i64, *const u8, usize)> {
+        |caller: Caller<Runtime>| env::block::get_block_at(height, caller)
+    }
+
+    // Call contract
+    fn is_paused(offset_contract_id: *const u8, length_contract_id: usize) -> Result<bool> {
+        |caller: Caller<Runtime>| {
+            env::call_contract::is_paused(offset_contract_id, length_contract_id, caller)
+        }
+    }
+
+    fn delegate_call(
+        offset_contract_id: *const u8,
+        length_contract_id: usize,
+        offset_func_name: *const u8,
+        length_func_name: usize,
+        offset_params: *const u8,
+        length_params: usize,
+    ) -> i32 {
+        |caller: Caller<Runtime>| {
+            env::call_contract::delegate_call(
+                offset_contract_id,
+                length_contract_id,
+                offset_func_name,
+                length_func_name,
+                offset_params,
+                length_params,
+                caller,
+            )
+        }
+    }
+
+    fn get_bytecode_hash(
+        offset_contract_id: *const u8,
+        length_contract_id: usize,
+    ) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| {
+            env::call_contract::get_bytecode_hash(offset_contract_id, length_contract_id, caller)
+        }
+    }
+
+    fn get_call_depth() -> i32 {
+        |caller: Caller<Runtime>| env::call_contract::get_call_depth(caller)
+    }
+
+    fn get_frame_caller(index: i32) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::call_contract::get_frame_caller(index, caller)
+    }
+
+    fn get_call_results() -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::call_contract::get_call_results(caller)
+    }
+
+    // Converts
+    #[fuel(base = 10, per_byte = 1)]
+    fn is_valid_utf8(offset: *const u8, length: usize) -> Result<bool> {
+        |caller: Caller<Runtime>| env::converts::is_valid_utf8(offset, length, caller)
+    }
+
+    #[fuel(base = 10, per_byte = 1)]
+    fn to_valid_utf8(offset: *const u8, length: usize) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::converts::to_valid_utf8(offset, length, caller)
+    }
+
+    // Crypto
+    fn address_from_public_key(
+        offset_public_key: *const u8,
+        length_public_key: usize,
+    ) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| {
+            env::crypto::address_from_public_key(offset_public_key, length_public_key, caller)
+        }
+    }
+
+    fn get_random(offset_seed: *const u8, length_seed: usize) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::crypto::get_random(offset_seed, length_seed, caller)
+    }
+
+    fn unique_id() -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::crypto::unique_id(caller)
+    }
+
+    // Lease
+    fn get_lease_info(
+        offset_lease_id: *const u8,
+        length_lease_id: usize,
+    ) -> Result<(*const u8, usize, *const u8, usize, i64, bool)> {
+        |caller: Caller<Runtime>| {
+            env::lease::get_lease_info(offset_lease_id, length_lease_id, caller)
+        }
+    }
+
+    // Memory
+    #[fuel(base = 10, per_byte = 1)]
+    fn mem_compare(
+        offset_left: *const u8,
+        length_left: usize,
+        offset_right: *const u8,
+        length_right: usize,
+    ) -> Result<i32> {
+        |caller: Caller<Runtime>| {
+            env::memory::mem_compare(offset_left, length_left, offset_right, length_right, caller)
+        }
+    }
+
+    #[fuel(base = 10, per_byte = 1)]
+    fn mem_copy(
+        offset_destination: *const u8,
+        offset_source: *const u8,
+        length: usize,
+    ) -> Result<()> {
+        |caller: Caller<Runtime>| {
+            env::memory::mem_copy(offset_destination, offset_source, length, caller)
+        }
+    }
+
+    #[fuel(base = 10, per_byte = 1)]
+    fn mem_fill(offset: *const u8, length: usize, value: i32) -> Result<()> {
+        |caller: Caller<Runtime>| env::memory::mem_fill(offset, length, value, caller)
+    }
+
+    // Permission
+    fn check_permission(
+        offset_address: *const u8,
+        length_address: usize,
+        role_id: i32,
+    ) -> Result<bool> {
+        |caller: Caller<Runtime>| {
+            env::permission::check_permission(offset_address, length_address, role_id, caller)
+        }
+    }
+
+    // Storage
+    fn get_account_data_int(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+    ) -> Result<i64> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_account_data_int(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                caller,
+            )
+        }
+    }
+
+    fn get_account_data_bool(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+    ) -> Result<bool> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_account_data_bool(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                caller,
+            )
+        }
+    }
+
+    fn get_account_data_binary(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+    ) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_account_data_binary(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                caller,
+            )
+        }
+    }
+
+    fn get_account_data_string(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+    ) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_account_data_string(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                caller,
+            )
+        }
+    }
+
+    fn get_storage_int_or(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+        default: i64,
+    ) -> Result<i64> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_storage_int_or(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                default,
+                caller,
+            )
+        }
+    }
+
+    fn get_storage_bool_or(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+        default: bool,
+    ) -> Result<bool> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_storage_bool_or(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                default,
+                caller,
+            )
+        }
+    }
+
+    fn get_storage_binary_into(
+        offset_address: *const u8,
+        length_address: usize,
+        offset_key: *const u8,
+        length_key: usize,
+        offset_buffer: *const u8,
+        capacity: usize,
+    ) -> Result<usize> {
+        |caller: Caller<Runtime>| {
+            env::storage::get_storage_binary_into(
+                offset_address,
+                length_address,
+                offset_key,
+                length_key,
+                offset_buffer,
+                capacity,
+                caller,
+            )
+        }
+    }
+
+    fn delete_storage(offset_key: *const u8, length_key: usize) -> Result<bool> {
+        |caller: Caller<Runtime>| env::storage::delete_storage(offset_key, length_key, caller)
+    }
+
     // Tx
-    fn get_payments() -> (i32, i64) {
+    fn get_payments() -> Result<i64> {
         |caller: Caller<Runtime>| env::tx::get_payments(caller)
     }
 
-    fn get_payment_asset_id(number: i64) -> (i32, *const u8, usize) {
+    fn get_payment_asset_id(number: i64) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| env::tx::get_payment_asset_id(number, caller)
     }
 
-    fn get_payment_amount(number: i64) -> (i32, i64) {
+    fn get_payment_amount(number: i64) -> Result<i64> {
         |caller: Caller<Runtime>| env::tx::get_payment_amount(number, caller)
     }
 
-    fn tx(offset_field: *const u8, length_field: usize) -> (i32, *const u8, usize) {
+    fn get_tx_fee() -> Result<i64> {
+        |caller: Caller<Runtime>| env::tx::get_tx_fee(caller)
+    }
+
+    fn get_tx_fee_asset_id() -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| {
+            env::tx::tx(env::Field::String("feeAssetId".to_string()), caller)
+        }
+    }
+
+    fn is_tx_fee_sponsored() -> Result<bool> {
+        |caller: Caller<Runtime>| env::tx::is_tx_fee_sponsored(caller)
+    }
+
+    fn tx(offset_field: *const u8, length_field: usize) -> Result<(*const u8, usize)> {
         |caller: Caller<Runtime>| {
             env::tx::tx(env::Field::Binary(offset_field, length_field), caller)
         }
     }
+
+    // Utils
+    #[fuel(base = 100, per_byte = 20)]
+    fn base_58(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::utils::base58(offset_bytes, length_bytes, caller)
+    }
+
+    #[fuel(base = 100, per_byte = 20)]
+    fn to_base_58_string(
+        offset_bytes: *const u8,
+        length_bytes: usize,
+    ) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::utils::to_base58_string(offset_bytes, length_bytes, caller)
+    }
+
+    #[fuel(base = 100, per_byte = 5)]
+    fn to_le_bytes(offset_bytes: *const u8, length_bytes: usize) -> Result<(*const u8, usize)> {
+        |caller: Caller<Runtime>| env::utils::to_le_bytes(offset_bytes, length_bytes, caller)
+    }
 }
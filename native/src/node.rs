@@ -1,4 +1,7 @@
-use crate::error::Result;
+use crate::{
+    error::{Error, ExecutableError, Result},
+    runtime::data_entry::DataEntry,
+};
 
 /// Interface of interaction with the node.
 pub trait Node {
@@ -6,6 +9,8 @@ pub trait Node {
     fn require(&self, message: &[u8]) -> Result<()>;
     fn get_bytecode(&self, contract_id: &[u8]) -> Result<Vec<u8>>;
     fn add_payments(&self, contract_id: &[u8], payment_id: &[u8], payments: &[u8]) -> Result<()>;
+    fn is_paused(&self, contract_id: &[u8]) -> Result<bool>;
+    fn is_feature_activated(&self, feature: &[u8]) -> Result<bool>;
     // Asset
     fn get_balance(&self, asset_id: &[u8], address: &[u8]) -> Result<i64>;
     fn transfer(
@@ -34,6 +39,8 @@ pub trait Node {
     ) -> Result<()>;
     // Block
     fn block(&self, field: &[u8]) -> Result<Vec<u8>>;
+    /// Timestamp of the block at the height as 8 bytes, followed by the address of its generator.
+    fn get_block_at(&self, height: i64) -> Result<Vec<u8>>;
     // Crypto
     fn fast_hash(&self, bytes: &[u8]) -> Result<Vec<u8>>;
     fn secure_hash(&self, bytes: &[u8]) -> Result<Vec<u8>>;
@@ -41,13 +48,52 @@ pub trait Node {
     // Lease
     fn lease(&self, contract_id: &[u8], recipient: &[u8], amount: i64) -> Result<Vec<u8>>;
     fn cancel_lease(&self, contract_id: &[u8], lease_id: &[u8]) -> Result<()>;
+    /// State of the lease: the active flag as 1 byte, the amount as 8 bytes, the length of
+    /// the sender as 1 byte, the sender and the recipient.
+    fn get_lease_info(&self, lease_id: &[u8]) -> Result<Vec<u8>>;
+    // Permission
+    fn check_permission(&self, address: &[u8], role_id: i32) -> Result<bool>;
     // Storage
     fn contains_key(&self, address: &[u8], key: &[u8]) -> Result<bool>;
     fn get_storage(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>>;
+    /// Writing the binary value of the entry into the buffer, returns the length of the value.
+    /// Nothing is written if the value doesn't fit into the buffer, the caller compares
+    /// the length with the capacity.
+    fn get_storage_binary_into(
+        &self,
+        address: &[u8],
+        key: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        let bytes = self.get_storage(address, key)?;
+        copy_binary(&bytes, buffer)
+    }
     fn set_storage(&self, contract_id: &[u8], value: &[u8]) -> Result<()>;
+    /// Removing the entry of the contract, returns `false` if the key is absent.
+    /// The removal is rolled back by `restore` like the writes.
+    fn delete_storage(&self, contract_id: &[u8], key: &[u8]) -> Result<bool>;
+    /// Serialized entry of the account data published by data transactions, empty if the key is absent.
+    fn get_account_data(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>>;
     // Tx
     fn get_tx_payments(&self, payment_id: &[u8]) -> Result<i64>;
     fn get_tx_payment_asset_id(&self, payment_id: &[u8], number: i64) -> Result<Vec<u8>>;
     fn get_tx_payment_amount(&self, payment_id: &[u8], number: i64) -> Result<i64>;
     fn tx(&self, field: &[u8]) -> Result<Vec<u8>>;
+    // Snapshot
+    fn snapshot(&self) -> Result<i32>;
+    fn restore(&self, snapshot: i32) -> Result<()>;
+    fn discard(&self, snapshot: i32) -> Result<()>;
+}
+
+/// Copying the binary value of the serialized entry into the buffer if it fits.
+pub(crate) fn copy_binary(bytes: &[u8], buffer: &mut [u8]) -> Result<usize> {
+    let value = match DataEntry::deserialize(bytes) {
+        Ok(DataEntry::Binary(value)) => value,
+        _ => return Err(Error::Executable(ExecutableError::FailedDeserialize)),
+    };
+
+    if let Some(target) = buffer.get_mut(..value.len()) {
+        target.copy_from_slice(&value);
+    }
+    Ok(value.len())
 }
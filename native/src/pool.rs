@@ -0,0 +1,190 @@
+//! Sessions opened by the node and shared between its threads by handles.
+//!
+//! The registry is locked only to find a session, so calls to different sessions run
+//! concurrently. Calls to the same session are serialized by the lock of the session,
+//! the node doesn't need to synchronize the threads using a handle.
+//!
+//! A session can be cancelled while another thread runs its call, e.g. on the abort of the block.
+
+use crate::{
+    cancel::CancellationToken,
+    error::{Error, JvmError, Result},
+    session::Session,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex, MutexGuard, RwLock,
+    },
+};
+
+/// Identifier of a session passed to the node, never `0`.
+pub type Handle = i64;
+
+struct Entry {
+    /// Session is taken out on closing, so the threads still holding it fail instead of using it.
+    session: Mutex<Option<Session>>,
+    /// Token of the session, available without waiting for the running call.
+    cancellation: CancellationToken,
+}
+
+static SESSIONS: RwLock<BTreeMap<Handle, Arc<Entry>>> = RwLock::new(BTreeMap::new());
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn not_found() -> Error {
+    Error::Jvm(JvmError::SessionNotFound)
+}
+
+fn lock(entry: &Entry) -> MutexGuard<'_, Option<Session>> {
+    entry
+        .session
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+fn get(handle: Handle) -> Result<Arc<Entry>> {
+    SESSIONS
+        .read()
+        .unwrap_or_else(|error| error.into_inner())
+        .get(&handle)
+        .cloned()
+        .ok_or_else(not_found)
+}
+
+/// Registering the session, returns its handle.
+pub fn open(session: Session) -> Handle {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    let entry = Entry {
+        cancellation: session.cancellation_token(),
+        session: Mutex::new(Some(session)),
+    };
+    SESSIONS
+        .write()
+        .unwrap_or_else(|error| error.into_inner())
+        .insert(handle, Arc::new(entry));
+    handle
+}
+
+/// Using the session of the handle, waits for the calls of other threads to the same session.
+pub fn with<T>(handle: Handle, f: impl FnOnce(&mut Session) -> T) -> Result<T> {
+    let entry = get(handle)?;
+    let mut session = lock(&entry);
+    session.as_mut().map(f).ok_or_else(not_found)
+}
+
+/// Cancelling the running and following calls of the session, the session must still be closed.
+pub fn cancel(handle: Handle) -> Result<()> {
+    get(handle)?.cancellation.cancel();
+    Ok(())
+}
+
+/// Removing the session of the handle, waits for the running call to finish.
+pub fn close(handle: Handle) -> Result<Session> {
+    let entry = SESSIONS
+        .write()
+        .unwrap_or_else(|error| error.into_inner())
+        .remove(&handle)
+        .ok_or_else(not_found)?;
+
+    let session = lock(&entry).take();
+    session.ok_or_else(not_found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::RuntimeError, mock::Mock, runtime::data_entry::DataEntry, session::Call};
+    use std::thread;
+
+    const CONTRACT_ID: [u8; 32] = [1; 32];
+
+    #[test]
+    fn test_pool() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "get_storage_int" (func $get_storage_int (param i32 i32 i32 i32) (result i32 i64)))
+            (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (local $value i64)
+                (call $get_storage_int
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                )
+                (local.set $value)
+                (drop)
+                (call $set_storage_int
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                    (i64.add (local.get $value) (i64.const 1))
+                )
+            )
+
+            (data (i32.const 0) "counter")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+        )
+        "#;
+        let call = Arc::new(Call {
+            contract_id: CONTRACT_ID.to_vec(),
+            bytecode: wat::parse_str(wat).expect("WAT code parsing failed"),
+            func_name: String::from("_constructor"),
+            params: vec![0, 0],
+            fuel_limit: 1024,
+        });
+
+        let mocks = [Mock::new(), Mock::new()];
+        let handles: Vec<Handle> = mocks
+            .iter()
+            .map(|mock| {
+                mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(0));
+                open(Session::new(Box::new(mock.clone())))
+            })
+            .collect();
+
+        let threads: Vec<_> = (0..8)
+            .map(|index| {
+                let handle = handles[index % 2];
+                let call = call.clone();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        let result = with(handle, |session| session.execute(&call))
+                            .expect("Session not found")
+                            .expect("Execution failed");
+                        assert_eq!(result[0].i32(), Some(0));
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().expect("Thread panicked");
+        }
+
+        // Calls of the same session aren't lost
+        for (mock, handle) in mocks.iter().zip(handles) {
+            assert_eq!(
+                mock.storage(&CONTRACT_ID, b"counter"),
+                Some(DataEntry::Integer(40))
+            );
+
+            // Cancelled sessions fail without executing the contract
+            cancel(handle).expect("Session not found");
+            let result = with(handle, |session| session.execute(&call)).expect("Session not found");
+            assert_eq!(
+                result.map(|_| ()).map_err(|error| error.error),
+                Err(Error::Runtime(RuntimeError::Cancelled))
+            );
+
+            close(handle).expect("Session not found").finish();
+            assert!(with(handle, |_| ()).is_err());
+            assert!(close(handle).is_err());
+            assert!(cancel(handle).is_err());
+        }
+    }
+}
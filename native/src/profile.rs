@@ -0,0 +1,231 @@
+use base58::ToBase58;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt::{self, Write},
+    time::{Duration, Instant},
+};
+
+/// Number of calls of a function and the fuel consumed by them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    pub calls: u64,
+    pub fuel: u64,
+}
+
+impl Stats {
+    fn add(&mut self, fuel: u64) {
+        self.calls += 1;
+        self.fuel += fuel;
+    }
+}
+
+/// Contract function being executed.
+#[derive(Clone, Debug)]
+struct Frame {
+    contract_id: Vec<u8>,
+    func_name: String,
+    // Fuel of the host functions called by the frame
+    host: u64,
+    started: Instant,
+    // Time of the nested calls of other contracts
+    nested: Duration,
+}
+
+/// Fuel and time consumed by the contracts executed in the profiling mode.
+///
+/// Guest functions are the exported functions called by the node or by other contracts.
+/// Internal functions of a contract aren't profiled on their own, their fuel and time are
+/// added to the called function. Fuel of a guest function excludes host functions and
+/// nested calls of other contracts. Fuel of a host function is the cost charged by the host.
+///
+/// Wall-clock time is attributed to the stacks of the contract calls, see `collapsed`.
+/// Time of a stack includes the host functions called by its top function.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    guest: BTreeMap<(Vec<u8>, String), Stats>,
+    host: BTreeMap<&'static str, Stats>,
+    stacks: BTreeMap<String, Duration>,
+    frames: Vec<Frame>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn guest_function(&self, contract_id: &[u8], func_name: &str) -> Option<Stats> {
+        self.guest
+            .get(&(contract_id.to_vec(), func_name.to_string()))
+            .copied()
+    }
+
+    /// Stats of the host function by its `module::name`.
+    pub fn host_function(&self, name: &str) -> Option<Stats> {
+        self.host.get(name).copied()
+    }
+
+    pub fn guest_functions(&self) -> impl Iterator<Item = (&[u8], &str, Stats)> {
+        self.guest.iter().map(|((contract_id, func_name), stats)| {
+            (contract_id.as_slice(), func_name.as_str(), *stats)
+        })
+    }
+
+    pub fn host_functions(&self) -> impl Iterator<Item = (&'static str, Stats)> + '_ {
+        self.host.iter().map(|(name, stats)| (*name, *stats))
+    }
+
+    /// Time spent in the guest function excluding nested calls of other contracts.
+    pub fn guest_time(&self, contract_id: &[u8], func_name: &str) -> Option<Duration> {
+        let name = frame_name(contract_id, func_name);
+        let times: Vec<_> = self
+            .stacks
+            .iter()
+            .filter(|(stack, _)| stack.rsplit(';').next() == Some(name.as_str()))
+            .map(|(_, time)| *time)
+            .collect();
+        (!times.is_empty()).then(|| times.into_iter().sum())
+    }
+
+    /// Time of the call stacks in the collapsed stack format, one `stack microseconds` line
+    /// per stack with the frames separated by `;`. The report can be rendered by flame graph tools.
+    pub fn collapsed(&self) -> String {
+        let mut report = String::new();
+        for (stack, time) in &self.stacks {
+            let _ = writeln!(report, "{} {}", stack, time.as_micros());
+        }
+        report
+    }
+
+    /// Fuel consumed by all guest and host functions.
+    pub fn total(&self) -> u64 {
+        self.guest
+            .values()
+            .chain(self.host.values())
+            .map(|stats| stats.fuel)
+            .sum()
+    }
+
+    /// Starting the execution of a contract function.
+    pub(crate) fn enter(&mut self, contract_id: &[u8], func_name: &str) {
+        self.frames.push(Frame {
+            contract_id: contract_id.to_vec(),
+            func_name: func_name.to_string(),
+            host: 0,
+            started: Instant::now(),
+            nested: Duration::ZERO,
+        });
+    }
+
+    /// Adding the call of a host function to the executed frame.
+    pub(crate) fn record_host(&mut self, name: &'static str, fuel: u64) {
+        self.host.entry(name).or_default().add(fuel);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.host += fuel;
+        }
+    }
+
+    /// Finishing the execution of the last entered function.
+    /// `fuel_consumed` is the total fuel consumed by the instance including host functions.
+    pub(crate) fn exit(&mut self, fuel_consumed: u64) {
+        let stack = self
+            .frames
+            .iter()
+            .map(|frame| frame_name(&frame.contract_id, &frame.func_name))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let elapsed = frame.started.elapsed();
+        *self.stacks.entry(stack).or_default() += elapsed.saturating_sub(frame.nested);
+        if let Some(parent) = self.frames.last_mut() {
+            parent.nested += elapsed;
+        }
+
+        self.guest
+            .entry((frame.contract_id, frame.func_name))
+            .or_default()
+            .add(fuel_consumed.saturating_sub(frame.host));
+    }
+}
+
+fn frame_name(contract_id: &[u8], func_name: &str) -> String {
+    format!("{}::{}", contract_id.to_base58(), func_name)
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut guest: Vec<_> = self.guest_functions().collect();
+        guest.sort_by_key(|(_, _, stats)| Reverse(stats.fuel));
+
+        let mut host: Vec<_> = self.host_functions().collect();
+        host.sort_by_key(|(_, stats)| Reverse(stats.fuel));
+
+        writeln!(f, "Total fuel: {}", self.total())?;
+
+        writeln!(f, "Called guest functions, internal functions included:")?;
+        for (contract_id, func_name, stats) in guest {
+            writeln!(
+                f,
+                "  {} {}: calls {}, fuel {}",
+                contract_id.to_base58(),
+                func_name,
+                stats.calls,
+                stats.fuel
+            )?;
+        }
+
+        writeln!(f, "Host functions:")?;
+        for (name, stats) in host {
+            writeln!(f, "  {}: calls {}, fuel {}", name, stats.calls, stats.fuel)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let mut profile = Profile::new();
+
+        profile.enter(&[1], "_constructor");
+        profile.record_host("env0::call_contract", 10);
+        // Nested call
+        profile.enter(&[2], "get");
+        profile.record_host("env0::get_storage", 5);
+        profile.exit(25);
+        profile.record_host("env0::get_storage", 5);
+        profile.exit(100);
+
+        assert_eq!(
+            profile.guest_function(&[1], "_constructor"),
+            Some(Stats { calls: 1, fuel: 85 })
+        );
+        assert_eq!(
+            profile.guest_function(&[2], "get"),
+            Some(Stats { calls: 1, fuel: 20 })
+        );
+        assert_eq!(
+            profile.host_function("env0::get_storage"),
+            Some(Stats { calls: 2, fuel: 10 })
+        );
+        assert_eq!(profile.total(), 125);
+
+        let collapsed = profile.collapsed();
+        let stacks: Vec<_> = collapsed
+            .lines()
+            .map(|line| line.rsplit_once(' ').expect("Invalid line").0)
+            .collect();
+        assert_eq!(stacks, vec!["2::_constructor", "2::_constructor;3::get"]);
+        assert!(profile.guest_time(&[2], "get").is_some());
+        assert!(profile.guest_time(&[3], "get").is_none());
+    }
+}
@@ -5,29 +5,61 @@ pub mod payment_id;
 pub mod payments;
 pub mod utils;
 
-use crate::vm::Vm;
+use crate::{
+    error::Result,
+    vm::{ExecutionMode, Vm},
+};
 use params::Params;
 use payments::Payments;
-use wasmi::Memory;
+use wasmi::{
+    core::TrapCode,
+    errors::{MemoryError, TableError},
+    Caller, Memory, ResourceLimiter,
+};
+
+/// Size of a page of linear memory.
+const PAGE_SIZE: usize = 65536;
+
+/// State of the runtime and the node saved by `Runtime::snapshot`.
+pub struct Snapshot {
+    params: Params,
+    payments: Payments,
+    heap_base: i32,
+    fuel_refund: u64,
+    /// Identifier of the snapshot of the node, covering the write set and the events.
+    node: i32,
+}
 
 /// A structure accessible within the WASM interpreter to access linear memory,
 /// call stack, heap address, and other necessary information.
 pub struct Runtime<'a> {
     memory: Option<Memory>,
     pub vm: &'a mut Vm,
+    /// Mode of the execution, checked by the host functions changing the state.
+    pub mode: ExecutionMode,
     heap_base: i32,
     pub params: Params,
     pub payments: Payments,
+    /// Values returned by the last successful call of another contract.
+    pub results: Params,
+    /// Pages of linear memory allocated by the instance.
+    memory_pages: u32,
+    /// Pages of the last growth, released if the growth fails.
+    growing_pages: u32,
 }
 
 impl<'a> Runtime<'a> {
     pub fn new(vm: &'a mut Vm) -> Self {
         Self {
             memory: None,
+            mode: vm.mode(),
             vm,
             heap_base: 0,
             params: Params::new(),
             payments: Payments::new(),
+            results: Params::new(),
+            memory_pages: 0,
+            growing_pages: 0,
         }
     }
 
@@ -50,4 +82,89 @@ impl<'a> Runtime<'a> {
     pub fn set_heap_base(&mut self, value: i32) {
         self.heap_base = value;
     }
+
+    /// Saving the arguments and payments accumulated for a call, the heap address,
+    /// the refunds of the fuel and the changes made to the node. Used for speculative execution and
+    /// for the rollback of failed calls.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        Ok(Snapshot {
+            params: self.params.clone(),
+            payments: self.payments.clone(),
+            heap_base: self.heap_base,
+            fuel_refund: self.vm.fuel_refund(),
+            node: self.vm.node.snapshot()?,
+        })
+    }
+
+    /// Returning to the saved state. Snapshots taken after it can't be used anymore.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        self.vm.node.restore(snapshot.node)?;
+        self.params = snapshot.params;
+        self.payments = snapshot.payments;
+        self.heap_base = snapshot.heap_base;
+        self.vm.set_fuel_refund(snapshot.fuel_refund);
+        Ok(())
+    }
+
+    /// Keeping the current state. Snapshots taken after it can't be used anymore.
+    pub fn discard(&mut self, snapshot: Snapshot) -> Result<()> {
+        self.vm.node.discard(snapshot.node)
+    }
+}
+
+/// Consuming fuel of the store by a host function, traps if the fuel is exhausted.
+pub(crate) fn consume_fuel(
+    caller: &mut Caller<Runtime>,
+    fuel: u64,
+) -> core::result::Result<(), wasmi::Error> {
+    let remaining = caller.get_fuel()?;
+    match remaining.checked_sub(fuel) {
+        Some(remaining) => Ok(caller.set_fuel(remaining)?),
+        None => Err(TrapCode::OutOfFuel.into()),
+    }
+}
+
+/// Linear memory is accounted by the virtual machine across the instances of nested calls.
+impl ResourceLimiter for Runtime<'_> {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, MemoryError> {
+        if maximum.is_some_and(|maximum| desired > maximum) {
+            return Ok(false);
+        }
+
+        let pages = ((desired - current) / PAGE_SIZE) as u32;
+        if !self.vm.allocate_memory(pages) {
+            return Ok(false);
+        }
+
+        self.memory_pages += pages;
+        self.growing_pages = pages;
+        Ok(true)
+    }
+
+    fn memory_grow_failed(&mut self, _error: &MemoryError) {
+        self.vm.release_memory(self.growing_pages);
+        self.memory_pages -= self.growing_pages;
+        self.growing_pages = 0;
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> Result<bool, TableError> {
+        Ok(true)
+    }
+}
+
+impl Drop for Runtime<'_> {
+    fn drop(&mut self) {
+        // Memory of the instance is freed together with the store
+        self.vm.release_memory(self.memory_pages);
+    }
 }
@@ -1,9 +1,10 @@
 use crate::{
-    error::{Error, ExecutableError, Result},
+    error::{Error, ExecutableError, Result, RuntimeError},
     runtime::utils,
 };
+use wasmi::Val;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DataEntry {
     Integer(i64),
     Boolean(i32),
@@ -76,7 +77,10 @@ impl DataEntry {
                 Self::Binary(value) => {
                     let length = value.len();
                     let offset_o = *offset_output;
-                    output[offset_o..offset_o + length].copy_from_slice(value.as_slice());
+                    output
+                        .get_mut(offset_o..offset_o + length)
+                        .ok_or(Error::Runtime(RuntimeError::MemoryOutOfBounds))?
+                        .copy_from_slice(value.as_slice());
                     params.push(format!("{}", *offset_output));
                     params.push(format!("{}", length));
                     *offset_output += length;
@@ -84,7 +88,10 @@ impl DataEntry {
                 Self::String(value) => {
                     let length = value.len();
                     let offset_o = *offset_output;
-                    output[offset_o..offset_o + length].copy_from_slice(value.as_slice());
+                    output
+                        .get_mut(offset_o..offset_o + length)
+                        .ok_or(Error::Runtime(RuntimeError::MemoryOutOfBounds))?
+                        .copy_from_slice(value.as_slice());
                     params.push(format!("{}", *offset_output));
                     params.push(format!("{}", length));
                     *offset_output += length;
@@ -96,6 +103,56 @@ impl DataEntry {
         Ok(params)
     }
 
+    /// Splitting the values returned by a contract function into the error code and the results.
+    /// The first value is always the `i32` error code, the following `i32` and `i64` values
+    /// are returned as integers.
+    pub fn from_results(values: &[Val]) -> Result<(i32, Vec<Self>)> {
+        let (code, values) = match values {
+            [Val::I32(code), values @ ..] => (*code, values),
+            _ => {
+                return Err(Error::Runtime(RuntimeError::InvalidResult(String::from(
+                    "Functions must return an i32 error code first",
+                ))))
+            }
+        };
+
+        let results = values
+            .iter()
+            .map(|value| match value {
+                Val::I32(value) => Ok(Self::Integer(*value as i64)),
+                Val::I64(value) => Ok(Self::Integer(*value)),
+                _ => Err(Error::Runtime(RuntimeError::InvalidResult(format!(
+                    "Unsupported type of the result: {:?}",
+                    value.ty()
+                )))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((code, results))
+    }
+
+    /// Counting the arguments of the function receiving the serialized params.
+    /// Binary and string params are passed as an offset and a length.
+    pub fn count_args(input: &[u8]) -> Result<usize> {
+        let mut offset_input: usize = 0;
+        let mut args = 0;
+
+        if input.is_empty() {
+            return Ok(args);
+        }
+
+        let count = utils::get_u16(input, &mut offset_input)?;
+        for _ in 0..count {
+            Self::skip_key(input, &mut offset_input)?;
+            args += match Self::get_value(input, &mut offset_input)? {
+                Self::Integer(_) | Self::Boolean(_) => 1,
+                Self::Binary(_) | Self::String(_) => 2,
+            };
+        }
+
+        Ok(args)
+    }
+
     fn skip_key(input: &[u8], offset: &mut usize) -> Result<()> {
         let length = utils::get_u16(input, offset)?;
         *offset += length as usize;
@@ -193,4 +250,21 @@ mod tests {
         let length = usize::from_str(&result[5]).expect("Failed usize from_str");
         assert_eq!(memory[offset..offset + length], data);
     }
+
+    #[test]
+    fn test_from_results() {
+        assert_eq!(DataEntry::from_results(&[Val::I32(0)]), Ok((0, vec![])));
+        assert_eq!(
+            DataEntry::from_results(&[Val::I32(1), Val::I64(42)]),
+            Ok((1, vec![DataEntry::Integer(42)]))
+        );
+        assert_eq!(
+            DataEntry::from_results(&[Val::I32(0), Val::I32(3), Val::I64(-1)]),
+            Ok((0, vec![DataEntry::Integer(3), DataEntry::Integer(-1)]))
+        );
+        assert!(DataEntry::from_results(&[Val::I64(42), Val::I32(-1)]).is_err());
+        assert!(DataEntry::from_results(&[Val::I64(42)]).is_err());
+        assert!(DataEntry::from_results(&[]).is_err());
+        assert!(DataEntry::from_results(&[Val::I32(0), Val::F64(1.0.into())]).is_err());
+    }
 }
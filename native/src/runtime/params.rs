@@ -1,6 +1,9 @@
 use crate::runtime::data_entry::DataEntry;
 
 /// Structure allowing to accumulate arguments for calling a contract function.
+/// Arguments are serialized in the order they are pushed by the contract,
+/// so the same calls produce the same bytes on every node.
+#[derive(Clone)]
 pub struct Params {
     bytes: Vec<u8>,
     count: u16,
@@ -40,3 +43,26 @@ impl Params {
         self.count = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 26] = [
+        0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 1, 1, 0, 0, 3, 0, 0, 0, 2, 97, 98,
+    ];
+
+    #[test]
+    fn test_serialize_params() {
+        let mut params = Params::default();
+
+        params.push(DataEntry::Integer(42));
+        params.push(DataEntry::Boolean(1));
+        params.push(DataEntry::String(b"ab".to_vec()));
+
+        assert_eq!(params.as_bytes(), BYTES.to_vec());
+
+        params.reset();
+        assert_eq!(params.as_bytes(), vec![0, 0]);
+    }
+}
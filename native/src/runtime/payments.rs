@@ -1,5 +1,18 @@
+use crate::{
+    error::{Error, Result, RuntimeError},
+    runtime::utils,
+};
+
+/// Length of the identifier of an asset, the native token has an empty identifier.
+const ASSET_ID_LENGTH: usize = 32;
+
 /// Structure allowing to accumulate payments for calling a contract function.
-pub struct Payments(pub Vec<(Vec<u8>, i64)>);
+/// Payments are serialized in the order they are pushed by the contract,
+/// so the same calls produce the same bytes on every node.
+///
+/// Payments of the same asset are merged into the first one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Payments(Vec<(Vec<u8>, i64)>);
 
 impl Default for Payments {
     fn default() -> Self {
@@ -8,15 +21,49 @@ impl Default for Payments {
 }
 
 impl Payments {
+    /// Maximum number of assets paid by one call.
+    pub const LIMIT: usize = 100;
+
     /// Initialization of an empty set of function payments.
     pub fn new() -> Self {
         Self(vec![])
     }
 
+    /// Parsing the byte representation of function payments.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut offset = 0;
+        let count = utils::get_u16(bytes, &mut offset)?;
+        let mut payments = Self::new();
+
+        for _ in 0..count {
+            let asset_id = match utils::get_u8(bytes, &mut offset)? {
+                0 => vec![],
+                1 => utils::get_bytes(bytes, &mut offset, ASSET_ID_LENGTH)?,
+                _ => return Err(Error::Runtime(RuntimeError::InvalidPayment)),
+            };
+            let amount = utils::get_u64(bytes, &mut offset)? as i64;
+
+            payments.push(&asset_id, amount)?;
+        }
+
+        Ok(payments)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Iterating over the asset identifiers and amounts in the order of pushing.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], i64)> {
+        self.0
+            .iter()
+            .map(|(asset_id, amount)| (asset_id.as_slice(), *amount))
+    }
+
     /// Getting byte representation of function payments.
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut bytes = vec![];
@@ -41,8 +88,31 @@ impl Payments {
     }
 
     /// Adding an payment to call the contract function.
-    pub fn push(&mut self, asset_id: &[u8], amount: i64) {
-        self.0.push((asset_id.to_vec(), amount));
+    /// The amount must be positive, the total amount of an asset must fit into `i64`.
+    pub fn push(&mut self, asset_id: &[u8], amount: i64) -> Result<()> {
+        self.push_limited(asset_id, amount, Self::LIMIT)
+    }
+
+    /// Adding an payment, at most `limit` assets are paid.
+    pub fn push_limited(&mut self, asset_id: &[u8], amount: i64, limit: usize) -> Result<()> {
+        if amount <= 0 || !(asset_id.is_empty() || asset_id.len() == ASSET_ID_LENGTH) {
+            return Err(Error::Runtime(RuntimeError::InvalidPayment));
+        }
+
+        let count = self.0.len();
+        match self.0.iter_mut().find(|(id, _)| id == asset_id) {
+            Some((_, total)) => {
+                *total = total
+                    .checked_add(amount)
+                    .ok_or(Error::Runtime(RuntimeError::PaymentOverflow))?;
+            }
+            None if count >= limit => {
+                return Err(Error::Runtime(RuntimeError::TooManyPayments));
+            }
+            None => self.0.push((asset_id.to_vec(), amount)),
+        }
+
+        Ok(())
     }
 
     /// Reset payments
@@ -64,15 +134,74 @@ mod tests {
     fn test_serialize_payments() {
         let mut payments = Payments::default();
 
-        payments.push(&[], 42);
-        payments.push(
-            &[
-                1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7,
-                1, 3, 3, 7,
-            ],
-            24,
-        );
+        payments.push(&[], 42).expect("Failed to push payment");
+        payments
+            .push(
+                &[
+                    1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3, 3, 7, 1, 3,
+                    3, 7, 1, 3, 3, 7,
+                ],
+                24,
+            )
+            .expect("Failed to push payment");
 
         assert_eq!(payments.as_bytes(), BYTES.to_vec());
+        assert_eq!(
+            Payments::from_bytes(&BYTES).expect("Failed to parse payments"),
+            payments
+        );
+    }
+
+    #[test]
+    fn test_payments_order() {
+        let mut payments = Payments::default();
+
+        payments.push(&[1; 32], 24).expect("Failed to push payment");
+        payments.push(&[], 42).expect("Failed to push payment");
+
+        // The order of pushing is kept
+        let mut bytes = vec![0, 2, 1];
+        bytes.extend_from_slice(&[1; 32]);
+        bytes.extend_from_slice(&24i64.to_be_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&42i64.to_be_bytes());
+
+        assert_eq!(payments.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_push_payments() {
+        let mut payments = Payments::default();
+
+        payments.push(&[], 40).expect("Failed to push payment");
+        payments.push(&[1; 32], 1).expect("Failed to push payment");
+        payments.push(&[], 2).expect("Failed to push payment");
+        assert_eq!(
+            payments.iter().collect::<Vec<_>>(),
+            vec![(&[][..], 42), (&[1; 32][..], 1)]
+        );
+
+        assert_eq!(
+            payments.push(&[], 0),
+            Err(Error::Runtime(RuntimeError::InvalidPayment))
+        );
+        assert_eq!(
+            payments.push(&[1; 31], 1),
+            Err(Error::Runtime(RuntimeError::InvalidPayment))
+        );
+        assert_eq!(
+            payments.push(&[], i64::MAX),
+            Err(Error::Runtime(RuntimeError::PaymentOverflow))
+        );
+
+        for index in payments.len()..Payments::LIMIT {
+            payments
+                .push(&[index as u8; 32], 1)
+                .expect("Failed to push payment");
+        }
+        assert_eq!(
+            payments.push(&[u8::MAX; 32], 1),
+            Err(Error::Runtime(RuntimeError::TooManyPayments))
+        );
     }
 }
@@ -0,0 +1,618 @@
+//! Execution of the calls of one block.
+//!
+//! A session keeps the compiled modules, the execution stacks of the engine and the
+//! results of storage reads between calls, so the setup costs are paid once per block.
+//!
+//! ```ignore
+//! let mut session = Session::new(node);
+//! for call in calls {
+//!     session.execute(&call)?;
+//! }
+//! session.finish();
+//! ```
+
+use crate::{
+    cancel::CancellationToken,
+    error::{Context, ContextError, Result},
+    exec::{EngineConfig, Executable},
+    external::ExternalFunction,
+    limits::ExecutionLimits,
+    modules::{self, Module as HostModule},
+    node::Node,
+    runtime::data_entry::DataEntry,
+    vm::{ExecutionMode, Vm},
+};
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use wasmi::{Engine, Module, Val};
+
+/// Call of a contract function executed in the session.
+pub struct Call {
+    pub contract_id: Vec<u8>,
+    pub bytecode: Vec<u8>,
+    pub func_name: String,
+    pub params: Vec<u8>,
+    pub fuel_limit: u64,
+}
+
+/// Depth of the call stack and hash of the bytecode.
+type ModuleKey = (usize, Vec<u8>);
+
+/// Modules compiled by the engines of each depth of the call stack, by the hash of the bytecode.
+///
+/// An engine keeps its resources locked for reads while it executes a function,
+/// and linking the host functions of a nested call locks them for writes.
+/// So a module can't be shared between depths, the modules of nested calls
+/// are compiled and instantiated by the engine of their depth.
+pub(crate) struct ModuleCache {
+    config: EngineConfig,
+    engines: Mutex<Vec<Engine>>,
+    modules: Mutex<HashMap<ModuleKey, Arc<Module>>>,
+}
+
+impl ModuleCache {
+    pub(crate) fn new(config: &EngineConfig) -> Self {
+        Self {
+            config: *config,
+            engines: Default::default(),
+            modules: Default::default(),
+        }
+    }
+
+    /// Getting the module compiled for the depth of the call stack,
+    /// the bytecode is compiled on the first request.
+    pub(crate) fn get(&self, bytecode: &[u8], depth: usize) -> Result<Arc<Module>> {
+        let key = (depth, Sha256::digest(bytecode).to_vec());
+
+        if let Some(module) = lock(&self.modules).get(&key) {
+            return Ok(module.clone());
+        }
+
+        let engine = {
+            let mut engines = lock(&self.engines);
+            while engines.len() <= depth {
+                engines.push(Executable::engine(false, &self.config)?);
+            }
+            engines[depth].clone()
+        };
+
+        let module = Arc::new(Executable::compile(&engine, bytecode)?);
+        lock(&self.modules).insert(key, module.clone());
+
+        Ok(module)
+    }
+
+    fn len(&self) -> usize {
+        lock(&self.modules).len()
+    }
+}
+
+/// Locking a cache, the values are consistent even if a call panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Address and key of a storage entry.
+type Entry = (Vec<u8>, Vec<u8>);
+
+struct Shared {
+    node: Box<dyn Node + Send + Sync>,
+    // Results of storage reads
+    storage: Mutex<HashMap<Entry, Vec<u8>>>,
+    contains: Mutex<HashMap<Entry, bool>>,
+}
+
+impl Shared {
+    fn invalidate(&self, address: Option<&[u8]>) {
+        match address {
+            Some(address) => {
+                lock(&self.storage).retain(|key, _| key.0 != address);
+                lock(&self.contains).retain(|key, _| key.0 != address);
+            }
+            None => {
+                lock(&self.storage).clear();
+                lock(&self.contains).clear();
+            }
+        }
+    }
+}
+
+/// Node of the session caching storage reads.
+/// Writes drop the cached values of the contract, restores of snapshots drop all of them.
+#[derive(Clone)]
+struct SessionNode(Arc<Shared>);
+
+/// Calls of the block executed against the node with shared caches.
+pub struct Session {
+    node: SessionNode,
+    limits: ExecutionLimits,
+    mode: ExecutionMode,
+    modules: fn() -> Vec<HostModule>,
+    external: Vec<ExternalFunction>,
+    engine_config: EngineConfig,
+    cache: Arc<ModuleCache>,
+    cancellation: CancellationToken,
+    calls: usize,
+}
+
+impl Session {
+    /// Session with the memory of the node and the host functions of all env modules.
+    pub fn new(node: Box<dyn Node + Send + Sync>) -> Self {
+        Self {
+            node: SessionNode(Arc::new(Shared {
+                node,
+                storage: Default::default(),
+                contains: Default::default(),
+            })),
+            limits: ExecutionLimits::default(),
+            mode: ExecutionMode::default(),
+            modules: modules::all,
+            external: vec![],
+            engine_config: EngineConfig::default(),
+            cache: Arc::new(ModuleCache::new(&EngineConfig::default())),
+            cancellation: CancellationToken::new(),
+            calls: 0,
+        }
+    }
+
+    pub fn with_memory(mut self, memory: (u32, u32)) -> Self {
+        self.limits.memory = memory;
+        self
+    }
+
+    /// Setting the limits of the block, the fuel is set by each call.
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Setting the mode of all calls of the session, e.g. to validate the transactions.
+    pub fn with_mode(mut self, mode: ExecutionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Replacing the host functions available to contracts.
+    pub fn with_modules(mut self, modules: fn() -> Vec<HostModule>) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    /// Configuring the engine of the session, the modules compiled so far are dropped.
+    pub fn with_engine_config(mut self, config: EngineConfig) -> Self {
+        self.engine_config = config;
+        self.cache = Arc::new(ModuleCache::new(&config));
+        self
+    }
+
+    /// Adding host functions defined outside of the crate.
+    pub fn with_external_functions(mut self, functions: Vec<ExternalFunction>) -> Self {
+        self.external = functions;
+        self
+    }
+
+    /// Getting the token cancelling the running and following calls of the session.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Executing the call. The cached storage reads are dropped if the call fails.
+    pub fn execute(&mut self, call: &Call) -> Result<Vec<Val>, ContextError> {
+        self.calls += 1;
+
+        let result = Vm::new(
+            call.contract_id.clone(),
+            call.bytecode.clone(),
+            ExecutionLimits {
+                fuel: call.fuel_limit,
+                ..self.limits
+            },
+            (self.modules)(),
+            Box::new(self.node.clone()),
+            self.mode,
+        )
+        .map_err(|error| ContextError {
+            error,
+            context: Context {
+                contract_id: call.contract_id.clone(),
+                func_name: call.func_name.clone(),
+                host_function: None,
+                frame: 0,
+            },
+        })
+        .and_then(|mut vm| {
+            vm.set_engine_config(self.engine_config);
+            if self.engine_config.cache_modules {
+                vm.set_module_cache(Some(self.cache.clone()));
+            }
+            vm.set_cancellation_token(self.cancellation.clone());
+            vm.set_external_functions(self.external.clone());
+            vm.run(&call.func_name, &call.params)
+        });
+
+        // Functions return the error code first, the following values don't matter
+        let success = matches!(
+            result.as_deref().map(DataEntry::from_results),
+            Ok(Ok((0, _)))
+        );
+        if !success {
+            self.invalidate();
+        }
+
+        result
+    }
+
+    /// Dropping the cached storage reads.
+    /// Must be called if the node discards the changes made by a call.
+    pub fn invalidate(&self) {
+        self.node.0.invalidate(None);
+    }
+
+    /// Closing the session at the end of the block.
+    pub fn finish(self) {
+        debug!(
+            "The session is finished. Calls: {}. Modules: {}",
+            self.calls,
+            self.cache.len()
+        );
+    }
+}
+
+impl Node for SessionNode {
+    fn get_chain_id(&self) -> Result<i8> {
+        self.0.node.get_chain_id()
+    }
+
+    fn require(&self, message: &[u8]) -> Result<()> {
+        self.0.node.require(message)
+    }
+
+    fn get_bytecode(&self, contract_id: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.get_bytecode(contract_id)
+    }
+
+    fn add_payments(&self, contract_id: &[u8], payment_id: &[u8], payments: &[u8]) -> Result<()> {
+        self.0.node.add_payments(contract_id, payment_id, payments)
+    }
+
+    fn is_paused(&self, contract_id: &[u8]) -> Result<bool> {
+        self.0.node.is_paused(contract_id)
+    }
+
+    fn is_feature_activated(&self, feature: &[u8]) -> Result<bool> {
+        self.0.node.is_feature_activated(feature)
+    }
+
+    fn get_balance(&self, asset_id: &[u8], address: &[u8]) -> Result<i64> {
+        self.0.node.get_balance(asset_id, address)
+    }
+
+    fn transfer(
+        &self,
+        contract_id: &[u8],
+        asset_id: &[u8],
+        recipient: &[u8],
+        amount: i64,
+    ) -> Result<()> {
+        self.0
+            .node
+            .transfer(contract_id, asset_id, recipient, amount)
+    }
+
+    fn issue(
+        &self,
+        contract_id: &[u8],
+        name: &[u8],
+        description: &[u8],
+        quantity: i64,
+        decimals: i64,
+        is_reissuable: bool,
+    ) -> Result<Vec<u8>> {
+        self.0.node.issue(
+            contract_id,
+            name,
+            description,
+            quantity,
+            decimals,
+            is_reissuable,
+        )
+    }
+
+    fn burn(&self, contract_id: &[u8], asset_id: &[u8], amount: i64) -> Result<()> {
+        self.0.node.burn(contract_id, asset_id, amount)
+    }
+
+    fn reissue(
+        &self,
+        contract_id: &[u8],
+        asset_id: &[u8],
+        amount: i64,
+        is_reissuable: bool,
+    ) -> Result<()> {
+        self.0
+            .node
+            .reissue(contract_id, asset_id, amount, is_reissuable)
+    }
+
+    fn block(&self, field: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.block(field)
+    }
+
+    fn get_block_at(&self, height: i64) -> Result<Vec<u8>> {
+        self.0.node.get_block_at(height)
+    }
+
+    fn fast_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.fast_hash(bytes)
+    }
+
+    fn secure_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.secure_hash(bytes)
+    }
+
+    fn sig_verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+        self.0.node.sig_verify(message, signature, public_key)
+    }
+
+    fn lease(&self, contract_id: &[u8], recipient: &[u8], amount: i64) -> Result<Vec<u8>> {
+        self.0.node.lease(contract_id, recipient, amount)
+    }
+
+    fn cancel_lease(&self, contract_id: &[u8], lease_id: &[u8]) -> Result<()> {
+        self.0.node.cancel_lease(contract_id, lease_id)
+    }
+
+    fn get_lease_info(&self, lease_id: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.get_lease_info(lease_id)
+    }
+
+    fn check_permission(&self, address: &[u8], role_id: i32) -> Result<bool> {
+        self.0.node.check_permission(address, role_id)
+    }
+
+    fn contains_key(&self, address: &[u8], key: &[u8]) -> Result<bool> {
+        let entry = (address.to_vec(), key.to_vec());
+        if let Some(result) = lock(&self.0.contains).get(&entry) {
+            return Ok(*result);
+        }
+
+        let result = self.0.node.contains_key(address, key)?;
+        lock(&self.0.contains).insert(entry, result);
+        Ok(result)
+    }
+
+    fn get_storage(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        let entry = (address.to_vec(), key.to_vec());
+        if let Some(result) = lock(&self.0.storage).get(&entry) {
+            return Ok(result.clone());
+        }
+
+        let result = self.0.node.get_storage(address, key)?;
+        // Absent keys are read as empty values
+        lock(&self.0.contains).insert(entry.clone(), !result.is_empty());
+        lock(&self.0.storage).insert(entry, result.clone());
+        Ok(result)
+    }
+
+    fn get_storage_binary_into(
+        &self,
+        address: &[u8],
+        key: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        let entry = (address.to_vec(), key.to_vec());
+        if let Some(result) = lock(&self.0.storage).get(&entry) {
+            return crate::node::copy_binary(result, buffer);
+        }
+
+        // Values read into buffers aren't cached, they are written straight into the buffer
+        self.0.node.get_storage_binary_into(address, key, buffer)
+    }
+
+    fn set_storage(&self, contract_id: &[u8], value: &[u8]) -> Result<()> {
+        self.0.invalidate(Some(contract_id));
+        self.0.node.set_storage(contract_id, value)
+    }
+
+    fn delete_storage(&self, contract_id: &[u8], key: &[u8]) -> Result<bool> {
+        self.0.invalidate(Some(contract_id));
+        self.0.node.delete_storage(contract_id, key)
+    }
+
+    fn get_account_data(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.get_account_data(address, key)
+    }
+
+    fn get_tx_payments(&self, payment_id: &[u8]) -> Result<i64> {
+        self.0.node.get_tx_payments(payment_id)
+    }
+
+    fn get_tx_payment_asset_id(&self, payment_id: &[u8], number: i64) -> Result<Vec<u8>> {
+        self.0.node.get_tx_payment_asset_id(payment_id, number)
+    }
+
+    fn get_tx_payment_amount(&self, payment_id: &[u8], number: i64) -> Result<i64> {
+        self.0.node.get_tx_payment_amount(payment_id, number)
+    }
+
+    fn tx(&self, field: &[u8]) -> Result<Vec<u8>> {
+        self.0.node.tx(field)
+    }
+
+    fn snapshot(&self) -> Result<i32> {
+        self.0.node.snapshot()
+    }
+
+    fn restore(&self, snapshot: i32) -> Result<()> {
+        self.0.invalidate(None);
+        self.0.node.restore(snapshot)
+    }
+
+    fn discard(&self, snapshot: i32) -> Result<()> {
+        self.0.node.discard(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{Error, ExecutableError},
+        mock::Mock,
+    };
+
+    const CONTRACT_ID: [u8; 32] = [1; 32];
+
+    const COUNTER: &str = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "get_storage_int" (func $get_storage_int (param i32 i32 i32 i32) (result i32 i64)))
+        (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (local $error i32) (local $value i64)
+            (block $code
+                (call $get_storage_int
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                )
+                (local.set $value)
+                (br_if $code (local.tee $error))
+
+                (local.set $error
+                    (call $set_storage_int
+                        (i32.const 0) ;; Key offset
+                        (i32.const 7) ;; Key length
+                        (i64.add (local.get $value) (i64.const 1))
+                    )
+                )
+            )
+            (local.get $error)
+        )
+
+        (data (i32.const 0) "counter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+    )
+    "#;
+
+    #[test]
+    fn test_session() {
+        let mock = Mock::new();
+        mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+
+        let mut session = Session::new(Box::new(mock.clone()));
+        let call = Call {
+            contract_id: CONTRACT_ID.to_vec(),
+            bytecode: wat::parse_str(COUNTER).expect("WAT code parsing failed"),
+            func_name: String::from("_constructor"),
+            params: vec![0, 0],
+            fuel_limit: 1024,
+        };
+
+        for _ in 0..3 {
+            let result = session.execute(&call).expect("Execution failed");
+            assert_eq!(result[0].i32(), Some(0));
+        }
+
+        // Writes are visible to the next calls
+        assert_eq!(
+            mock.storage(&CONTRACT_ID, b"counter"),
+            Some(DataEntry::Integer(4))
+        );
+        assert_eq!(session.cache.len(), 1);
+
+        let result = session.execute(&Call {
+            bytecode: vec![0, 97, 115, 109],
+            ..call
+        });
+        assert!(matches!(
+            result.map_err(|error| error.error),
+            Err(Error::Executable(ExecutableError::InvalidBytecode(_)))
+        ));
+
+        session.finish();
+    }
+
+    #[test]
+    fn test_contains_cached_by_reads() {
+        let mock = Mock::new();
+        let session = Session::new(Box::new(mock.clone()));
+
+        assert_eq!(
+            session.node.get_storage(&CONTRACT_ID, b"counter"),
+            Ok(vec![])
+        );
+        // Written past the session, so only the cached result of the read is seen
+        mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+        assert_eq!(
+            session.node.contains_key(&CONTRACT_ID, b"counter"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_reads_dropped_on_failure() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "get_storage_int" (func $get_storage_int (param i32 i32 i32 i32) (result i32 i64)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "read") (result i32 i64)
+                (call $get_storage_int
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                )
+            )
+
+            (func (export "fail") (result i32)
+                (i32.const 1)
+            )
+
+            (data (i32.const 0) "counter")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+        )
+        "#;
+
+        let mock = Mock::new();
+        mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+
+        let mut session = Session::new(Box::new(mock.clone()));
+        let call = |func_name: &str| Call {
+            contract_id: CONTRACT_ID.to_vec(),
+            bytecode: wat::parse_str(wat).expect("WAT code parsing failed"),
+            func_name: func_name.to_string(),
+            params: vec![0, 0],
+            fuel_limit: 1024,
+        };
+        let mut read = || {
+            let result = session.execute(&call("read")).expect("Execution failed");
+            result[1].i64()
+        };
+
+        assert_eq!(read(), Some(1));
+        // Written past the session, successful calls returning values keep the cached read
+        mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(2));
+        assert_eq!(read(), Some(1));
+
+        let result = session.execute(&call("fail")).expect("Execution failed");
+        assert_eq!(result[0].i32(), Some(1));
+        let result = session.execute(&call("read")).expect("Execution failed");
+        assert_eq!(result[1].i64(), Some(2));
+    }
+}
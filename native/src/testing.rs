@@ -0,0 +1,360 @@
+//! Harness for integration tests of contracts.
+//!
+//! Contracts are executed by the real virtual machine against the in-memory node,
+//! so the state is kept between calls and the changes made by each call can be asserted.
+//!
+//! ```ignore
+//! let env = TestEnv::new();
+//! env.deploy(&CONTRACT_ID, bytecode);
+//! env.set_balance(&[], &CONTRACT_ID, 100);
+//!
+//! env.call(&CONTRACT_ID, "_constructor", &[DataEntry::Integer(42)])
+//!     .assert_success()
+//!     .assert_write(&CONTRACT_ID, b"value", DataEntry::Integer(42));
+//! ```
+
+use crate::{
+    coverage::Coverage,
+    error::ContextError,
+    limits::ExecutionLimits,
+    mock::{Event, Mock, Write},
+    modules::{self, Module},
+    profile::Profile,
+    runtime::{data_entry::DataEntry, params::Params},
+    vm::{ExecutionMode, Vm},
+    MEMORY, MEMORY_LIMIT,
+};
+use std::cell::RefCell;
+use wasmi::Val;
+
+/// Environment sharing the state of the node between contract calls.
+pub struct TestEnv {
+    mock: Mock,
+    memory: (u32, u32),
+    fuel_limit: u64,
+    memory_limit: u32,
+    deletion_refund: u64,
+    modules: fn() -> Vec<Module>,
+    coverage: RefCell<Option<Coverage>>,
+    profile: RefCell<Option<Profile>>,
+}
+
+impl Default for TestEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestEnv {
+    /// Environment with the memory of the node and the host functions of all env modules.
+    pub fn new() -> Self {
+        Self {
+            mock: Mock::new(),
+            memory: MEMORY,
+            fuel_limit: 1_000_000,
+            memory_limit: MEMORY_LIMIT,
+            deletion_refund: 0,
+            modules: modules::all,
+            coverage: RefCell::new(None),
+            profile: RefCell::new(None),
+        }
+    }
+
+    pub fn with_memory(mut self, memory: (u32, u32)) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    pub fn with_fuel_limit(mut self, fuel_limit: u64) -> Self {
+        self.fuel_limit = fuel_limit;
+        self
+    }
+
+    /// Setting the pages of linear memory available to all contracts of a call.
+    pub fn with_memory_limit(mut self, memory_limit: u32) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// Setting the fuel refunded for each existing data entry removed by a contract.
+    pub fn with_deletion_refund(mut self, deletion_refund: u64) -> Self {
+        self.deletion_refund = deletion_refund;
+        self
+    }
+
+    /// Replacing the host functions available to contracts.
+    pub fn with_modules(mut self, modules: fn() -> Vec<Module>) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    /// Collecting the code coverage of all calls made in the environment.
+    pub fn with_coverage(self) -> Self {
+        self.coverage.replace(Some(Coverage::new()));
+        self
+    }
+
+    /// Coverage collected so far, if enabled.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage.borrow().clone()
+    }
+
+    /// Profiling the fuel consumed by all calls made in the environment.
+    pub fn with_profile(self) -> Self {
+        self.profile.replace(Some(Profile::new()));
+        self
+    }
+
+    /// Profile collected so far, if enabled.
+    pub fn profile(&self) -> Option<Profile> {
+        self.profile.borrow().clone()
+    }
+
+    /// The node used by the environment. Allows to prepare the state
+    /// which has no dedicated helper.
+    pub fn mock(&self) -> &Mock {
+        &self.mock
+    }
+
+    pub fn deploy(&self, contract_id: &[u8], bytecode: Vec<u8>) {
+        self.mock.set_bytecode(contract_id, bytecode);
+    }
+
+    pub fn set_storage(&self, contract_id: &[u8], key: &[u8], value: DataEntry) {
+        self.mock.set_storage_entry(contract_id, key, value);
+    }
+
+    pub fn storage(&self, contract_id: &[u8], key: &[u8]) -> Option<DataEntry> {
+        self.mock.storage(contract_id, key)
+    }
+
+    pub fn set_balance(&self, asset_id: &[u8], holder: &[u8], amount: i64) {
+        self.mock.set_balance(asset_id, holder, amount);
+    }
+
+    pub fn balance(&self, asset_id: &[u8], holder: &[u8]) -> i64 {
+        self.mock.balance(asset_id, holder)
+    }
+
+    /// Calling the function of a deployed contract.
+    pub fn call(&self, contract_id: &[u8], func_name: &str, params: &[DataEntry]) -> Execution {
+        self.call_with_payments(contract_id, func_name, params, &[])
+    }
+
+    /// Calling the function of a deployed contract with payments attached to the transaction.
+    /// An empty `asset_id` stands for the native token.
+    pub fn call_with_payments(
+        &self,
+        contract_id: &[u8],
+        func_name: &str,
+        params: &[DataEntry],
+        payments: &[(&[u8], i64)],
+    ) -> Execution {
+        self.mock.reset();
+
+        for (asset_id, amount) in payments {
+            self.mock.add_payment(contract_id, asset_id, *amount);
+        }
+
+        let mut bytes = Params::new();
+        for param in params {
+            bytes.push(param.clone());
+        }
+
+        let (result, (fuel_consumed, fuel_refunded), peak_memory) =
+            self.execute(contract_id, func_name, &bytes.as_bytes());
+
+        Execution {
+            result,
+            fuel_consumed,
+            fuel_refunded,
+            peak_memory,
+            events: self.mock.events(),
+            write_set: self.mock.write_set(),
+            error_message: self.mock.error_message(),
+        }
+    }
+
+    fn execute(
+        &self,
+        contract_id: &[u8],
+        func_name: &str,
+        params: &[u8],
+    ) -> (Result<Vec<Val>, ContextError>, (u64, u64), u32) {
+        let bytecode = self
+            .mock
+            .bytecode(contract_id)
+            .unwrap_or_else(|| panic!("Contract is not deployed: {:?}", contract_id));
+
+        let mut vm = Vm::new(
+            contract_id.to_vec(),
+            bytecode,
+            ExecutionLimits {
+                memory: self.memory,
+                fuel: self.fuel_limit,
+                memory_limit: self.memory_limit,
+                deletion_refund: self.deletion_refund,
+                ..Default::default()
+            },
+            (self.modules)(),
+            Box::new(self.mock.clone()),
+            ExecutionMode::Apply,
+        )
+        .expect("Call stack creation failed");
+        vm.set_coverage(self.coverage.take());
+        vm.set_profile(self.profile.take());
+
+        let result = vm.run(func_name, params);
+        self.coverage.replace(vm.take_coverage());
+        self.profile.replace(vm.take_profile());
+
+        let fuel = (vm.fuel_consumed(), vm.fuel_refunded());
+        (result, fuel, vm.peak_memory())
+    }
+}
+
+/// Result of a contract call with the changes made to the state of the node.
+#[derive(Debug)]
+pub struct Execution {
+    pub result: Result<Vec<Val>, ContextError>,
+    pub fuel_consumed: u64,
+    /// Fuel refunded for the removed data entries, not deducted from `fuel_consumed`.
+    pub fuel_refunded: u64,
+    /// Pages of linear memory allocated at once by the contracts of the call.
+    pub peak_memory: u32,
+    pub events: Vec<Event>,
+    pub write_set: Vec<Write>,
+    /// Message passed by the contract to `require`.
+    pub error_message: Vec<u8>,
+}
+
+impl Execution {
+    /// Asserting that the function has returned the success code.
+    #[track_caller]
+    pub fn assert_success(&self) -> &Self {
+        match &self.result {
+            Ok(values) => assert!(
+                matches!(DataEntry::from_results(values), Ok((0, _))),
+                "Function returned an error code: {:?}",
+                values
+            ),
+            Err(error) => panic!("Function failed: {}", error),
+        }
+        self
+    }
+
+    /// Asserting that the call has failed with the error code,
+    /// either returned by the function or raised by the virtual machine.
+    #[track_caller]
+    pub fn assert_error(&self, code: i32) -> &Self {
+        let actual = match &self.result {
+            Ok(values) => match DataEntry::from_results(values) {
+                Ok((code, _)) if code != 0 => code,
+                _ => panic!("Function succeeded: {:?}", values),
+            },
+            Err(error) => error.as_i32(),
+        };
+        assert_eq!(actual, code, "Unexpected error code");
+        self
+    }
+
+    /// Asserting that the function has succeeded and returned the values.
+    #[track_caller]
+    pub fn assert_results(&self, results: &[DataEntry]) -> &Self {
+        self.assert_success();
+        let actual = match &self.result {
+            Ok(values) => DataEntry::from_results(values).map(|(_, results)| results),
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(actual, Ok(results.to_vec()), "Unexpected results");
+        self
+    }
+
+    /// Asserting that the value was written to the storage during the call.
+    #[track_caller]
+    pub fn assert_write(&self, contract_id: &[u8], key: &[u8], value: DataEntry) -> &Self {
+        let write = Write {
+            contract_id: contract_id.to_vec(),
+            key: key.to_vec(),
+            value,
+        };
+        assert!(
+            self.write_set.contains(&write),
+            "Write {:?} not found in {:?}",
+            write,
+            self.write_set
+        );
+        self
+    }
+
+    /// Asserting that the event was emitted during the call.
+    #[track_caller]
+    pub fn assert_event(&self, event: &Event) -> &Self {
+        assert!(
+            self.events.contains(event),
+            "Event {:?} not found in {:?}",
+            event,
+            self.events
+        );
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ExecutableError,
+        tests::{deploy, test_env, CONTRACT_ID, COUNTER},
+    };
+
+    #[test]
+    fn test_chained_calls() {
+        let env = test_env(COUNTER);
+        env.set_storage(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+
+        env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(2)])
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"counter", DataEntry::Integer(3));
+
+        let execution = env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(4)]);
+        execution
+            .assert_success()
+            .assert_write(&CONTRACT_ID, b"counter", DataEntry::Integer(7));
+        assert_eq!(execution.write_set.len(), 1);
+        assert!(execution.fuel_consumed > 0);
+
+        assert_eq!(
+            env.storage(&CONTRACT_ID, b"counter"),
+            Some(DataEntry::Integer(7))
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let env = TestEnv::new().with_coverage();
+        deploy(&env, &CONTRACT_ID, COUNTER);
+        env.set_storage(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+
+        env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(2)])
+            .assert_success();
+        env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(2)])
+            .assert_success();
+
+        let coverage = env.coverage().expect("Coverage is not enabled");
+        let contract = coverage.contract(&CONTRACT_ID).expect("Contract not found");
+
+        // The constructor is not called
+        assert_eq!(contract.total(), 4);
+        assert_eq!(contract.hits, vec![0, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_error() {
+        let env = test_env(COUNTER);
+
+        // The counter is not initialized
+        env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(2)])
+            .assert_error(ExecutableError::FailedDeserialize.as_i32());
+    }
+}
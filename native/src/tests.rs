@@ -1,9 +1,12 @@
 use crate::{
-    error::{Error, ExecutableError, Result},
-    vm::Vm,
+    error::{Error, ExecutableError, JvmError, Result, RuntimeError},
+    limits::{ExecutionLimits, REFUND_QUOTIENT},
+    mock::Mock,
+    runtime::data_entry::DataEntry,
+    testing::TestEnv,
+    vm::{ExecutionMode, Vm},
 };
-use jni::{InitArgsBuilder, JNIVersion, JavaVM};
-use wasmi::Value;
+use wasmi::Val;
 use wevm_proc_macro::module;
 
 /// Converts the given `.wat` into `.wasm`.
@@ -11,6 +14,64 @@ pub fn wat2wasm(wat: &str) -> Result<Vec<u8>, wat::Error> {
     wat::parse_str(wat)
 }
 
+/// Contract deployed by `test_env`.
+pub const CONTRACT_ID: [u8; 32] = [1; 32];
+
+/// Contract adding the argument of `increment` to the `counter` entry.
+pub const COUNTER: &str = r#"
+(module
+    (import "env" "memory" (memory 2 16))
+
+    (import "env0" "get_storage_int" (func $get_storage_int (param i32 i32 i32 i32) (result i32 i64)))
+    (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+    (func (export "_constructor") (result i32)
+        (i32.const 0)
+    )
+
+    (func (export "increment") (param $p0 i64) (result i32)
+        (local $error i32) (local $value i64)
+        (block $code
+            (call $get_storage_int
+                (i32.const 0) ;; Address offset
+                (i32.const 0) ;; Address length
+                (i32.const 0) ;; Key offset
+                (i32.const 7) ;; Key length
+            )
+            (local.set $value)
+            (br_if $code (local.tee $error))
+
+            (br_if $code
+                (local.tee $error
+                    (call $set_storage_int
+                        (i32.const 0) ;; Key offset
+                        (i32.const 7) ;; Key length
+                        (i64.add (local.get $value) (local.get $p0))
+                    )
+                )
+            )
+        )
+        (local.get $error)
+    )
+
+    (data (i32.const 0) "counter")
+
+    (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+)
+"#;
+
+/// Deploying the contract in the WAT format into the test environment.
+pub fn deploy(env: &TestEnv, contract_id: &[u8], wat: &str) {
+    env.deploy(contract_id, wat2wasm(wat).expect("WAT code parsing failed"));
+}
+
+/// Test environment with the contract deployed as `CONTRACT_ID`.
+pub fn test_env(wat: &str) -> TestEnv {
+    let env = TestEnv::new();
+    deploy(&env, &CONTRACT_ID, wat);
+    env
+}
+
 #[module(env0)]
 mod test {
     fn test_set_value(value: u32) {
@@ -38,40 +99,34 @@ mod test {
             assert_eq!("Hi", result);
         }
     }
-}
 
-struct TestRunner {
-    java_vm: JavaVM,
+    #[since(feature = "test")]
+    fn test_since() -> Result<u32> {
+        |mut _caller: Caller<Runtime>| Ok(42)
+    }
+
+    #[fuel(base = 100, per_byte = 2)]
+    fn test_fuel(offset: *const u8, length: usize) {
+        |mut _caller: Caller<Runtime>| {
+            let _ = (offset, length);
+        }
+    }
+
+    fn test_pair(value: u32) -> Result<(u32, i64)> {
+        |mut _caller: Caller<Runtime>| Ok((value, 42))
+    }
 }
 
+/// Runs the `_constructor` of the contract with the host functions of the test module.
+/// Contracts are executed against the in-memory node.
+struct TestRunner;
+
 impl TestRunner {
     pub fn new() -> Self {
-        // Preparing a fake jvm to initialize the call stack
-        let jvm_args = InitArgsBuilder::new()
-            .version(JNIVersion::V8)
-            .option("-Xcheck:jni")
-            .build()
-            .expect("Failed to initialize JVM args");
-        let java_vm = JavaVM::new(jvm_args).expect("JavaVM initialization failed");
-
-        Self { java_vm }
+        Self
     }
 
-    pub fn run(&self, wat: &str, params: Vec<u8>) -> Result<Vec<Value>> {
-        // Preparing a fake jvm to initialize the call stack
-        let env = self
-            .java_vm
-            .attach_current_thread()
-            .expect("Failed attaches the current thread to the Java VM");
-
-        let jvm = env
-            .get_java_vm()
-            .expect("Failed receiving JavaVM interface");
-        let array = env.new_byte_array(1).expect("Array creation failed");
-        let global_ref = env
-            .new_global_ref(array)
-            .expect("Error callback new_global_ref");
-
+    pub fn run(&self, wat: &str, params: Vec<u8>) -> Result<Vec<Val>> {
         let bytecode = wat2wasm(wat).expect("WAT code parsing failed");
         let memory: (u32, u32) = (1, 1);
         let fuel_limit = 1024;
@@ -79,15 +134,20 @@ impl TestRunner {
         let mut stack = Vm::new(
             vec![],
             bytecode,
-            memory,
-            fuel_limit,
+            ExecutionLimits {
+                memory,
+                fuel: fuel_limit,
+                ..Default::default()
+            },
             modules::modules(),
-            Some(jvm),
-            Some(global_ref),
+            Box::new(Mock::new()),
+            ExecutionMode::Apply,
         )
         .expect("Call stack creation failed");
 
-        stack.run("_constructor", &params)
+        stack
+            .run("_constructor", &params)
+            .map_err(|error| error.error)
     }
 }
 
@@ -174,6 +234,41 @@ fn test_vm() {
         assert_eq!(values[0].i32(), Some(0));
     }
 
+    // Fuel test
+    {
+        let wat = |length: u32| {
+            format!(
+                r#"
+                (module
+                    (import "env0" "test_fuel" (func $test_fuel (param i32 i32)))
+
+                    (func (export "_constructor") (result i32)
+                        (call $test_fuel
+                            (i32.const 0)
+                            (i32.const {}))
+
+                        (i32.const 0)
+                    )
+
+                    (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+                )
+                "#,
+                length
+            )
+        };
+
+        // 100 + 2 * 16 fuel fits into the limit
+        let result = runner.run(&wat(16), vec![]);
+        assert!(result.is_ok());
+
+        // 100 + 2 * 512 fuel exceeds the limit
+        let result = runner.run(&wat(512), vec![]);
+        assert!(matches!(
+            result,
+            Err(Error::Runtime(RuntimeError::OutOfFuel(message))) if message.starts_with("limit 1024")
+        ));
+    }
+
     // Args test
     {
         let wat = r#"
@@ -224,10 +319,889 @@ fn test_vm() {
             ],
         );
 
-        if let Err(Error::Executable(ExecutableError::InvalidBytecode(_))) = result {
-            assert!(true);
-        } else {
-            assert!(false);
-        }
+        assert!(matches!(
+            result,
+            Err(Error::Executable(ExecutableError::InvalidBytecode(_)))
+        ));
+    }
+}
+
+#[test]
+fn test_since() {
+    let mock = Mock::new();
+    mock.set_feature_activated("test", false);
+
+    let mut vm = Vm::new(
+        vec![],
+        vec![],
+        ExecutionLimits {
+            memory: (1, 1),
+            fuel: 0,
+            ..Default::default()
+        },
+        vec![],
+        Box::new(mock.clone()),
+        ExecutionMode::Apply,
+    )
+    .expect("Call stack creation failed");
+    let engine = wasmi::Engine::default();
+    let mut store = wasmi::Store::new(&engine, crate::runtime::Runtime::new(&mut vm));
+
+    let func = modules::modules()
+        .into_iter()
+        .map(|item| item(&mut store).expect("Linking failed"))
+        .find(|(_, name, _)| name == "test_since")
+        .map(|(_, _, func)| func)
+        .expect("Function not found");
+
+    let mut results = [Val::I32(0), Val::I32(0)];
+    let error = func
+        .call(&mut store, &[], &mut results)
+        .expect_err("Function is not stubbed");
+
+    assert_eq!(
+        error.downcast::<Error>(),
+        Some(Error::Runtime(RuntimeError::FeatureNotActivated(
+            "test".to_string()
+        )))
+    );
+
+    // The activation is requested once per execution
+    mock.set_feature_activated("test", true);
+    assert_eq!(store.data_mut().vm.feature_activated("test"), Ok(false));
+
+    // Errors of the node fail the linking instead of stubbing the function
+    let trace = crate::trace::Trace::new();
+    let mut vm = Vm::new(
+        vec![],
+        vec![],
+        ExecutionLimits {
+            memory: (1, 1),
+            fuel: 0,
+            ..Default::default()
+        },
+        vec![],
+        Box::new(crate::trace::Replayer::new(&trace)),
+        ExecutionMode::Apply,
+    )
+    .expect("Call stack creation failed");
+    let mut store = wasmi::Store::new(&engine, crate::runtime::Runtime::new(&mut vm));
+
+    let linked: Result<Vec<_>> = modules::modules()
+        .into_iter()
+        .map(|item| item(&mut store))
+        .collect();
+    assert!(linked.is_err());
+}
+
+#[test]
+fn test_bindings() {
+    assert_eq!(
+        RUST_BINDINGS,
+        "#[link(wasm_import_module = \"env0\")]\n\
+        extern \"C\" {\n    \
+            pub fn test_set_value(value: u32);\n    \
+            pub fn test_get_value() -> u32;\n    \
+            pub fn test_memory(offset: u32, length: u32);\n    \
+            pub fn test_since() -> (i32, u32);\n    \
+            pub fn test_fuel(offset: *const u8, length: usize);\n    \
+            pub fn test_pair(value: u32) -> (i32, u32, i64);\n\
+        }\n"
+    );
+
+    assert_eq!(
+        ASSEMBLYSCRIPT_BINDINGS,
+        "// @ts-ignore: decorator\n\
+        @external(\"env0\", \"test_set_value\")\n\
+        export declare function test_set_value(value: u32): void;\n\
+        \n\
+        // @ts-ignore: decorator\n\
+        @external(\"env0\", \"test_get_value\")\n\
+        export declare function test_get_value(): u32;\n\
+        \n\
+        // @ts-ignore: decorator\n\
+        @external(\"env0\", \"test_memory\")\n\
+        export declare function test_memory(offset: u32, length: u32): void;\n\
+        \n\
+        // env0.test_since(): (i32, u32) returns multiple values and isn't declared\n\
+        \n\
+        // @ts-ignore: decorator\n\
+        @external(\"env0\", \"test_fuel\")\n\
+        export declare function test_fuel(offset: usize, length: usize): void;\n\
+        \n\
+        // env0.test_pair(value: u32): (i32, u32, i64) returns multiple values and isn't declared\n"
+    );
+}
+
+#[test]
+fn test_execution_mode() {
+    let wat = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+        (import "env0" "burn" (func $burn (param i32 i32 i64) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "write") (result i32)
+            (call $set_storage_int
+                (i32.const 0) ;; Key offset
+                (i32.const 7) ;; Key length
+                (i64.const 1)
+            )
+        )
+
+        (func (export "burn") (result i32)
+            (call $burn
+                (i32.const 0) ;; Asset id offset
+                (i32.const 0) ;; Asset id length
+                (i64.const 1)
+            )
+        )
+
+        (data (i32.const 0) "counter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+    )
+    "#;
+    let bytecode = wat2wasm(wat).expect("WAT code parsing failed");
+    let forbidden = RuntimeError::ForbiddenInMode(String::new()).as_i32();
+
+    let run = |mode: ExecutionMode, func_name: &str| {
+        let mut vm = Vm::new(
+            vec![],
+            bytecode.clone(),
+            ExecutionLimits {
+                memory: (2, 16),
+                fuel: 1024,
+                ..Default::default()
+            },
+            crate::modules::all(),
+            Box::new(Mock::new()),
+            mode,
+        )
+        .expect("Call stack creation failed");
+
+        let result = vm.run(func_name, &[]).expect("Execution failed");
+        result[0].i32().expect("Invalid result")
+    };
+
+    assert_eq!(run(ExecutionMode::Apply, "write"), 0);
+    assert_ne!(run(ExecutionMode::Apply, "burn"), forbidden);
+    // The validation has the result of the application
+    assert_eq!(run(ExecutionMode::Validate, "write"), 0);
+    assert_eq!(
+        run(ExecutionMode::Validate, "burn"),
+        run(ExecutionMode::Apply, "burn")
+    );
+    assert_eq!(run(ExecutionMode::ReadOnly, "write"), forbidden);
+    assert_eq!(run(ExecutionMode::ReadOnly, "burn"), forbidden);
+
+    assert_eq!(ExecutionMode::from_i32(1), Ok(ExecutionMode::Validate));
+    assert!(matches!(
+        ExecutionMode::from_i32(3),
+        Err(Error::Jvm(JvmError::InvalidArgument(_)))
+    ));
+}
+
+#[cfg(feature = "dev")]
+#[test]
+fn test_wat_input() {
+    let wat = r#"
+    (module
+        (func (export "_constructor") (result i32)
+            (i32.const 42)
+        )
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+    )
+    "#;
+
+    let mut vm = Vm::new(
+        vec![],
+        wat.as_bytes().to_vec(),
+        ExecutionLimits {
+            memory: (1, 1),
+            fuel: 1024,
+            ..Default::default()
+        },
+        modules::modules(),
+        Box::new(Mock::new()),
+        ExecutionMode::Apply,
+    )
+    .expect("Call stack creation failed");
+
+    let result = vm.run("_constructor", &[]).expect("Execution failed");
+    assert_eq!(result[0].i32(), Some(42));
+
+    let mut vm = Vm::new(
+        vec![],
+        b"(module".to_vec(),
+        ExecutionLimits {
+            memory: (1, 1),
+            fuel: 1024,
+            ..Default::default()
+        },
+        modules::modules(),
+        Box::new(Mock::new()),
+        ExecutionMode::Apply,
+    )
+    .expect("Call stack creation failed");
+
+    let result = vm.run("_constructor", &[]).map_err(|error| error.error);
+    assert!(matches!(
+        result,
+        Err(Error::Executable(ExecutableError::InvalidBytecode(_)))
+    ));
+}
+
+#[test]
+fn test_profile() {
+    let env = TestEnv::new().with_profile();
+    deploy(&env, &CONTRACT_ID, COUNTER);
+    env.set_storage(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+
+    let first = env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(2)]);
+    let second = env.call(&CONTRACT_ID, "increment", &[DataEntry::Integer(2)]);
+
+    let profile = env.profile().expect("Profiling is not enabled");
+    let stats = profile
+        .guest_function(&CONTRACT_ID, "increment")
+        .expect("Function not found");
+    assert_eq!(stats.calls, 2);
+    assert_eq!(profile.total(), first.fuel_consumed + second.fuel_consumed);
+    assert!(profile.guest_time(&CONTRACT_ID, "increment").is_some());
+    assert_eq!(profile.collapsed().lines().count(), 1);
+
+    let stats = profile
+        .host_function("env0::set_storage_int")
+        .expect("Host function not found");
+    assert_eq!(stats.calls, 2);
+
+    const INTERNAL_ID: [u8; 32] = [2; 32];
+
+    let internal = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func $square (param $value i64) (result i64)
+            (i64.mul (local.get $value) (local.get $value))
+        )
+
+        (func (export "outer") (result i32)
+            (drop (call $square (call $square (i64.const 3))))
+            (i32.const 0)
+        )
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+    )
+    "#;
+    deploy(&env, &INTERNAL_ID, internal);
+
+    // Internal functions are profiled as a part of the called function
+    let execution = env.call(&INTERNAL_ID, "outer", &[]);
+    execution.assert_success();
+    let profile = env.profile().expect("Profiling is not enabled");
+    let stats = profile
+        .guest_function(&INTERNAL_ID, "outer")
+        .expect("Function not found");
+    assert_eq!(stats.fuel, execution.fuel_consumed);
+    assert_eq!(
+        profile
+            .guest_functions()
+            .filter(|(contract_id, _, _)| *contract_id == INTERNAL_ID)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn test_rollback_nested_call() {
+    const CALLEE_ID: [u8; 32] = [2; 32];
+
+    let caller = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "call_fail") (result i32)
+            (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 4))
+        )
+
+        (func (export "call_write") (result i32)
+            (call $call_contract (i32.const 0) (i32.const 32) (i32.const 36) (i32.const 5))
+        )
+
+        (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 32) "failwrite")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 41))
+    )
+    "#;
+
+    let callee = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "fail") (result i32)
+            (drop (call $set_storage_int (i32.const 0) (i32.const 7) (i64.const 13)))
+            (i32.const 1)
+        )
+
+        (func (export "write") (result i32)
+            (call $set_storage_int (i32.const 0) (i32.const 7) (i64.const 42))
+        )
+
+        (data (i32.const 0) "counter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+    )
+    "#;
+
+    let env = test_env(caller);
+    deploy(&env, &CALLEE_ID, callee);
+
+    let execution = env.call(&CONTRACT_ID, "call_fail", &[]);
+    execution.assert_error(1);
+    assert!(execution.write_set.is_empty());
+    assert_eq!(env.storage(&CALLEE_ID, b"counter"), None);
+
+    env.call(&CONTRACT_ID, "call_write", &[])
+        .assert_success()
+        .assert_write(&CALLEE_ID, b"counter", DataEntry::Integer(42));
+}
+
+#[test]
+fn test_deletion_refund() {
+    const CALLEE_ID: [u8; 32] = [2; 32];
+
+    let caller = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+        (import "env1" "delete_storage" (func $delete_storage (param i32 i32) (result i32 i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "delete") (result i32)
+            (call $delete_storage (i32.const 36) (i32.const 7))
+            (drop)
+        )
+
+        ;; Succeeds if the callee has failed with its own code
+        (func (export "call_fail") (result i32)
+            (i32.ne
+                (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 4))
+                (i32.const 1)
+            )
+        )
+
+        (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 32) "failcounter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 43))
+    )
+    "#;
+
+    let callee = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env1" "delete_storage" (func $delete_storage (param i32 i32) (result i32 i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "fail") (result i32)
+            (call $delete_storage (i32.const 0) (i32.const 7))
+            (drop)
+            (drop)
+            (i32.const 1)
+        )
+
+        (data (i32.const 0) "counter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+    )
+    "#;
+
+    let env = test_env(caller).with_deletion_refund(1);
+    deploy(&env, &CALLEE_ID, callee);
+
+    env.set_storage(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+    let execution = env.call(&CONTRACT_ID, "delete", &[]);
+    execution.assert_success();
+    assert_eq!(execution.fuel_refunded, 1);
+    assert_eq!(env.storage(&CONTRACT_ID, b"counter"), None);
+
+    // Absent keys aren't refunded
+    let execution = env.call(&CONTRACT_ID, "delete", &[]);
+    execution.assert_success();
+    assert_eq!(execution.fuel_refunded, 0);
+
+    // Removals of a failed nested call are rolled back with their refunds
+    env.set_storage(&CALLEE_ID, b"counter", DataEntry::Integer(1));
+    let execution = env.call(&CONTRACT_ID, "call_fail", &[]);
+    execution.assert_success();
+    assert_eq!(execution.fuel_refunded, 0);
+    assert_eq!(
+        env.storage(&CALLEE_ID, b"counter"),
+        Some(DataEntry::Integer(1))
+    );
+
+    // The refund is bounded by a fraction of the consumed fuel
+    let env = test_env(caller).with_deletion_refund(u64::MAX);
+    env.set_storage(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+    let execution = env.call(&CONTRACT_ID, "delete", &[]);
+    execution.assert_success();
+    assert_eq!(
+        execution.fuel_refunded,
+        execution.fuel_consumed / REFUND_QUOTIENT
+    );
+
+    // Views can't remove entries
+    let mut vm = Vm::new(
+        CONTRACT_ID.to_vec(),
+        wat2wasm(caller).expect("WAT code parsing failed"),
+        ExecutionLimits::default(),
+        crate::modules::all(),
+        Box::new(env.mock().clone()),
+        ExecutionMode::ReadOnly,
+    )
+    .expect("Call stack creation failed");
+    let result = vm
+        .run("delete", &[0, 0])
+        .map(|values| values[0].i32())
+        .map_err(|error| error.error);
+    assert_eq!(
+        result,
+        Ok(Some(RuntimeError::ForbiddenInMode(String::new()).as_i32()))
+    );
+}
+
+#[test]
+fn test_memory_limit() {
+    const CALLEE_ID: [u8; 32] = [2; 32];
+
+    let caller = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "call_grow") (result i32)
+            (local $error i32)
+            (block $code
+                (br_if $code
+                    (local.tee $error
+                        (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 4))
+                    )
+                )
+                (local.set $error
+                    (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 4))
+                )
+            )
+            (local.get $error)
+        )
+
+        (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 32) "grow")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 36))
+    )
+    "#;
+
+    let callee = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "grow") (result i32)
+            (i32.eq (memory.grow (i32.const 4)) (i32.const -1))
+        )
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+    )
+    "#;
+
+    let deploy_all = |env: &TestEnv| {
+        deploy(env, &CONTRACT_ID, caller);
+        deploy(env, &CALLEE_ID, callee);
+    };
+
+    // Memory of the first call is released before the second one
+    let env = TestEnv::new().with_memory_limit(8);
+    deploy_all(&env);
+    let execution = env.call(&CONTRACT_ID, "call_grow", &[]);
+    execution.assert_success();
+    assert_eq!(execution.peak_memory, 8);
+
+    // Growth is rejected
+    let env = TestEnv::new().with_memory_limit(6);
+    deploy_all(&env);
+    let execution = env.call(&CONTRACT_ID, "call_grow", &[]);
+    execution.assert_error(1);
+    assert_eq!(execution.peak_memory, 4);
+
+    // Instantiation is rejected
+    let env = TestEnv::new().with_memory_limit(3);
+    deploy_all(&env);
+    env.call(&CONTRACT_ID, "call_grow", &[])
+        .assert_error(ExecutableError::MemoryLimits(String::new()).as_i32());
+}
+
+#[test]
+fn test_payment_balance() {
+    const CALLEE_ID: [u8; 32] = [2; 32];
+
+    let caller = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "call_payment" (func $call_payment (param i32 i32 i64) (result i32)))
+        (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func $pay (param $amount i64) (param $offset i32) (param $length i32) (result i32)
+            (local $error i32)
+            (block $code
+                (br_if $code
+                    (local.tee $error
+                        (call $call_payment (i32.const 0) (i32.const 0) (local.get $amount))
+                    )
+                )
+                (local.set $error
+                    (call $call_contract (i32.const 0) (i32.const 32) (local.get $offset) (local.get $length))
+                )
+            )
+            (local.get $error)
+        )
+
+        (func (export "pay") (param $amount i64) (result i32)
+            (call $pay (local.get $amount) (i32.const 32) (i32.const 7))
+        )
+
+        (func (export "pay_reject") (param $amount i64) (result i32)
+            (call $pay (local.get $amount) (i32.const 39) (i32.const 6))
+        )
+
+        ;; The rejected payments must not be carried by the next call
+        (func (export "pay_then_call") (param $amount i64) (result i32)
+            (local $error i32)
+            (local.set $error
+                (call $pay (local.get $amount) (i32.const 32) (i32.const 7))
+            )
+            (if (i32.eqz (local.get $error))
+                (then (return (i32.const -1)))
+            )
+            (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 7))
+        )
+
+        (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 32) "receivereject")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 45))
+    )
+    "#;
+
+    let callee = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "receive") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "reject") (result i32)
+            (i32.const 1)
+        )
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+    )
+    "#;
+
+    let env = test_env(caller);
+    deploy(&env, &CALLEE_ID, callee);
+    env.set_balance(&[], &CONTRACT_ID, 10);
+
+    env.call(&CONTRACT_ID, "pay", &[DataEntry::Integer(4)])
+        .assert_success();
+    assert_eq!(env.balance(&[], &CALLEE_ID), 4);
+
+    env.call(&CONTRACT_ID, "pay", &[DataEntry::Integer(7)])
+        .assert_error(RuntimeError::InsufficientBalance.as_i32());
+    env.call(&CONTRACT_ID, "pay", &[DataEntry::Integer(-1)])
+        .assert_error(RuntimeError::InvalidPayment.as_i32());
+    assert_eq!(env.balance(&[], &CONTRACT_ID), 6);
+
+    let execution = env.call(&CONTRACT_ID, "pay_then_call", &[DataEntry::Integer(7)]);
+    execution.assert_success();
+    assert!(execution.events.is_empty());
+    assert_eq!(env.balance(&[], &CONTRACT_ID), 6);
+
+    // Payments to the failed callee are refunded
+    let execution = env.call(&CONTRACT_ID, "pay_reject", &[DataEntry::Integer(3)]);
+    execution.assert_error(1);
+    assert!(execution.events.is_empty());
+    assert_eq!(env.balance(&[], &CONTRACT_ID), 6);
+    assert_eq!(env.balance(&[], &CALLEE_ID), 4);
+}
+
+#[test]
+fn test_libraries() {
+    use base58::ToBase58;
+
+    const LIBRARY_ID: [u8; 32] = [2; 32];
+    const DATA_ID: [u8; 32] = [3; 32];
+
+    let contract = format!(
+        r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+        (import "{}" "add" (func $add (param i64 i64) (result i64)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "sum") (result i32)
+            (call $set_storage_int
+                (i32.const 0) ;; Key offset
+                (i32.const 3) ;; Key length
+                (call $add (i64.const 40) (i64.const 2))
+            )
+        )
+
+        (data (i32.const 0) "sum")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 3))
+    )
+    "#,
+        LIBRARY_ID.to_base58()
+    );
+
+    let library = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "add") (param $a i64) (param $b i64) (result i64)
+            (i64.add (local.get $a) (local.get $b))
+        )
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+    )
+    "#;
+
+    let env = TestEnv::new();
+    deploy(&env, &CONTRACT_ID, &contract);
+    deploy(&env, &LIBRARY_ID, library);
+
+    env.call(&CONTRACT_ID, "sum", &[])
+        .assert_success()
+        .assert_write(&CONTRACT_ID, b"sum", DataEntry::Integer(42));
+
+    // Data segments of libraries would overwrite the memory of the contract
+    env.deploy(
+        &DATA_ID,
+        wat::parse_str(library.replace("(global", "(data (i32.const 0) \"data\") (global"))
+            .expect("Failed to parse WAT"),
+    );
+    env.deploy(
+        &CONTRACT_ID,
+        wat::parse_str(contract.replace(&LIBRARY_ID.to_base58(), &DATA_ID.to_base58()))
+            .expect("Failed to parse WAT"),
+    );
+
+    env.call(&CONTRACT_ID, "sum", &[])
+        .assert_error(ExecutableError::LinkerError(String::new()).as_i32());
+
+    // Allocators of libraries would hand out the memory of the contract
+    let allocator = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func $alloc (export "alloc") (param $size i32) (result i32)
+            (global.get $heap)
+            (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+        )
+
+        (func (export "add") (param $a i64) (param $b i64) (result i64)
+            (i64.store (call $alloc (i32.const 8)) (i64.add (local.get $a) (local.get $b)))
+            (i64.load (i32.sub (global.get $heap) (i32.const 8)))
+        )
+
+        (global $heap (mut i32) (i32.const 0))
+        (global $__heap_base (export "__heap_base") i32 (i32.const 0))
+    )
+    "#;
+    let static_memory = library.replace("(i32.const 0))", "(i32.const 1024))");
+
+    for library in [allocator, &static_memory] {
+        deploy(&env, &DATA_ID, library);
+        env.call(&CONTRACT_ID, "sum", &[])
+            .assert_error(ExecutableError::LinkerError(String::new()).as_i32());
     }
 }
+
+#[test]
+fn test_check_permission() {
+    let wat = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env1" "check_permission" (func $check_permission (param i32 i32 i32) (result i32 i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "is_admin") (result i32 i32)
+            (call $check_permission
+                (i32.const 0) ;; Address offset
+                (i32.const 5) ;; Address length
+                (i32.const 1) ;; Role
+            )
+        )
+
+        (func (export "is_miner") (result i32 i32)
+            (call $check_permission
+                (i32.const 0) ;; Address offset
+                (i32.const 5) ;; Address length
+                (i32.const 2) ;; Role
+            )
+        )
+
+        (data (i32.const 0) "alice")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 5))
+    )
+    "#;
+
+    let env = test_env(wat);
+    env.call(&CONTRACT_ID, "is_admin", &[])
+        .assert_results(&[DataEntry::Integer(0)]);
+
+    env.mock().add_role(b"alice", 1);
+    env.call(&CONTRACT_ID, "is_admin", &[])
+        .assert_results(&[DataEntry::Integer(1)]);
+    // Roles are checked one by one
+    env.call(&CONTRACT_ID, "is_miner", &[])
+        .assert_results(&[DataEntry::Integer(0)]);
+}
+
+#[test]
+fn test_paused_callee() {
+    const CALLEE_ID: [u8; 32] = [2; 32];
+
+    let caller = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "call_read") (result i32)
+            (call $call_contract (i32.const 0) (i32.const 32) (i32.const 32) (i32.const 4))
+        )
+
+        (func (export "call_write") (result i32)
+            (call $call_contract (i32.const 0) (i32.const 32) (i32.const 36) (i32.const 5))
+        )
+
+        (data (i32.const 0) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 16) "\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02\02")
+        (data (i32.const 32) "readwrite")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 41))
+    )
+    "#;
+
+    let callee = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "contains_key" (func $contains_key (param i32 i32 i32 i32) (result i32 i32)))
+        (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+        (func (export "_constructor") (result i32)
+            (i32.const 0)
+        )
+
+        (func (export "read") (result i32)
+            (drop (call $contains_key (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 7)))
+        )
+
+        (func (export "write") (result i32)
+            (call $set_storage_int (i32.const 0) (i32.const 7) (i64.const 42))
+        )
+
+        (data (i32.const 0) "counter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+    )
+    "#;
+
+    let env = test_env(caller);
+    deploy(&env, &CALLEE_ID, callee);
+    env.mock().set_paused(&CALLEE_ID, true);
+
+    // Paused contracts can be queried
+    env.call(&CONTRACT_ID, "call_read", &[]).assert_success();
+
+    let execution = env.call(&CONTRACT_ID, "call_write", &[]);
+    execution.assert_error(RuntimeError::ContractPaused.as_i32());
+    assert!(execution.write_set.is_empty());
+}
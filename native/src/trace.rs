@@ -0,0 +1,757 @@
+use crate::{
+    error::{Error, ExecutableError, Result, RuntimeError},
+    node::Node,
+    runtime::utils,
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+/// Header of the serialized trace.
+const MAGIC: &[u8] = b"WEVMTRACE";
+const VERSION: u8 = 1;
+
+/// Response of the node to a call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    Unit,
+    Bool(bool),
+    I8(i8),
+    I32(i32),
+    I64(i64),
+    Bytes(Vec<u8>),
+    Error(Error),
+}
+
+impl From<()> for Response {
+    fn from(_: ()) -> Self {
+        Response::Unit
+    }
+}
+
+impl From<bool> for Response {
+    fn from(value: bool) -> Self {
+        Response::Bool(value)
+    }
+}
+
+impl From<i8> for Response {
+    fn from(value: i8) -> Self {
+        Response::I8(value)
+    }
+}
+
+impl From<i32> for Response {
+    fn from(value: i32) -> Self {
+        Response::I32(value)
+    }
+}
+
+impl From<i64> for Response {
+    fn from(value: i64) -> Self {
+        Response::I64(value)
+    }
+}
+
+impl From<Vec<u8>> for Response {
+    fn from(value: Vec<u8>) -> Self {
+        Response::Bytes(value)
+    }
+}
+
+/// Converting a response served by the replayer to the type expected by the caller.
+trait FromResponse: Sized {
+    fn from_response(response: Response) -> Option<Self>;
+}
+
+impl FromResponse for () {
+    fn from_response(response: Response) -> Option<Self> {
+        matches!(response, Response::Unit).then_some(())
+    }
+}
+
+impl FromResponse for bool {
+    fn from_response(response: Response) -> Option<Self> {
+        match response {
+            Response::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromResponse for i8 {
+    fn from_response(response: Response) -> Option<Self> {
+        match response {
+            Response::I8(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromResponse for i32 {
+    fn from_response(response: Response) -> Option<Self> {
+        match response {
+            Response::I32(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromResponse for i64 {
+    fn from_response(response: Response) -> Option<Self> {
+        match response {
+            Response::I64(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromResponse for Vec<u8> {
+    fn from_response(response: Response) -> Option<Self> {
+        match response {
+            Response::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// A call to the node together with its response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interaction {
+    pub method: String,
+    /// Arguments of the call, numbers are in big-endian byte order.
+    pub args: Vec<Vec<u8>>,
+    pub response: Response,
+}
+
+/// Interactions with the node in the order of the execution.
+///
+/// Clones share the same interactions, so one clone can be passed to the `Recorder`
+/// while the other is used to save the trace after the execution.
+#[derive(Clone, Debug, Default)]
+pub struct Trace(Rc<RefCell<Vec<Interaction>>>);
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.0.borrow().clone()
+    }
+
+    fn push(&self, interaction: Interaction) {
+        self.0.borrow_mut().push(interaction);
+    }
+
+    /// Getting byte representation of the trace.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let interactions = self.0.borrow();
+
+        let mut result = MAGIC.to_vec();
+        result.push(VERSION);
+        result.extend_from_slice(&(interactions.len() as u32).to_be_bytes());
+
+        for interaction in interactions.iter() {
+            result.extend_from_slice(&(interaction.method.len() as u16).to_be_bytes());
+            result.extend_from_slice(interaction.method.as_bytes());
+
+            result.extend_from_slice(&(interaction.args.len() as u16).to_be_bytes());
+            for arg in &interaction.args {
+                result.extend_from_slice(&(arg.len() as u32).to_be_bytes());
+                result.extend_from_slice(arg);
+            }
+
+            match &interaction.response {
+                Response::Unit => result.push(0),
+                Response::Bool(value) => result.extend_from_slice(&[1, *value as u8]),
+                Response::I8(value) => result.extend_from_slice(&[2, *value as u8]),
+                Response::I32(value) => {
+                    result.push(6);
+                    result.extend_from_slice(&value.to_be_bytes());
+                }
+                Response::I64(value) => {
+                    result.push(3);
+                    result.extend_from_slice(&value.to_be_bytes());
+                }
+                Response::Bytes(value) => {
+                    result.push(4);
+                    result.extend_from_slice(&(value.len() as u32).to_be_bytes());
+                    result.extend_from_slice(value);
+                }
+                Response::Error(error) => {
+                    let reason = error.reason().unwrap_or_default();
+                    result.push(5);
+                    result.extend_from_slice(&error.as_i32().to_be_bytes());
+                    result.extend_from_slice(&(reason.len() as u32).to_be_bytes());
+                    result.extend_from_slice(reason.as_bytes());
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Restoring the trace from its byte representation.
+    pub fn from_bytes(input: &[u8]) -> Result<Self> {
+        let error = || Error::Executable(ExecutableError::FailedDeserialize);
+        let mut offset = 0;
+
+        if utils::get_bytes(input, &mut offset, MAGIC.len())? != MAGIC
+            || utils::get_u8(input, &mut offset)? != VERSION
+        {
+            return Err(error());
+        }
+
+        let count = utils::get_u32(input, &mut offset)?;
+        let mut interactions = vec![];
+
+        for _ in 0..count {
+            let length = utils::get_u16(input, &mut offset)?;
+            let method = String::from_utf8(utils::get_bytes(input, &mut offset, length as usize)?)
+                .map_err(|_| error())?;
+
+            let mut args = vec![];
+            for _ in 0..utils::get_u16(input, &mut offset)? {
+                let length = utils::get_u32(input, &mut offset)?;
+                args.push(utils::get_bytes(input, &mut offset, length as usize)?);
+            }
+
+            let response = match utils::get_u8(input, &mut offset)? {
+                0 => Response::Unit,
+                1 => Response::Bool(utils::get_u8(input, &mut offset)? != 0),
+                2 => Response::I8(utils::get_u8(input, &mut offset)? as i8),
+                3 => Response::I64(utils::get_u64(input, &mut offset)? as i64),
+                4 => {
+                    let length = utils::get_u32(input, &mut offset)?;
+                    Response::Bytes(utils::get_bytes(input, &mut offset, length as usize)?)
+                }
+                5 => {
+                    let code = utils::get_u32(input, &mut offset)? as i32;
+                    let length = utils::get_u32(input, &mut offset)?;
+                    let reason = utils::get_bytes(input, &mut offset, length as usize)?;
+                    let reason = String::from_utf8(reason).map_err(|_| error())?;
+                    Response::Error(Error::from_code(code, &reason).ok_or_else(error)?)
+                }
+                6 => Response::I32(utils::get_u32(input, &mut offset)? as i32),
+                _ => return Err(error()),
+            };
+
+            interactions.push(Interaction {
+                method,
+                args,
+                response,
+            });
+        }
+
+        Ok(Self(Rc::new(RefCell::new(interactions))))
+    }
+}
+
+fn int(value: i64) -> Vec<u8> {
+    value.to_be_bytes().to_vec()
+}
+
+/// Backend recording every call to the wrapped node and its response.
+pub struct Recorder {
+    node: Box<dyn Node>,
+    trace: Trace,
+}
+
+impl Recorder {
+    pub fn new(node: Box<dyn Node>, trace: Trace) -> Self {
+        Self { node, trace }
+    }
+
+    fn record<T>(&self, method: &str, args: Vec<Vec<u8>>, result: Result<T>) -> Result<T>
+    where
+        T: Clone + Into<Response>,
+    {
+        let response = match &result {
+            Ok(value) => value.clone().into(),
+            Err(error) => Response::Error(error.clone()),
+        };
+
+        self.trace.push(Interaction {
+            method: method.to_string(),
+            args,
+            response,
+        });
+
+        result
+    }
+}
+
+/// Backend serving the responses of a recorded trace.
+/// Calls must be made in the recorded order with the recorded arguments.
+pub struct Replayer {
+    interactions: Vec<Interaction>,
+    position: Cell<usize>,
+}
+
+impl Replayer {
+    pub fn new(trace: &Trace) -> Self {
+        Self {
+            interactions: trace.interactions(),
+            position: Cell::new(0),
+        }
+    }
+
+    /// Checking that all recorded interactions have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.position.get() == self.interactions.len()
+    }
+
+    fn replay<T: FromResponse>(&self, method: &str, args: Vec<Vec<u8>>) -> Result<T> {
+        let mismatch = |message: String| {
+            Error::Runtime(RuntimeError::Exception(format!(
+                "Replay mismatch at {}: {}",
+                self.position.get(),
+                message
+            )))
+        };
+
+        let interaction = self
+            .interactions
+            .get(self.position.get())
+            .ok_or_else(|| mismatch(format!("unexpected call {}", method)))?;
+
+        if interaction.method != method {
+            return Err(mismatch(format!(
+                "expected {}, got {}",
+                interaction.method, method
+            )));
+        }
+
+        if interaction.args != args {
+            return Err(mismatch(format!("arguments of {} differ", method)));
+        }
+
+        self.position.set(self.position.get() + 1);
+
+        match interaction.response.clone() {
+            Response::Error(error) => Err(error),
+            response => T::from_response(response)
+                .ok_or_else(|| mismatch(format!("response type of {}", method))),
+        }
+    }
+}
+
+/// Implements `Node` for the backends of the trace.
+/// Every method passes its name, the arguments converted to bytes and the call to the wrapped node.
+macro_rules! node {
+    ($backend:ty, |$self:ident, $method:ident, $args:ident, $call:ident| $body:expr) => {
+        impl Node for $backend {
+            fn get_chain_id(&$self) -> Result<i8> {
+                let ($method, $args) = ("get_chain_id", vec![]);
+                let $call = |node: &dyn Node| node.get_chain_id();
+                $body
+            }
+
+            fn require(&$self, message: &[u8]) -> Result<()> {
+                let ($method, $args) = ("require", vec![message.to_vec()]);
+                let $call = |node: &dyn Node| node.require(message);
+                $body
+            }
+
+            fn get_bytecode(&$self, contract_id: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("get_bytecode", vec![contract_id.to_vec()]);
+                let $call = |node: &dyn Node| node.get_bytecode(contract_id);
+                $body
+            }
+
+            fn add_payments(
+                &$self,
+                contract_id: &[u8],
+                payment_id: &[u8],
+                payments: &[u8],
+            ) -> Result<()> {
+                let ($method, $args) = (
+                    "add_payments",
+                    vec![contract_id.to_vec(), payment_id.to_vec(), payments.to_vec()],
+                );
+                let $call = |node: &dyn Node| node.add_payments(contract_id, payment_id, payments);
+                $body
+            }
+
+            fn is_paused(&$self, contract_id: &[u8]) -> Result<bool> {
+                let ($method, $args) = ("is_paused", vec![contract_id.to_vec()]);
+                let $call = |node: &dyn Node| node.is_paused(contract_id);
+                $body
+            }
+
+            fn is_feature_activated(&$self, feature: &[u8]) -> Result<bool> {
+                let ($method, $args) = ("is_feature_activated", vec![feature.to_vec()]);
+                let $call = |node: &dyn Node| node.is_feature_activated(feature);
+                $body
+            }
+
+            fn get_balance(&$self, asset_id: &[u8], address: &[u8]) -> Result<i64> {
+                let ($method, $args) = ("get_balance", vec![asset_id.to_vec(), address.to_vec()]);
+                let $call = |node: &dyn Node| node.get_balance(asset_id, address);
+                $body
+            }
+
+            fn transfer(
+                &$self,
+                contract_id: &[u8],
+                asset_id: &[u8],
+                recipient: &[u8],
+                amount: i64,
+            ) -> Result<()> {
+                let ($method, $args) = (
+                    "transfer",
+                    vec![
+                        contract_id.to_vec(),
+                        asset_id.to_vec(),
+                        recipient.to_vec(),
+                        int(amount),
+                    ],
+                );
+                let $call =
+                    |node: &dyn Node| node.transfer(contract_id, asset_id, recipient, amount);
+                $body
+            }
+
+            fn issue(
+                &$self,
+                contract_id: &[u8],
+                name: &[u8],
+                description: &[u8],
+                quantity: i64,
+                decimals: i64,
+                is_reissuable: bool,
+            ) -> Result<Vec<u8>> {
+                let ($method, $args) = (
+                    "issue",
+                    vec![
+                        contract_id.to_vec(),
+                        name.to_vec(),
+                        description.to_vec(),
+                        int(quantity),
+                        int(decimals),
+                        vec![is_reissuable as u8],
+                    ],
+                );
+                let $call = |node: &dyn Node| {
+                    node.issue(
+                        contract_id,
+                        name,
+                        description,
+                        quantity,
+                        decimals,
+                        is_reissuable,
+                    )
+                };
+                $body
+            }
+
+            fn burn(&$self, contract_id: &[u8], asset_id: &[u8], amount: i64) -> Result<()> {
+                let ($method, $args) = (
+                    "burn",
+                    vec![contract_id.to_vec(), asset_id.to_vec(), int(amount)],
+                );
+                let $call = |node: &dyn Node| node.burn(contract_id, asset_id, amount);
+                $body
+            }
+
+            fn reissue(
+                &$self,
+                contract_id: &[u8],
+                asset_id: &[u8],
+                amount: i64,
+                is_reissuable: bool,
+            ) -> Result<()> {
+                let ($method, $args) = (
+                    "reissue",
+                    vec![
+                        contract_id.to_vec(),
+                        asset_id.to_vec(),
+                        int(amount),
+                        vec![is_reissuable as u8],
+                    ],
+                );
+                let $call =
+                    |node: &dyn Node| node.reissue(contract_id, asset_id, amount, is_reissuable);
+                $body
+            }
+
+            fn block(&$self, field: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("block", vec![field.to_vec()]);
+                let $call = |node: &dyn Node| node.block(field);
+                $body
+            }
+
+            fn get_block_at(&$self, height: i64) -> Result<Vec<u8>> {
+                let ($method, $args) = ("get_block_at", vec![int(height)]);
+                let $call = |node: &dyn Node| node.get_block_at(height);
+                $body
+            }
+
+            fn fast_hash(&$self, bytes: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("fast_hash", vec![bytes.to_vec()]);
+                let $call = |node: &dyn Node| node.fast_hash(bytes);
+                $body
+            }
+
+            fn secure_hash(&$self, bytes: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("secure_hash", vec![bytes.to_vec()]);
+                let $call = |node: &dyn Node| node.secure_hash(bytes);
+                $body
+            }
+
+            fn sig_verify(
+                &$self,
+                message: &[u8],
+                signature: &[u8],
+                public_key: &[u8],
+            ) -> Result<bool> {
+                let ($method, $args) = (
+                    "sig_verify",
+                    vec![message.to_vec(), signature.to_vec(), public_key.to_vec()],
+                );
+                let $call = |node: &dyn Node| node.sig_verify(message, signature, public_key);
+                $body
+            }
+
+            fn lease(&$self, contract_id: &[u8], recipient: &[u8], amount: i64) -> Result<Vec<u8>> {
+                let ($method, $args) = (
+                    "lease",
+                    vec![contract_id.to_vec(), recipient.to_vec(), int(amount)],
+                );
+                let $call = |node: &dyn Node| node.lease(contract_id, recipient, amount);
+                $body
+            }
+
+            fn cancel_lease(&$self, contract_id: &[u8], lease_id: &[u8]) -> Result<()> {
+                let ($method, $args) =
+                    ("cancel_lease", vec![contract_id.to_vec(), lease_id.to_vec()]);
+                let $call = |node: &dyn Node| node.cancel_lease(contract_id, lease_id);
+                $body
+            }
+
+            fn get_lease_info(&$self, lease_id: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("get_lease_info", vec![lease_id.to_vec()]);
+                let $call = |node: &dyn Node| node.get_lease_info(lease_id);
+                $body
+            }
+
+            fn check_permission(&$self, address: &[u8], role_id: i32) -> Result<bool> {
+                let ($method, $args) = (
+                    "check_permission",
+                    vec![address.to_vec(), role_id.to_be_bytes().to_vec()],
+                );
+                let $call = |node: &dyn Node| node.check_permission(address, role_id);
+                $body
+            }
+
+            fn contains_key(&$self, address: &[u8], key: &[u8]) -> Result<bool> {
+                let ($method, $args) = ("contains_key", vec![address.to_vec(), key.to_vec()]);
+                let $call = |node: &dyn Node| node.contains_key(address, key);
+                $body
+            }
+
+            fn get_storage(&$self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("get_storage", vec![address.to_vec(), key.to_vec()]);
+                let $call = |node: &dyn Node| node.get_storage(address, key);
+                $body
+            }
+
+            fn set_storage(&$self, contract_id: &[u8], value: &[u8]) -> Result<()> {
+                let ($method, $args) = ("set_storage", vec![contract_id.to_vec(), value.to_vec()]);
+                let $call = |node: &dyn Node| node.set_storage(contract_id, value);
+                $body
+            }
+
+            fn delete_storage(&$self, contract_id: &[u8], key: &[u8]) -> Result<bool> {
+                let ($method, $args) = ("delete_storage", vec![contract_id.to_vec(), key.to_vec()]);
+                let $call = |node: &dyn Node| node.delete_storage(contract_id, key);
+                $body
+            }
+
+            fn get_account_data(&$self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("get_account_data", vec![address.to_vec(), key.to_vec()]);
+                let $call = |node: &dyn Node| node.get_account_data(address, key);
+                $body
+            }
+
+            fn get_tx_payments(&$self, payment_id: &[u8]) -> Result<i64> {
+                let ($method, $args) = ("get_tx_payments", vec![payment_id.to_vec()]);
+                let $call = |node: &dyn Node| node.get_tx_payments(payment_id);
+                $body
+            }
+
+            fn get_tx_payment_asset_id(&$self, payment_id: &[u8], number: i64) -> Result<Vec<u8>> {
+                let ($method, $args) = (
+                    "get_tx_payment_asset_id",
+                    vec![payment_id.to_vec(), int(number)],
+                );
+                let $call = |node: &dyn Node| node.get_tx_payment_asset_id(payment_id, number);
+                $body
+            }
+
+            fn get_tx_payment_amount(&$self, payment_id: &[u8], number: i64) -> Result<i64> {
+                let ($method, $args) = (
+                    "get_tx_payment_amount",
+                    vec![payment_id.to_vec(), int(number)],
+                );
+                let $call = |node: &dyn Node| node.get_tx_payment_amount(payment_id, number);
+                $body
+            }
+
+            fn tx(&$self, field: &[u8]) -> Result<Vec<u8>> {
+                let ($method, $args) = ("tx", vec![field.to_vec()]);
+                let $call = |node: &dyn Node| node.tx(field);
+                $body
+            }
+
+            fn snapshot(&$self) -> Result<i32> {
+                let ($method, $args) = ("snapshot", vec![]);
+                let $call = |node: &dyn Node| node.snapshot();
+                $body
+            }
+
+            fn restore(&$self, snapshot: i32) -> Result<()> {
+                let ($method, $args) = ("restore", vec![snapshot.to_be_bytes().to_vec()]);
+                let $call = |node: &dyn Node| node.restore(snapshot);
+                $body
+            }
+
+            fn discard(&$self, snapshot: i32) -> Result<()> {
+                let ($method, $args) = ("discard", vec![snapshot.to_be_bytes().to_vec()]);
+                let $call = |node: &dyn Node| node.discard(snapshot);
+                $body
+            }
+        }
+    };
+}
+
+node!(Recorder, |self, method, args, call| self.record(
+    method,
+    args,
+    call(self.node.as_ref())
+));
+
+node!(Replayer, |self, method, args, call| {
+    let _ = call;
+    self.replay(method, args)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        limits::ExecutionLimits,
+        mock::Mock,
+        runtime::data_entry::DataEntry,
+        vm::{ExecutionMode, Vm},
+    };
+
+    const CONTRACT_ID: [u8; 32] = [1; 32];
+
+    const CONTRACT: &str = r#"
+    (module
+        (import "env" "memory" (memory 2 16))
+
+        (import "env0" "get_storage_int" (func $get_storage_int (param i32 i32 i32 i32) (result i32 i64)))
+        (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+        (func (export "_constructor") (param $p0 i64) (result i32)
+            (local $error i32) (local $value i64)
+            (block $code
+                (call $get_storage_int
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                )
+                (local.set $value)
+                (br_if $code (local.tee $error))
+
+                (local.set $error
+                    (call $set_storage_int
+                        (i32.const 0) ;; Key offset
+                        (i32.const 7) ;; Key length
+                        (i64.add (local.get $value) (local.get $p0))
+                    )
+                )
+            )
+            (local.get $error)
+        )
+
+        (data (i32.const 0) "counter")
+
+        (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+    )
+    "#;
+
+    fn run(node: Box<dyn Node>, param: i64) -> Result<i32> {
+        let bytecode = wat::parse_str(CONTRACT).expect("WAT code parsing failed");
+        let mut params = crate::runtime::params::Params::new();
+        params.push(DataEntry::Integer(param));
+
+        let mut vm = Vm::new(
+            CONTRACT_ID.to_vec(),
+            bytecode,
+            ExecutionLimits {
+                memory: (2, 16),
+                fuel: 1024,
+                ..Default::default()
+            },
+            crate::modules::all(),
+            node,
+            ExecutionMode::Apply,
+        )?;
+
+        let result = vm
+            .run("_constructor", &params.as_bytes())
+            .map_err(|error| error.error)?;
+        Ok(result[0].i32().unwrap_or_default())
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let mock = Mock::new();
+        mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(1));
+
+        let trace = Trace::new();
+        let recorder = Recorder::new(Box::new(mock.clone()), trace.clone());
+        assert_eq!(run(Box::new(recorder), 2), Ok(0));
+        assert_eq!(
+            mock.storage(&CONTRACT_ID, b"counter"),
+            Some(DataEntry::Integer(3))
+        );
+
+        let methods: Vec<String> = trace
+            .interactions()
+            .into_iter()
+            .map(|interaction| interaction.method)
+            .collect();
+        assert_eq!(methods, vec!["get_storage", "is_paused", "set_storage"]);
+
+        let trace = Trace::from_bytes(&trace.to_bytes()).expect("Failed to restore trace");
+        assert_eq!(run(Box::new(Replayer::new(&trace)), 2), Ok(0));
+
+        // A different value is written to the storage
+        let replayer = Replayer::new(&trace);
+        assert_eq!(run(Box::new(replayer), 3), Ok(300));
+    }
+
+    #[test]
+    fn test_serialize_errors() {
+        let trace = Trace::new();
+        trace.push(Interaction {
+            method: String::from("tx"),
+            args: vec![b"id".to_vec()],
+            response: Response::Error(Error::Runtime(RuntimeError::Exception(String::from(
+                "Unknown field",
+            )))),
+        });
+
+        let restored = Trace::from_bytes(&trace.to_bytes()).expect("Failed to restore trace");
+        assert_eq!(restored.interactions(), trace.interactions());
+
+        assert!(Trace::from_bytes(b"WEVMTRACE").is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Read-only execution of contract functions.
+//!
+//! Views let the node query the state through the logic of contracts. Views are executed in the
+//! `ExecutionMode::ReadOnly` mode, host functions changing the state fail with
+//! `RuntimeError::ForbiddenInMode` and the fuel is limited by `FUEL_LIMIT` instead of the budget
+//! of the block. The node is wrapped as well, so its state can't be changed by other means.
+
+use crate::{
+    error::Result,
+    external,
+    limits::ExecutionLimits,
+    modules,
+    node::Node,
+    runtime::data_entry::DataEntry,
+    vm::{ExecutionMode, Vm},
+};
+use log::error;
+
+/// Fuel available to a view function.
+pub const FUEL_LIMIT: u64 = 10_000_000;
+
+/// Executing the function of a contract deployed on the node without changing the state.
+/// Returns the error code of the function and its results, see `DataEntry::from_results`.
+pub fn run(
+    contract_id: &[u8],
+    func_name: &str,
+    params: &[u8],
+    node: Box<dyn Node>,
+) -> Result<(i32, Vec<DataEntry>)> {
+    let bytecode = node.get_bytecode(contract_id)?;
+
+    let mut vm = Vm::new(
+        contract_id.to_vec(),
+        bytecode,
+        ExecutionLimits {
+            fuel: FUEL_LIMIT,
+            ..Default::default()
+        },
+        modules::all(),
+        Box::new(ReadOnlyNode(node)),
+        ExecutionMode::ReadOnly,
+    )?;
+    vm.set_external_functions(external::registered());
+
+    let values = vm.run(func_name, params).map_err(|error| {
+        error!("{}", error);
+        error.error
+    })?;
+
+    DataEntry::from_results(&values)
+}
+
+/// Node rejecting the changes of the state.
+struct ReadOnlyNode(Box<dyn Node>);
+
+fn forbidden<T>() -> Result<T> {
+    Err(ExecutionMode::ReadOnly.forbidden("State change"))
+}
+
+impl Node for ReadOnlyNode {
+    fn get_chain_id(&self) -> Result<i8> {
+        self.0.get_chain_id()
+    }
+
+    fn require(&self, message: &[u8]) -> Result<()> {
+        self.0.require(message)
+    }
+
+    fn get_bytecode(&self, contract_id: &[u8]) -> Result<Vec<u8>> {
+        self.0.get_bytecode(contract_id)
+    }
+
+    fn add_payments(
+        &self,
+        _contract_id: &[u8],
+        _payment_id: &[u8],
+        _payments: &[u8],
+    ) -> Result<()> {
+        forbidden()
+    }
+
+    fn is_paused(&self, contract_id: &[u8]) -> Result<bool> {
+        self.0.is_paused(contract_id)
+    }
+
+    fn is_feature_activated(&self, feature: &[u8]) -> Result<bool> {
+        self.0.is_feature_activated(feature)
+    }
+
+    fn get_balance(&self, asset_id: &[u8], address: &[u8]) -> Result<i64> {
+        self.0.get_balance(asset_id, address)
+    }
+
+    fn transfer(
+        &self,
+        _contract_id: &[u8],
+        _asset_id: &[u8],
+        _recipient: &[u8],
+        _amount: i64,
+    ) -> Result<()> {
+        forbidden()
+    }
+
+    fn issue(
+        &self,
+        _contract_id: &[u8],
+        _name: &[u8],
+        _description: &[u8],
+        _quantity: i64,
+        _decimals: i64,
+        _is_reissuable: bool,
+    ) -> Result<Vec<u8>> {
+        forbidden()
+    }
+
+    fn burn(&self, _contract_id: &[u8], _asset_id: &[u8], _amount: i64) -> Result<()> {
+        forbidden()
+    }
+
+    fn reissue(
+        &self,
+        _contract_id: &[u8],
+        _asset_id: &[u8],
+        _amount: i64,
+        _is_reissuable: bool,
+    ) -> Result<()> {
+        forbidden()
+    }
+
+    fn block(&self, field: &[u8]) -> Result<Vec<u8>> {
+        self.0.block(field)
+    }
+
+    fn get_block_at(&self, height: i64) -> Result<Vec<u8>> {
+        self.0.get_block_at(height)
+    }
+
+    fn fast_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        self.0.fast_hash(bytes)
+    }
+
+    fn secure_hash(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        self.0.secure_hash(bytes)
+    }
+
+    fn sig_verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool> {
+        self.0.sig_verify(message, signature, public_key)
+    }
+
+    fn lease(&self, _contract_id: &[u8], _recipient: &[u8], _amount: i64) -> Result<Vec<u8>> {
+        forbidden()
+    }
+
+    fn cancel_lease(&self, _contract_id: &[u8], _lease_id: &[u8]) -> Result<()> {
+        forbidden()
+    }
+
+    fn get_lease_info(&self, lease_id: &[u8]) -> Result<Vec<u8>> {
+        self.0.get_lease_info(lease_id)
+    }
+
+    fn check_permission(&self, address: &[u8], role_id: i32) -> Result<bool> {
+        self.0.check_permission(address, role_id)
+    }
+
+    fn contains_key(&self, address: &[u8], key: &[u8]) -> Result<bool> {
+        self.0.contains_key(address, key)
+    }
+
+    fn get_storage(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        self.0.get_storage(address, key)
+    }
+
+    fn get_storage_binary_into(
+        &self,
+        address: &[u8],
+        key: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        self.0.get_storage_binary_into(address, key, buffer)
+    }
+
+    fn set_storage(&self, _contract_id: &[u8], _value: &[u8]) -> Result<()> {
+        forbidden()
+    }
+
+    fn delete_storage(&self, _contract_id: &[u8], _key: &[u8]) -> Result<bool> {
+        forbidden()
+    }
+
+    fn get_account_data(&self, address: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        self.0.get_account_data(address, key)
+    }
+
+    fn get_tx_payments(&self, payment_id: &[u8]) -> Result<i64> {
+        self.0.get_tx_payments(payment_id)
+    }
+
+    fn get_tx_payment_asset_id(&self, payment_id: &[u8], number: i64) -> Result<Vec<u8>> {
+        self.0.get_tx_payment_asset_id(payment_id, number)
+    }
+
+    fn get_tx_payment_amount(&self, payment_id: &[u8], number: i64) -> Result<i64> {
+        self.0.get_tx_payment_amount(payment_id, number)
+    }
+
+    fn tx(&self, field: &[u8]) -> Result<Vec<u8>> {
+        self.0.tx(field)
+    }
+
+    fn snapshot(&self) -> Result<i32> {
+        self.0.snapshot()
+    }
+
+    fn restore(&self, snapshot: i32) -> Result<()> {
+        self.0.restore(snapshot)
+    }
+
+    fn discard(&self, snapshot: i32) -> Result<()> {
+        self.0.discard(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::RuntimeError, mock::Mock};
+
+    const CONTRACT_ID: [u8; 32] = [1; 32];
+
+    #[test]
+    fn test_view() {
+        let wat = r#"
+        (module
+            (import "env" "memory" (memory 2 16))
+
+            (import "env0" "get_storage_int" (func $get_storage_int (param i32 i32 i32 i32) (result i32 i64)))
+            (import "env0" "set_storage_int" (func $set_storage_int (param i32 i32 i64) (result i32)))
+
+            (func (export "_constructor") (result i32)
+                (i32.const 0)
+            )
+
+            (func (export "get") (result i32 i64)
+                (call $get_storage_int
+                    (i32.const 0) ;; Address offset
+                    (i32.const 0) ;; Address length
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                )
+            )
+
+            (func (export "reset") (result i32)
+                (call $set_storage_int
+                    (i32.const 0) ;; Key offset
+                    (i32.const 7) ;; Key length
+                    (i64.const 0)
+                )
+            )
+
+            (func (export "spin") (result i32)
+                (loop $loop
+                    (br $loop)
+                )
+                (i32.const 0)
+            )
+
+            (data (i32.const 0) "counter")
+
+            (global $__heap_base (export "__heap_base") i32 (i32.const 7))
+        )
+        "#;
+        let bytecode = wat::parse_str(wat).expect("WAT code parsing failed");
+
+        let mock = Mock::new();
+        mock.set_bytecode(&CONTRACT_ID, bytecode);
+        mock.set_storage_entry(&CONTRACT_ID, b"counter", DataEntry::Integer(42));
+
+        let result = run(&CONTRACT_ID, "get", &[], Box::new(mock.clone()));
+        assert_eq!(result, Ok((0, vec![DataEntry::Integer(42)])));
+
+        // Errors of host functions are returned to the contract
+        let result = run(&CONTRACT_ID, "reset", &[], Box::new(mock.clone()));
+        assert_eq!(
+            result,
+            Ok((
+                RuntimeError::ForbiddenInMode(String::new()).as_i32(),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            mock.storage(&CONTRACT_ID, b"counter"),
+            Some(DataEntry::Integer(42))
+        );
+
+        // Views are bounded by their own fuel limit
+        assert!(run(&CONTRACT_ID, "spin", &[], Box::new(mock)).is_err());
+    }
+}
@@ -1,22 +1,30 @@
 use crate::{
-    error::{Error, ExecutableError, Result},
-    exec::{Executable, LoadableFunction},
+    bytecode::{self, Inspection},
+    cancel::CancellationToken,
+    coverage::Coverage,
+    error::{Context, ContextError, Error, ExecutableError, JvmError, Result, RuntimeError},
+    exec::{EngineConfig, Executable, LoadableFunction},
+    external::ExternalFunction,
+    limits::{ExecutionLimits, REFUND_QUOTIENT},
     modules::Module,
-    runtime::payment_id::PaymentId,
+    node::Node,
+    profile::Profile,
+    runtime::{data_entry::DataEntry, payment_id::PaymentId},
+    session::ModuleCache,
 };
 use base58::ToBase58;
-use jni::{objects::GlobalRef, JavaVM};
 use log::debug;
-use std::str::FromStr;
-use wasmi::Value;
-
-const MAX_FRAMES: usize = 64;
+use std::{cmp::Ordering, collections::BTreeMap, str::FromStr, sync::Arc, time::Instant};
+use wasmi::Val;
+use wasmparser::ValType;
 
 /// A frame of the call stack that stores the `contract_id` and `bytecode` of the contract.
 pub struct Frame {
     contract_id: Vec<u8>,
     bytecode: Vec<u8>,
     nonce: u64,
+    /// Contract whose storage is used by the delegate call.
+    delegator: Option<Vec<u8>>,
 }
 
 impl Frame {
@@ -24,11 +32,80 @@ impl Frame {
         self.contract_id.clone()
     }
 
+    /// Contract whose storage is read and written by the storage host functions.
+    /// The bytecode of a delegate call works with the storage of the calling contract.
+    pub fn storage_id(&self) -> Vec<u8> {
+        self.delegator
+            .clone()
+            .unwrap_or_else(|| self.contract_id.clone())
+    }
+
+    /// Checking that the frame is executed by a delegate call.
+    pub fn is_delegated(&self) -> bool {
+        self.delegator.is_some()
+    }
+
     pub fn payment_id(&self) -> Vec<u8> {
         PaymentId::new(self.contract_id.clone(), self.nonce).as_bytes()
     }
 }
 
+/// Purpose of the execution, restricting the host functions changing the state.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ExecutionMode {
+    /// Executing the transaction, the state can be changed.
+    #[default]
+    Apply,
+    /// Validating the transaction before it's applied. Host functions behave as in `Apply`,
+    /// so the validation has the result of the application, the node discards the changes.
+    Validate,
+    /// Querying the state, the state can't be changed.
+    ReadOnly,
+}
+
+impl ExecutionMode {
+    pub(crate) fn forbidden(self, change: &str) -> Error {
+        Error::Runtime(RuntimeError::ForbiddenInMode(format!(
+            "{} in the {:?} mode",
+            change, self
+        )))
+    }
+
+    /// Mode passed by the node: `0` applies, `1` validates and `2` queries the state.
+    pub fn from_i32(value: i32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Apply),
+            1 => Ok(Self::Validate),
+            2 => Ok(Self::ReadOnly),
+            _ => Err(Error::Jvm(JvmError::InvalidArgument(format!(
+                "Unknown execution mode {}",
+                value
+            )))),
+        }
+    }
+
+    /// Checking that the state can be changed by the operation.
+    pub fn check_change(self, operation: &str) -> Result<()> {
+        match self {
+            Self::Apply | Self::Validate => Ok(()),
+            Self::ReadOnly => Err(self.forbidden(operation)),
+        }
+    }
+}
+
+/// Converting a contract in the WAT text format to the binary one.
+/// Binary bytecode is returned as is.
+#[cfg(feature = "dev")]
+fn wat_to_wasm(bytecode: Vec<u8>) -> Result<Vec<u8>> {
+    if bytecode.starts_with(crate::exec::WASM_MAGIC) {
+        return Ok(bytecode);
+    }
+
+    wat::parse_bytes(&bytecode)
+        .map(|bytecode| bytecode.into_owned())
+        .map_err(|error| Error::Executable(ExecutableError::InvalidBytecode(error.to_string())))
+}
+
 /// The entry point for the virtual machine.
 /// Contains:
 /// * Call stack
@@ -37,12 +114,32 @@ impl Frame {
 pub struct Vm {
     frames: Vec<Frame>,
     first_frame: Frame,
-    memory: (u32, u32),
-    fuel_limit: u64,
+    limits: ExecutionLimits,
+    mode: ExecutionMode,
+    /// End of the time of the execution, set by the first call.
+    deadline: Option<Instant>,
     modules: Vec<Module>,
-    pub jvm: Option<JavaVM>,
-    pub jvm_callback: Option<GlobalRef>,
+    external: Vec<ExternalFunction>,
+    pub node: Box<dyn Node>,
     nonce: u64,
+    /// Identifiers generated by contracts during the execution.
+    unique_ids: u64,
+    /// Pause status of the contracts changing the state, requested once per execution.
+    paused: BTreeMap<Vec<u8>, bool>,
+    /// Features of the node gating host functions, requested once per execution.
+    features: BTreeMap<String, bool>,
+    host_function: Option<&'static str>,
+    fuel_consumed: u64,
+    /// Fuel refunds of the removed data entries, rolled back with the failed calls.
+    fuel_refund: u64,
+    /// Pages of linear memory allocated by the live instances.
+    memory_used: u32,
+    peak_memory: u32,
+    coverage: Option<Coverage>,
+    profile: Option<Profile>,
+    engine_config: EngineConfig,
+    module_cache: Option<Arc<ModuleCache>>,
+    cancellation: CancellationToken,
 }
 
 impl Vm {
@@ -51,16 +148,16 @@ impl Vm {
     pub fn new(
         contract_id: Vec<u8>,
         bytecode: Vec<u8>,
-        memory: (u32, u32),
-        fuel_limit: u64,
+        limits: ExecutionLimits,
         modules: Vec<Module>,
-        jvm: Option<JavaVM>,
-        jvm_callback: Option<GlobalRef>,
+        node: Box<dyn Node>,
+        mode: ExecutionMode,
     ) -> Result<Self> {
         let first_frame = Frame {
             contract_id,
             bytecode,
             nonce: 0,
+            delegator: None,
         };
 
         debug!(
@@ -71,17 +168,72 @@ impl Vm {
         Ok(Self {
             frames: Default::default(),
             first_frame,
-            memory,
-            fuel_limit,
+            limits,
+            mode,
+            deadline: None,
             modules,
-            jvm,
-            jvm_callback,
+            external: vec![],
+            node,
             nonce: 0,
+            unique_ids: 0,
+            paused: BTreeMap::new(),
+            features: BTreeMap::new(),
+            host_function: None,
+            fuel_consumed: 0,
+            fuel_refund: 0,
+            memory_used: 0,
+            peak_memory: 0,
+            coverage: None,
+            profile: None,
+            engine_config: EngineConfig::default(),
+            module_cache: None,
+            cancellation: CancellationToken::new(),
         })
     }
 
+    /// Inspecting the imports, exports, memory and data segments of a contract.
+    /// The WAT disassembly is added if `disassemble` is set.
+    pub fn inspect(bytecode: &[u8], disassemble: bool) -> Result<Inspection> {
+        #[cfg(feature = "dev")]
+        let bytecode = &wat_to_wasm(bytecode.to_vec())?;
+
+        bytecode::inspect(bytecode, disassemble)
+    }
+
+    /// Running the `_constructor` of arbitrary bytecode against the in-memory node.
+    /// Errors of malformed contracts and params are returned, the process must never panic.
+    #[cfg(any(fuzzing, test))]
+    pub fn run_untrusted(bytecode: &[u8], params: &[u8]) -> Result<Vec<Val>, ContextError> {
+        // Bounds the execution time of generated contracts
+        const FUEL_LIMIT: u64 = 100_000;
+
+        let mut vm = Self::new(
+            vec![],
+            bytecode.to_vec(),
+            ExecutionLimits {
+                fuel: FUEL_LIMIT,
+                ..Default::default()
+            },
+            crate::modules::all(),
+            Box::new(crate::mock::Mock::new()),
+            ExecutionMode::Apply,
+        )
+        .map_err(|error| ContextError {
+            error,
+            context: Context {
+                contract_id: vec![],
+                func_name: "_constructor".to_string(),
+                host_function: None,
+                frame: 0,
+            },
+        })?;
+
+        vm.run("_constructor", params)
+    }
+
     /// Calling another contract when a contract is executed.
     /// Contract is placed on top of the call stack.
+    /// A contract paused by the node can be called, but its changes of the state are rejected.
     pub fn call(
         &mut self,
         contract_id: Vec<u8>,
@@ -89,13 +241,54 @@ impl Vm {
         nonce: u64,
         func_name: &str,
         params: &[u8],
-    ) -> Result<Vec<Value>> {
+    ) -> Result<Vec<Val>, ContextError> {
+        let _span = tracing::trace_span!("call", contract_id = %contract_id.to_base58(), func_name)
+            .entered();
+
+        let frame = Frame {
+            contract_id,
+            bytecode,
+            nonce,
+            delegator: None,
+        };
+
+        self.call_frame(frame, func_name, params)
+    }
+
+    /// Executing the bytecode of another contract with the storage of the current one.
+    /// Delegation is kept by nested delegate calls, so a chain of libraries works
+    /// with the storage of the contract that started it.
+    pub fn delegate_call(
+        &mut self,
+        contract_id: Vec<u8>,
+        bytecode: Vec<u8>,
+        nonce: u64,
+        func_name: &str,
+        params: &[u8],
+    ) -> Result<Vec<Val>, ContextError> {
+        let _span = tracing::trace_span!(
+            "delegate_call",
+            contract_id = %contract_id.to_base58(),
+            func_name
+        )
+        .entered();
+
         let frame = Frame {
             contract_id,
             bytecode,
             nonce,
+            delegator: Some(self.top_frame().storage_id()),
         };
 
+        self.call_frame(frame, func_name, params)
+    }
+
+    fn call_frame(
+        &mut self,
+        frame: Frame,
+        func_name: &str,
+        params: &[u8],
+    ) -> Result<Vec<Val>, ContextError> {
         debug!(
             "The contract with id: {} triggers the contract with id: {} to call the function: {}",
             self.top_frame().contract_id().to_base58(),
@@ -103,30 +296,145 @@ impl Vm {
             func_name
         );
 
-        self.push_frame(frame)?;
+        let contract_id = frame.contract_id();
+        let result = Self::verify_function(&frame.bytecode, func_name, params)
+            .and_then(|()| self.push_frame(frame));
+
+        if let Err(error) = result {
+            let context = Context {
+                contract_id,
+                func_name: func_name.to_string(),
+                host_function: None,
+                frame: self.frames.len() + 1,
+            };
+            return Err(ContextError { error, context });
+        }
+
         self.run(func_name, params)
     }
 
+    /// Checking that the called contract exports the function receiving the params
+    /// and returning the error code and values, see `DataEntry::from_results`.
+    /// Mistakes of the caller are reported before the callee is instantiated.
+    fn verify_function(bytecode: &[u8], func_name: &str, params: &[u8]) -> Result<()> {
+        #[cfg(feature = "dev")]
+        let bytecode = &wat_to_wasm(bytecode.to_vec())?;
+
+        let mismatch =
+            |message: String| Error::Executable(ExecutableError::SignatureMismatch(message));
+
+        let func_type = bytecode::func_type(bytecode, func_name)?
+            .ok_or(Error::Executable(ExecutableError::FuncNotFound))?;
+
+        let integer = |ty: &ValType| matches!(ty, ValType::I32 | ValType::I64);
+        if !func_type.params().iter().all(integer) || !func_type.results().iter().all(integer) {
+            return Err(mismatch(String::from(
+                "Only i32 and i64 types are supported",
+            )));
+        }
+
+        if func_type.results().first() != Some(&ValType::I32) {
+            return Err(mismatch(String::from(
+                "Function must return an i32 error code first",
+            )));
+        }
+
+        let args = DataEntry::count_args(params)?;
+        if func_type.params().len() != args {
+            return Err(mismatch(format!(
+                "Expected {} arguments, received {}",
+                func_type.params().len(),
+                args
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Run contract. The contract is taken from the top of the call stack.
-    pub fn run(&mut self, func_name: &str, params: &[u8]) -> Result<Vec<Value>> {
-        let frame = self.top_frame();
+    /// The error contains the place of the execution where it occurred.
+    pub fn run(&mut self, func_name: &str, params: &[u8]) -> Result<Vec<Val>, ContextError> {
+        let _span = tracing::trace_span!(
+            "run",
+            contract_id = %self.top_frame().contract_id.to_base58(),
+            func_name
+        )
+        .entered();
+
+        if self.frames.is_empty() {
+            self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        }
+
+        // The host function of the calling contract is restored after the call
+        let host_function = self.host_function.take();
+
+        let result = self.execute(func_name, params).map_err(|error| {
+            let context = Context {
+                contract_id: self.top_frame().contract_id(),
+                func_name: func_name.to_string(),
+                host_function: self.host_function,
+                frame: self.frames.len(),
+            };
+            ContextError { error, context }
+        });
+
+        self.frames.pop();
+        self.host_function = host_function;
+
+        result
+    }
+
+    /// Executing the contract function from the top of the call stack.
+    fn execute(&mut self, func_name: &str, params: &[u8]) -> Result<Vec<Val>> {
+        self.check_interrupted()?;
+
+        if self.module_cache.is_none() && self.engine_config.cache_modules {
+            self.module_cache = Some(Arc::new(ModuleCache::new(&self.engine_config)));
+        }
+
+        // Fields are accessed directly to borrow the coverage mutably
+        let frame = self.frames.last().unwrap_or(&self.first_frame);
 
         let func_name = LoadableFunction::from_str(func_name)?;
 
-        let mut exec = Executable::new(self.memory.0, self.memory.1, self.fuel_limit);
-        exec.load_bytecode(&frame.bytecode)?;
+        ExecutionLimits::check(
+            "Bytecode size",
+            frame.bytecode.len(),
+            self.limits.bytecode_size,
+        )?;
+
+        let (initial, maximum) = self.limits.memory;
+        let mut exec = Executable::new(initial, maximum, self.limits.fuel);
+        {
+            let _span =
+                tracing::trace_span!("load_module", contract_id = %frame.contract_id.to_base58())
+                    .entered();
+            #[cfg(feature = "dev")]
+            let bytecode = &wat_to_wasm(frame.bytecode.clone())?;
+            #[cfg(not(feature = "dev"))]
+            let bytecode = &frame.bytecode;
+
+            match self.coverage.as_mut() {
+                Some(coverage) => {
+                    // The contract must be valid without the instrumentation
+                    Executable::validate_bytecode(bytecode)?;
+                    let bytecode = coverage.instrument(&frame.contract_id, bytecode)?;
+                    exec.load_instrumented_bytecode(&bytecode, &self.engine_config)?;
+                }
+                None => match &self.module_cache {
+                    Some(cache) => exec.load_module(cache.get(bytecode, self.frames.len())?),
+                    None => exec.load_bytecode(bytecode, &self.engine_config)?,
+                },
+            }
+        }
 
         debug!(
             "Calling the function: {} contract: {}",
-            func_name.to_string(),
+            func_name,
             self.top_frame().contract_id().to_base58()
         );
 
-        let result = exec.execute(&func_name, params, self.modules.clone(), self);
-
-        self.frames.pop();
-
-        result
+        exec.execute(&func_name, params, self.modules.clone(), self)
     }
 
     /// Getting the frame at the top of the call stack.
@@ -134,11 +442,197 @@ impl Vm {
         self.frames.last().unwrap_or(&self.first_frame)
     }
 
+    /// Fuel consumed by all contracts executed by the virtual machine.
+    pub fn fuel_consumed(&self) -> u64 {
+        self.fuel_consumed
+    }
+
+    pub fn add_fuel_consumed(&mut self, fuel: u64) {
+        self.fuel_consumed += fuel;
+    }
+
+    /// Fuel refunded for the data entries removed by the contracts, bounded by
+    /// `1 / REFUND_QUOTIENT` of the consumed fuel. The node credits it only if the call succeeds.
+    pub fn fuel_refunded(&self) -> u64 {
+        self.fuel_refund.min(self.fuel_consumed / REFUND_QUOTIENT)
+    }
+
+    /// Refunds accumulated so far, saved by the snapshots of the runtime.
+    pub(crate) fn fuel_refund(&self) -> u64 {
+        self.fuel_refund
+    }
+
+    pub(crate) fn set_fuel_refund(&mut self, fuel: u64) {
+        self.fuel_refund = fuel;
+    }
+
+    /// The largest number of pages of linear memory allocated at once by the contracts
+    /// of the call stack.
+    pub fn peak_memory(&self) -> u32 {
+        self.peak_memory
+    }
+
+    pub fn limits(&self) -> &ExecutionLimits {
+        &self.limits
+    }
+
+    /// Rejecting the changes of the state of a paused contract.
+    pub(crate) fn check_paused(&mut self, contract_id: &[u8]) -> Result<()> {
+        let paused = match self.paused.get(contract_id) {
+            Some(paused) => *paused,
+            None => {
+                let paused = self.node.is_paused(contract_id)?;
+                self.paused.insert(contract_id.to_vec(), paused);
+                paused
+            }
+        };
+
+        match paused {
+            true => Err(Error::Runtime(RuntimeError::ContractPaused)),
+            false => Ok(()),
+        }
+    }
+
+    pub fn mode(&self) -> ExecutionMode {
+        self.mode
+    }
+
+    /// Reserving pages of linear memory for an instance.
+    /// Fails if the memory of the live instances exceeds the limit.
+    pub(crate) fn allocate_memory(&mut self, pages: u32) -> bool {
+        match self.memory_used.checked_add(pages) {
+            Some(used) if used <= self.limits.memory_limit => {
+                self.memory_used = used;
+                self.peak_memory = self.peak_memory.max(used);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn release_memory(&mut self, pages: u32) {
+        self.memory_used = self.memory_used.saturating_sub(pages);
+    }
+
+    /// Configuring the engine compiling the contracts.
+    /// Modules compiled so far with another configuration are dropped.
+    pub fn set_engine_config(&mut self, config: EngineConfig) {
+        if self.engine_config != config {
+            self.engine_config = config;
+            self.module_cache = None;
+        }
+    }
+
+    pub fn engine_config(&self) -> EngineConfig {
+        self.engine_config
+    }
+
+    /// Reusing the modules compiled by the session.
+    pub(crate) fn set_module_cache(&mut self, cache: Option<Arc<ModuleCache>>) {
+        self.module_cache = cache;
+    }
+
+    /// Linking host functions defined outside of the crate, see `wevm::external`.
+    pub fn set_external_functions(&mut self, functions: Vec<ExternalFunction>) {
+        self.external = functions;
+    }
+
+    pub fn external_functions(&self) -> &[ExternalFunction] {
+        &self.external
+    }
+
+    /// Enabling the coverage mode. Hit counts are added to the given coverage.
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Adding hit counts of the contract on top of the call stack.
+    pub(crate) fn record_coverage(&mut self, counters: Vec<(String, u64)>) {
+        let contract_id = self.top_frame().contract_id();
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record(&contract_id, counters);
+        }
+    }
+
+    /// Enabling the profiling mode. Consumed fuel is added to the given profile
+    /// by the called functions of the contracts, see `Profile`.
+    pub fn set_profile(&mut self, profile: Option<Profile>) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    /// Starting the function of the contract on top of the call stack.
+    pub(crate) fn enter_profile(&mut self, func_name: &str) {
+        let contract_id = self.top_frame().contract_id();
+        if let Some(profile) = self.profile.as_mut() {
+            profile.enter(&contract_id, func_name);
+        }
+    }
+
+    /// Adding the call of a host function with the fuel charged for it.
+    pub(crate) fn profile_host_function(&mut self, name: &'static str, fuel: u64) {
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record_host(name, fuel);
+        }
+    }
+
+    /// Adding the fuel consumed by the function of the contract on top of the call stack.
+    pub(crate) fn exit_profile(&mut self, fuel_consumed: u64) {
+        if let Some(profile) = self.profile.as_mut() {
+            profile.exit(fuel_consumed);
+        }
+    }
+
     pub fn get_nonce(&mut self) -> u64 {
         self.nonce += 1;
         self.nonce
     }
 
+    /// Counter of the identifiers generated by contracts, shared by the call stack
+    /// so a contract called again by another contract doesn't repeat them.
+    pub fn next_unique_id(&mut self) -> u64 {
+        self.unique_ids += 1;
+        self.unique_ids
+    }
+
+    /// Number of contracts on the call stack including the current one.
+    /// A contract called directly by the transaction is executed at depth 1.
+    pub fn call_depth(&self) -> usize {
+        self.frames.len() + 1
+    }
+
+    /// Getting the caller `index` frames below the current one, `0` is the direct caller.
+    /// The caller of the first contract is the transaction, it is returned as empty bytes.
+    pub fn frame_caller(&self, index: usize) -> Option<Vec<u8>> {
+        let depth = self.call_depth();
+        match index.cmp(&(depth - 1)) {
+            Ordering::Less => {
+                let position = depth - 2 - index;
+                match position {
+                    0 => Some(self.first_frame.contract_id()),
+                    _ => self.frames.get(position - 1).map(Frame::contract_id),
+                }
+            }
+            Ordering::Equal => Some(vec![]),
+            Ordering::Greater => None,
+        }
+    }
+
     /// Get the caller of the current frame.
     pub fn get_caller_current_frame(&self) -> Vec<u8> {
         if self.frames.is_empty() {
@@ -153,8 +647,44 @@ impl Vm {
         }
     }
 
+    /// Setting the token cancelling the execution from another thread.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = token;
+    }
+
+    /// Failing with `RuntimeError::Cancelled` if the execution is cancelled
+    /// and with `RuntimeError::Timeout` if its time is out.
+    pub fn check_interrupted(&self) -> Result<()> {
+        self.cancellation.check()?;
+
+        match self.deadline {
+            Some(deadline) if Instant::now() > deadline => {
+                Err(Error::Runtime(RuntimeError::Timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Setting the last host function called by the contract.
+    pub fn set_host_function(&mut self, host_function: &'static str) {
+        self.host_function = Some(host_function);
+    }
+
+    /// Checking that the feature of the node is activated.
+    /// The node is asked once per execution, its errors fail the execution.
+    pub fn feature_activated(&mut self, feature: &str) -> Result<bool> {
+        if let Some(activated) = self.features.get(feature) {
+            return Ok(*activated);
+        }
+
+        let activated = self.node.is_feature_activated(feature.as_bytes())?;
+        self.features.insert(feature.to_string(), activated);
+
+        Ok(activated)
+    }
+
     fn push_frame(&mut self, frame: Frame) -> Result<()> {
-        if self.frames.len() == MAX_FRAMES {
+        if self.frames.len() >= self.limits.call_depth {
             return Err(Error::Executable(ExecutableError::StackOverflow));
         }
 
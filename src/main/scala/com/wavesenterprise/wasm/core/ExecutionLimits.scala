@@ -0,0 +1,29 @@
+package com.wavesenterprise.wasm.core
+
+/**
+  * Limits of the contracts executed in a session, the fuel is passed with each call
+  * @param memoryInitial initial pages of linear memory of an instance
+  * @param memoryMaximum maximum pages of linear memory of an instance
+  * @param memoryLimit pages of linear memory available to all contracts of the call stack
+  * @param callDepth contracts called by other contracts on the call stack
+  * @param bytecodeSize size of the bytecode of a called contract in bytes
+  * @param keyLength length of the key of a written data entry
+  * @param valueSize size of the value of a written binary or string data entry
+  * @param payments assets paid by one call of another contract
+  * @param libraries libraries linked to a contract
+  * @param blockLookback past blocks available to `get_block_at`, counted back from the current height
+  * @param timeoutMillis wall-clock time of a call, unlimited if 0
+  */
+case class ExecutionLimits(
+    memoryInitial: Int,
+    memoryMaximum: Int,
+    memoryLimit: Int,
+    callDepth: Int,
+    bytecodeSize: Int,
+    keyLength: Int,
+    valueSize: Int,
+    payments: Int,
+    libraries: Int,
+    blockLookback: Int,
+    timeoutMillis: Long
+)
@@ -0,0 +1,8 @@
+package com.wavesenterprise.wasm.core
+
+/**
+  * @param code error code of the function, 0 on success
+  * @param results values returned by the function serialized like the params, empty on failure
+  * @param fuelConsumed fuel consumed by the contract and its nested calls, also on failure, 0 for views
+  */
+case class ExecutionResult(code: Int, results: Array[Byte], fuelConsumed: Long)
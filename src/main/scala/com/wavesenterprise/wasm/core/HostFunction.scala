@@ -0,0 +1,10 @@
+package com.wavesenterprise.wasm.core
+
+trait HostFunction {
+
+  /**
+    * @param args arguments of the function, `i32` values are widened to `Long`
+    * @return results of the function in the order of the signature
+    */
+  def call(args: Array[Long]): Array[Long]
+}
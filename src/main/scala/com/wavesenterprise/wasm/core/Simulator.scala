@@ -2,6 +2,7 @@ package com.wavesenterprise.wasm.core
 
 import com.wavesenterprise.account.Address
 import com.wavesenterprise.crypto.internals.WavesAlgorithms
+import com.wavesenterprise.state.DataEntry
 
 import java.nio.ByteBuffer
 
@@ -28,6 +29,16 @@ class Simulator(bytecode: Array[Byte]) {
 
   def height: Long = this.service.height
 
+  def vrf: Array[Byte] = this.service.vrf
+
+  def txId: Array[Byte] = this.service.txId
+
+  def setTxFee(fee: Long, assetId: Array[Byte], sponsored: Boolean) =
+    this.service.setTxFee(fee, assetId, sponsored)
+
+  def setAccountData(address: Array[Byte], dataEntry: DataEntry[_]) =
+    this.service.setAccountData(address, dataEntry)
+
   private val _accounts: Array[Array[Byte]] = generateAccounts(Array.empty[Array[Byte]])
 
   def accounts(i: Int): Array[Byte] =
@@ -53,6 +64,12 @@ class Simulator(bytecode: Array[Byte]) {
     contractId
   }
 
+  def setPaused(contractId: Array[Byte], value: Boolean) =
+    this.service.setPaused(ByteBuffer.wrap(contractId), value)
+
+  def setFeatureActivated(feature: String, value: Boolean) =
+    this.service.setFeatureActivated(feature, value)
+
   def callMethod(funcName: String, params: Array[Byte]): Int = {
     this.service.setTxSender(this._accounts(0))
     this.executor.runContract(this.contractId, this.bytecode, funcName, params, this.fuelLimit, this.service)
@@ -76,6 +93,9 @@ class Simulator(bytecode: Array[Byte]) {
   def getStorage(key: Array[Byte]): Array[Byte] =
     this.service.getStorage(this.contractId, key)
 
+  def addRole(address: Array[Byte], roleId: Int) =
+    this.service.addRole(address, roleId)
+
   def addPayment(assetId: Array[Byte], amount: Long) = {
     this.service.addPayment(this.contractId, assetId, amount)
     this.service.transfer(ByteBuffer.wrap(this._accounts(0)), ByteBuffer.wrap(assetId), ByteBuffer.wrap(this.contractId), amount)
@@ -3,14 +3,14 @@ package com.wavesenterprise.wasm.core
 import com.google.common.primitives.Longs
 
 import com.wavesenterprise.crypto.internals.WavesAlgorithms
-import com.wavesenterprise.state.DataEntry
+import com.wavesenterprise.state.{BinaryDataEntry, DataEntry}
 import com.wavesenterprise.transaction.docker.ContractTransactionEntryOps.{parse, toBytes}
 
 import java.nio.ByteBuffer
 import java.nio.charset.StandardCharsets.UTF_8
 import java.time.Instant
 
-import scala.collection.mutable.{Map, Seq}
+import scala.collection.mutable.{ArrayBuffer, Map, Seq}
 
 class SimulatorWASMService extends WASMService {
   private var _chainId: Byte             = 'V'.toByte
@@ -18,12 +18,34 @@ class SimulatorWASMService extends WASMService {
   private var _timestamp: Long           = Instant.now().toEpochMilli()
   private val _height: Long              = 1L
   private var _txSender: Array[Byte]     = Array.empty[Byte]
+  private var _txFee: Long               = 0L
+  private var _txFeeAssetId: Array[Byte] = Array.empty[Byte]
+  private var _txFeeSponsored: Boolean   = false
+  private val _txId: Array[Byte]         = WavesAlgorithms.secureHash(longToBytes(this._timestamp))
+  private val _vrf: Array[Byte]          = WavesAlgorithms.fastHash(longToBytes(this._height))
 
   private val _bytecodes: Map[ByteBuffer, Array[Byte]]             = Map.empty[ByteBuffer, Array[Byte]]
   private val _balances: Map[ByteBuffer, Map[ByteBuffer, Long]]    = Map.empty[ByteBuffer, Map[ByteBuffer, Long]]
-  private val _leases: Map[ByteBuffer, (ByteBuffer, Long)]         = Map.empty[ByteBuffer, (ByteBuffer, Long)]
+  private val _leases: Map[ByteBuffer, Lease]                      = Map.empty[ByteBuffer, Lease]
   private val _storage: Map[ByteBuffer, Map[String, DataEntry[_]]] = Map.empty[ByteBuffer, Map[String, DataEntry[_]]]
+  private val _accountData: Map[ByteBuffer, Map[String, DataEntry[_]]] =
+    Map.empty[ByteBuffer, Map[String, DataEntry[_]]]
   private val _payments: Map[ByteBuffer, Seq[(ByteBuffer, Long)]]  = Map.empty[ByteBuffer, Seq[(ByteBuffer, Long)]]
+  private val _roles: Map[ByteBuffer, Set[Int]]                    = Map.empty[ByteBuffer, Set[Int]]
+  private val _paused: Map[ByteBuffer, Boolean]                    = Map.empty[ByteBuffer, Boolean]
+  private val _features: Map[String, Boolean]                      = Map.empty[String, Boolean]
+  private val _blocks: Map[Long, (Long, Array[Byte])]              = Map.empty[Long, (Long, Array[Byte])]
+
+  private case class Lease(sender: Array[Byte], recipient: Array[Byte], amount: Long, active: Boolean)
+
+  private case class Snapshot(
+      balances: Map[ByteBuffer, Map[ByteBuffer, Long]],
+      leases: Map[ByteBuffer, Lease],
+      storage: Map[ByteBuffer, Map[String, DataEntry[_]]],
+      payments: Map[ByteBuffer, Seq[(ByteBuffer, Long)]]
+  )
+
+  private val _snapshots: ArrayBuffer[Snapshot] = ArrayBuffer.empty[Snapshot]
 
   private[core] def setChainId(value: Byte) = this._chainId = value
 
@@ -34,11 +56,42 @@ class SimulatorWASMService extends WASMService {
 
   private[core] def height: Long = this._height
 
+  private[core] def vrf: Array[Byte] = this._vrf
+
+  private[core] def txId: Array[Byte] = this._txId
+
+  private[core] def setBlock(height: Long, timestamp: Long, generator: Array[Byte]) =
+    this._blocks(height) = (timestamp, generator)
+
   private[core] def setTxSender(value: Array[Byte]) = this._txSender = value
 
+  private[core] def setAccountData(address: Array[Byte], dataEntry: DataEntry[_]) = {
+    val wAddress = ByteBuffer.wrap(address)
+    val kv       = this._accountData.getOrElse(wAddress, Map.empty[String, DataEntry[_]])
+    kv(dataEntry.key) = dataEntry
+    this._accountData(wAddress) = kv
+  }
+
+  private[core] def setTxFee(fee: Long, assetId: Array[Byte], sponsored: Boolean) = {
+    this._txFee = fee
+    this._txFeeAssetId = assetId
+    this._txFeeSponsored = sponsored
+  }
+
   private[core] def setBytecode(contractId: ByteBuffer, bytecode: Array[Byte]) =
     this._bytecodes(contractId) = bytecode
 
+  private[core] def setPaused(contractId: ByteBuffer, value: Boolean) =
+    this._paused(contractId) = value
+
+  private[core] def setFeatureActivated(feature: String, value: Boolean) =
+    this._features(feature) = value
+
+  private[core] def addRole(address: Array[Byte], roleId: Int) = {
+    val wAddress = ByteBuffer.wrap(address)
+    this._roles(wAddress) = this._roles.getOrElse(wAddress, Set.empty[Int]) + roleId
+  }
+
   private def addPayment(paymentId: ByteBuffer, payment: (ByteBuffer, Long)) =
     this._payments(paymentId) = this._payments.getOrElse(paymentId, Seq.empty[(ByteBuffer, Long)]) :+ payment
 
@@ -137,6 +190,12 @@ class SimulatorWASMService extends WASMService {
     }
   }
 
+  override def isPaused(contractId: Array[Byte]): Boolean =
+    this._paused.getOrElse(ByteBuffer.wrap(contractId), false)
+
+  override def isFeatureActivated(feature: Array[Byte]): Boolean =
+    this._features.getOrElse(new String(feature, UTF_8), true)
+
   override def getBalance(assetId: Array[Byte], assetHolder: Array[Byte]): Long = {
     val (_, _, holder) = parseAssetHolder(assetHolder)
     this.getBalance(ByteBuffer.wrap(assetId), ByteBuffer.wrap(holder))
@@ -185,9 +244,17 @@ class SimulatorWASMService extends WASMService {
     new String(field, UTF_8) match {
       case "timestamp" => longToBytes(this._timestamp)
       case "height"    => longToBytes(this._height)
+      case "vrf"       => this._vrf
+      case "signature" => WavesAlgorithms.secureHash(longToBytes(this._height))
       case _           => throw new Exception
     }
 
+  override def getBlockAt(height: Long): Array[Byte] =
+    this._blocks.get(height) match {
+      case Some((timestamp, generator)) => longToBytes(timestamp) ++ generator
+      case None                         => throw new Exception
+    }
+
   override def fastHash(bytes: Array[Byte]): Array[Byte] = WavesAlgorithms.fastHash(bytes)
 
   override def secureHash(bytes: Array[Byte]): Array[Byte] = WavesAlgorithms.secureHash(bytes)
@@ -205,17 +272,28 @@ class SimulatorWASMService extends WASMService {
 
     val leaseId = WavesAlgorithms.secureHash(contractId ++ recipient)
 
-    this._leases(ByteBuffer.wrap(leaseId)) = (ByteBuffer.wrap(holder), amount)
+    this._leases(ByteBuffer.wrap(leaseId)) = Lease(contractId, holder, amount, active = true)
 
     leaseId
   }
 
   override def cancelLease(contractId: Array[Byte], leaseId: Array[Byte]) =
-    this._leases.remove(ByteBuffer.wrap(leaseId)) match {
+    this._leases.get(ByteBuffer.wrap(leaseId)) match {
+      case Some(lease) if lease.active => this._leases(ByteBuffer.wrap(leaseId)) = lease.copy(active = false)
+      case _                           => throw new Exception
+    }
+
+  override def getLeaseInfo(leaseId: Array[Byte]): Array[Byte] =
+    this._leases.get(ByteBuffer.wrap(leaseId)) match {
+      case Some(lease) =>
+        Array(if (lease.active) 1.toByte else 0.toByte) ++ longToBytes(lease.amount) ++
+          Array(lease.sender.length.toByte) ++ lease.sender ++ lease.recipient
       case None => throw new Exception
-      case _    => ()
     }
 
+  override def checkPermission(address: Array[Byte], roleId: Int): Boolean =
+    this._roles.getOrElse(ByteBuffer.wrap(address), Set.empty[Int]).contains(roleId)
+
   override def containsKey(contractId: Array[Byte], key: Array[Byte]): Boolean = {
     val k = if (key.isEmpty) throw new Exception else new String(key)
     this.getKeyValueStorage(ByteBuffer.wrap(contractId)).contains(k)
@@ -230,6 +308,17 @@ class SimulatorWASMService extends WASMService {
     }
   }
 
+  override def getStorageBinaryInto(contractId: Array[Byte], key: Array[Byte], buffer: ByteBuffer): Int = {
+    val k = if (key.isEmpty) throw new Exception else new String(key)
+
+    this.getKeyValueStorage(ByteBuffer.wrap(contractId)).get(k) match {
+      case Some(BinaryDataEntry(_, value)) =>
+        if (value.arr.length <= buffer.capacity()) buffer.put(value.arr)
+        value.arr.length
+      case _ => -1
+    }
+  }
+
   override def setStorage(contractId: Array[Byte], value: Array[Byte]) = {
     val wContractId             = ByteBuffer.wrap(contractId)
     val dataEntry: DataEntry[_] = parse(value, 0)._1
@@ -239,6 +328,21 @@ class SimulatorWASMService extends WASMService {
     this._storage(wContractId) = kv
   }
 
+  override def deleteStorage(contractId: Array[Byte], key: Array[Byte]): Boolean = {
+    val k = if (key.isEmpty) throw new Exception else new String(key)
+
+    this._storage.get(ByteBuffer.wrap(contractId)).flatMap(_.remove(k)).isDefined
+  }
+
+  override def getAccountData(address: Array[Byte], key: Array[Byte]): Array[Byte] = {
+    val k = if (key.isEmpty) throw new Exception else new String(key)
+
+    this._accountData.get(ByteBuffer.wrap(address)).flatMap(_.get(k)) match {
+      case Some(value) => toBytes(value)
+      case None        => Array.empty[Byte]
+    }
+  }
+
   override def getTxPayments(paymentId: Array[Byte]): Long =
     this._payments.getOrElse(ByteBuffer.wrap(paymentId), Seq.empty[(ByteBuffer, Long)]).size
 
@@ -256,7 +360,38 @@ class SimulatorWASMService extends WASMService {
 
   override def tx(field: Array[Byte]): Array[Byte] =
     new String(field, UTF_8) match {
-      case "sender" => this._txSender
-      case _        => throw new Exception
+      case "sender"       => this._txSender
+      case "id"           => this._txId
+      case "fee"          => longToBytes(this._txFee)
+      case "feeAssetId"   => this._txFeeAssetId
+      case "feeSponsored" => Array(if (this._txFeeSponsored) 1.toByte else 0.toByte)
+      case _              => throw new Exception
     }
+
+  override def snapshot(): Int = {
+    this._snapshots += Snapshot(
+      this._balances.map { case (assetId, balances) => assetId -> balances.clone() },
+      this._leases.clone(),
+      this._storage.map { case (contractId, kv) => contractId -> kv.clone() },
+      this._payments.clone()
+    )
+    this._snapshots.length - 1
+  }
+
+  override def restore(snapshot: Int): Unit = {
+    val saved = this._snapshots(snapshot)
+    this.discard(snapshot)
+
+    this._balances.clear()
+    this._balances ++= saved.balances
+    this._leases.clear()
+    this._leases ++= saved.leases
+    this._storage.clear()
+    this._storage ++= saved.storage
+    this._payments.clear()
+    this._payments ++= saved.payments
+  }
+
+  override def discard(snapshot: Int): Unit =
+    this._snapshots.remove(snapshot, this._snapshots.length - snapshot)
 }
@@ -1,6 +1,7 @@
 package com.wavesenterprise.wasm.core
 
 import com.github.sbt.jni.syntax.NativeLoader
+import org.slf4j.Logger
 
 class WASMExecutor extends NativeLoader("wevm") {
   @native def runContract(
@@ -12,5 +13,139 @@ class WASMExecutor extends NativeLoader("wevm") {
       callback: WASMService
   ): Int
 
+  /**
+    * Executes the contract in the mode restricting the state changes.
+    * Host functions not allowed in the mode fail with the `ForbiddenInMode` error, unknown modes with `InvalidArgument`
+    * @param mode 0 applies the transaction, 1 validates it with the result of applying it, 2 doesn't change the state
+    */
+  @native def runContractInMode(
+      contractId: Array[Byte],
+      bytecode: Array[Byte],
+      funcName: String,
+      params: Array[Byte],
+      fuelLimit: Long,
+      callback: WASMService,
+      mode: Int
+  ): Int
+
+  /**
+    * Executes the contract and returns the values of the function.
+    * The first result of the function is its `i32` error code, the following results are returned as integers
+    * @return error code, results serialized like the params and consumed fuel or null on failure
+    */
+  @native def runContractWithResults(
+      contractId: Array[Byte],
+      bytecode: Array[Byte],
+      funcName: String,
+      params: Array[Byte],
+      fuelLimit: Long,
+      callback: WASMService
+  ): ExecutionResult
+
+  /**
+    * Executes the function of the deployed contract without changing the state.
+    * State-changing host functions fail and the fuel isn't counted against the block
+    * @return error code and results like `runContractWithResults` or null on failure
+    */
+  @native def runView(
+      contractId: Array[Byte],
+      funcName: String,
+      params: Array[Byte],
+      callback: WASMService
+  ): ExecutionResult
+
+  /**
+    * Opens the session executing the contracts of one block.
+    * Compiled modules and storage reads are reused by the calls of the session
+    * @return handle of the session or 0 on failure
+    */
+  @native def openSession(callback: WASMService): Long
+
+  /**
+    * Opens the session with the limits of the block
+    * @return handle of the session or 0 on failure
+    */
+  @native def openSessionWithLimits(callback: WASMService, limits: ExecutionLimits): Long
+
+  /**
+    * Executes the contract in the session, can be called from any thread.
+    * Calls to the same session are executed one by one
+    * @param session handle returned by `openSession`
+    */
+  @native def runInSession(
+      session: Long,
+      contractId: Array[Byte],
+      bytecode: Array[Byte],
+      funcName: String,
+      params: Array[Byte],
+      fuelLimit: Long
+  ): Int
+
+  /**
+    * Cancels the running and following calls of the session, e.g. on the abort of the block.
+    * Can be called from any thread, cancelled calls fail with the `Cancelled` error.
+    * The session must still be closed by `closeSession`
+    */
+  @native def cancelSession(session: Long): Int
+
+  /**
+    * Closes the session at the end of the block, waits for the running call.
+    * Calls with the closed handle fail with the `SessionNotFound` error
+    */
+  @native def closeSession(session: Long): Int
+
   @native def validateBytecode(bytecode: Array[Byte]): Int
+
+  /**
+    * Strips name and custom sections except `abi` and rewrites the module in the canonical form
+    * @return normalized bytecode or null if the bytecode is invalid
+    */
+  @native def normalizeBytecode(bytecode: Array[Byte]): Array[Byte]
+
+  /**
+    * @param disassemble add the WAT text of the contract
+    * @return JSON with imports, exports, memory limits and data segment sizes or null if the bytecode is malformed
+    */
+  @native def inspectBytecode(bytecode: Array[Byte], disassemble: Boolean): String
+
+  @native def manifest(): String
+
+  /**
+    * Native logs are dropped until the logger is set, it can be set only once
+    * @param logger Logger receiving the native logs and the trace spans of the execution
+    * @param level 0 - off, 1 - error, 2 - warn, 3 - info, 4 - debug, 5 - trace
+    */
+  @native def setLogger(logger: Logger, level: Int): Int
+
+  /**
+    * Native threads making callbacks are attached permanently by default, so a worker thread
+    * is attached once and detached when it exits
+    * @param permanent false to attach the thread for each callback and detach it after
+    */
+  @native def setThreadAttachment(permanent: Boolean): Unit
+
+  /**
+    * Sets the fuel refunded for each existing record removed by `delete_storage` in all following executions.
+    * The refund is deducted from `ExecutionResult.fuelConsumed` of successful calls, up to a fifth of the consumed fuel
+    * @param fuel refund of a removal, 0 by default. Must be the same on all nodes
+    */
+  @native def setDeletionRefund(fuel: Long): Unit
+
+  /**
+    * Registers the host function for all following executions, a function with the same name is replaced
+    * @param signature parameter and result types in the form `i32 i64 -> i64`
+    * @param fuelCost fuel consumed by each call
+    */
+  @native def registerHostFunction(
+      module: String,
+      name: String,
+      signature: String,
+      fuelCost: Long,
+      callback: HostFunction
+  ): Int
+
+  /**
+    * @return 1 if the function was registered, 0 otherwise
+    */
+  @native def unregisterHostFunction(module: String, name: String): Int
 }
@@ -1,5 +1,7 @@
 package com.wavesenterprise.wasm.core
 
+import java.nio.ByteBuffer
+
 trait WASMService {
 
   /**
@@ -25,6 +27,18 @@ trait WASMService {
     */
   def addPayments(contractId: Array[Byte], paymentId: Array[Byte], payments: Array[Byte]): Unit
 
+  /**
+    * @param contractId ID of a contract. Base58 bytes
+    * @return True if the contract is paused and must not change the state
+    */
+  def isPaused(contractId: Array[Byte]): Boolean
+
+  /**
+    * @param feature Name of the feature. UTF-8 bytes
+    * @return True if host functions of the feature can be called by contracts
+    */
+  def isFeatureActivated(feature: Array[Byte]): Boolean
+
   /**
     * @param assetId ID of a token (optional field, array can be empty). Base58 bytes
     * @param assetHolder AssetHolder of the token holder (possible contractId called this function)
@@ -67,11 +81,17 @@ trait WASMService {
   def reissue(contractId: Array[Byte], assetId: Array[Byte], amount: Long, isReissuable: Boolean): Unit
 
   /**
-    * @param field UTF-8 string with block field name
-    * @return Requested field data
+    * @param field UTF-8 string with block field name: `timestamp`, `height`, `vrf` or `signature`
+    * @return Requested field data, `vrf` is empty for blocks without it
     */
   def block(field: Array[Byte]): Array[Byte]
 
+  /**
+    * @param height Height of a past block within the look-back window
+    * @return Timestamp of the block as 8 bytes followed by the address of its generator
+    */
+  def getBlockAt(height: Long): Array[Byte]
+
   /**
     * @param bytes Raw data
     * @return Cryptographic hash
@@ -106,6 +126,19 @@ trait WASMService {
     */
   def cancelLease(contractId: Array[Byte], leaseId: Array[Byte]): Unit
 
+  /**
+    * @param leaseId ID of a leasing transaction. Base58 bytes
+    * @return Active flag as 1 byte, amount as 8 bytes, length of the sender as 1 byte, sender and recipient
+    */
+  def getLeaseInfo(leaseId: Array[Byte]): Array[Byte]
+
+  /**
+    * @param address Address of the account whose role is checked. Base58 bytes
+    * @param roleId Identifier of a node role
+    * @return True if the account has been granted the role
+    */
+  def checkPermission(address: Array[Byte], roleId: Int): Boolean
+
   /**
     * @param contractId ID of a contract (possible contractId called this function). Base58 bytes
     * @param key Record key. UTF-8 bytes
@@ -120,12 +153,40 @@ trait WASMService {
     */
   def getStorage(contractId: Array[Byte], key: Array[Byte]): Array[Byte]
 
+  /**
+    * Writes a binary record value straight into the memory of the contract
+    *
+    * @param contractId ID of a contract (possible contractId called this function). Base58 bytes
+    * @param key Record key. UTF-8 bytes
+    * @param buffer Direct buffer over the memory of the contract, valid only during the call
+    * @return Length of the value, nothing is written if it exceeds the buffer capacity,
+    * so the length must be compared with the capacity before the buffer is read.
+    * A negative length if the record isn't binary
+    */
+  def getStorageBinaryInto(contractId: Array[Byte], key: Array[Byte], buffer: ByteBuffer): Int
+
   /**
     * @param contractId ID of a contract called this function. Base58 bytes
     * @param value Serialized DataEntry record value
     */
   def setStorage(contractId: Array[Byte], value: Array[Byte]): Unit
 
+  /**
+    * Removes the record, the removal is rolled back by `restore` like the writes
+    *
+    * @param contractId ID of a contract called this function. Base58 bytes
+    * @param key Record key. UTF-8 bytes
+    * @return Boolean value whether the key existed
+    */
+  def deleteStorage(contractId: Array[Byte], key: Array[Byte]): Boolean
+
+  /**
+    * @param address Address of an account. Base58 bytes
+    * @param key Record key. UTF-8 bytes
+    * @return Record value published by a data transaction of the account, empty if the key is absent
+    */
+  def getAccountData(address: Array[Byte], key: Array[Byte]): Array[Byte]
+
   /**
     * @param paymentId Unique payment identifier. Represents the concatenation of contractId bytes and unique 8 bytes
     * @return Number of attached payments
@@ -147,8 +208,27 @@ trait WASMService {
   def getTxPaymentAmount(paymentId: Array[Byte], number: Long): Long
 
   /**
-    * @param field UTF-8 string with transaction field name
+    * @param field UTF-8 string with transaction field name: `sender`, `id`, `fee` (8 bytes),
+    * `feeAssetId` (empty for the system token) or `feeSponsored` (1 byte)
     * @return Requested field data
     */
   def tx(field: Array[Byte]): Array[Byte]
+
+  /**
+    * Saves the state changed by contracts: balances, leases, storage and payments
+    * @return Identifier of the snapshot
+    */
+  def snapshot(): Int
+
+  /**
+    * Restores the state saved by the snapshot, the snapshot and later ones are discarded
+    * @param snapshot Identifier of the snapshot
+    */
+  def restore(snapshot: Int): Unit
+
+  /**
+    * Keeps the current state, the snapshot and later ones are discarded
+    * @param snapshot Identifier of the snapshot
+    */
+  def discard(snapshot: Int): Unit
 }
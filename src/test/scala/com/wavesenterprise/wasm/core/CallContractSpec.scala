@@ -84,4 +84,42 @@ class CallContractSpec extends AnyFreeSpec with Matchers {
     parseDataEntry(simulator.getStorage(storageContractId, "binary".getBytes(UTF_8))) shouldBe binary
     parseDataEntry(simulator.getStorage(storageContractId, "string".getBytes(UTF_8))) shouldBe string
   }
+
+  "call_contract paused" in {
+    val simulator = new Simulator(bytecode)
+
+    // Load and pause storage contract
+    val storageBytecode   = getClass.getResourceAsStream("/storage.wasm").readAllBytes()
+    val storageContractId = simulator.loadAdditionalBytecode(storageBytecode)
+    simulator.setPaused(storageContractId, true)
+
+    val integer = IntegerDataEntry("integer", 42)
+    val data    = serializeDataEntryList(List(integer))
+
+    // Function parameters
+    val contractId = StringDataEntry("contractId", Base58.encode(storageContractId))
+    val funcName   = StringDataEntry("funcName", "set_storage")
+    val bytes      = BinaryDataEntry("bytes", ByteStr(data))
+    val params     = serializeDataEntryList(List(contractId, funcName, bytes))
+
+    simulator.callMethod("call_contract_params", params) shouldBe 309
+
+    simulator.getStorage(storageContractId, "integer".getBytes(UTF_8)) shouldBe Array.empty[Byte]
+  }
+
+  "is_paused" in {
+    val simulator = new Simulator(bytecode)
+
+    val storageBytecode   = getClass.getResourceAsStream("/storage.wasm").readAllBytes()
+    val storageContractId = simulator.loadAdditionalBytecode(storageBytecode)
+    simulator.setPaused(storageContractId, true)
+
+    val contractId = StringDataEntry("contractId", Base58.encode(storageContractId))
+    val params     = serializeDataEntryList(List(contractId))
+
+    simulator.callMethod("is_paused", params) shouldBe 0
+
+    val result = BooleanDataEntry("result", true)
+    parseDataEntry(simulator.getStorage("result".getBytes(UTF_8))) shouldBe result
+  }
 }
@@ -100,4 +100,18 @@ class CryptoSpec extends AnyFreeSpec with Matchers {
     val result = BooleanDataEntry("result", true)
     parseDataEntry(simulator.getStorage("result".getBytes(UTF_8))) shouldBe result
   }
+
+  "get_random" in {
+    val simulator = new Simulator(bytecode)
+
+    val seed   = Array[Byte](0, 0, 0, 1)
+    val binary = BinaryDataEntry("seed", ByteStr(seed))
+    val params = serializeDataEntryList(List(binary))
+
+    simulator.callMethod("get_random", params) shouldBe 0
+
+    val random = Blake2b256.hash(simulator.vrf ++ simulator.txId ++ seed)
+    val result = BinaryDataEntry("result", ByteStr(random))
+    parseDataEntry(simulator.getStorage("result".getBytes(UTF_8))) shouldBe result
+  }
 }
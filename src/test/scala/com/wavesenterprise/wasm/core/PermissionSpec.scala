@@ -0,0 +1,39 @@
+package com.wavesenterprise.wasm.core
+
+import com.wavesenterprise.state.{BinaryDataEntry, BooleanDataEntry, ByteStr, IntegerDataEntry}
+import org.scalatest.freespec.AnyFreeSpec
+import org.scalatest.matchers.should.Matchers
+
+import java.nio.charset.StandardCharsets.UTF_8
+
+class PermissionSpec extends AnyFreeSpec with Matchers {
+  val bytecode = getClass.getResourceAsStream("/permission.wasm").readAllBytes()
+
+  "check_permission" in {
+    val simulator = new Simulator(bytecode)
+
+    simulator.addRole(simulator.accounts(0), 1)
+
+    val address = BinaryDataEntry("address", ByteStr(simulator.accounts(0)))
+    val role    = IntegerDataEntry("role", 1)
+    val params  = serializeDataEntryList(List(address, role))
+
+    simulator.callMethod("check_permission", params) shouldBe 0
+
+    val result = BooleanDataEntry("result", true)
+    parseDataEntry(simulator.getStorage("result".getBytes(UTF_8))) shouldBe result
+  }
+
+  "check_permission without role" in {
+    val simulator = new Simulator(bytecode)
+
+    val address = BinaryDataEntry("address", ByteStr(simulator.accounts(1)))
+    val role    = IntegerDataEntry("role", 1)
+    val params  = serializeDataEntryList(List(address, role))
+
+    simulator.callMethod("check_permission", params) shouldBe 0
+
+    val result = BooleanDataEntry("result", false)
+    parseDataEntry(simulator.getStorage("result".getBytes(UTF_8))) shouldBe result
+  }
+}
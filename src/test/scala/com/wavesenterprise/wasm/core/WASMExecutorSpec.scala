@@ -17,6 +17,37 @@ class WASMExecutorSpec extends AnyFreeSpec with Matchers {
     executor.validateBytecode(wrongBytecode) shouldBe 100
   }
 
+  "normalize bytecode" in {
+    val executor = new WASMExecutor
+
+    val normalized = executor.normalizeBytecode(bytecode)
+
+    executor.validateBytecode(normalized) shouldBe 0
+    executor.normalizeBytecode(normalized) shouldBe normalized
+    executor.normalizeBytecode(Array[Byte](0, 14, 21, 1, 2)) shouldBe null
+  }
+
+  "inspect bytecode" in {
+    val executor = new WASMExecutor
+
+    val inspection = executor.inspectBytecode(bytecode, true)
+
+    inspection should include("{\"name\":\"_constructor\",\"kind\":\"func\"}")
+    inspection should include("\"wat\":\"(module")
+    executor.inspectBytecode(Array[Byte](0, 14, 21, 1, 2), false) shouldBe null
+  }
+
+  "manifest" in {
+    val executor = new WASMExecutor
+
+    val manifest = executor.manifest()
+
+    manifest should startWith("[")
+    manifest should include(
+      "{\"module\":\"env1\",\"name\":\"get_random\",\"version\":1,\"params\":[{\"name\":\"offset_seed\",\"type\":\"i32\"},{\"name\":\"length_seed\",\"type\":\"i32\"}],\"results\":[\"i32\",\"i32\",\"i32\"]}"
+    )
+  }
+
   "infinite_loop" in {
     val simulator = new Simulator(bytecode)
 
@@ -13,6 +13,10 @@
     (import "env0" "call_contract" (func $call_contract (param i32 i32 i32 i32) (result i32)))
     (import "env0" "call_contract_params" (func $call_contract_params (param i32 i32 i32 i32 i32 i32) (result i32)))
 
+    (import "env1" "is_paused" (func $is_paused (param i32 i32) (result i32 i32)))
+
+    (import "env0" "set_storage_bool" (func $set_storage_bool (param i32 i32 i32) (result i32)))
+
     (func (export "_constructor") (result i32)
         (i32.const 0)
     )
@@ -139,9 +143,53 @@
         (local.get $error)
     )
 
-    (global $__heap_base (export "__heap_base") i32 (i32.const 6))
+    (func (export "is_paused") (param $p0 i32) (param $p1 i32) (result i32)
+        (local $offset i32) (local $length i32) (local $result i32) (local $error i32)
+
+        (block $code
+            (call $base_58
+                (local.get $p0)
+                (local.get $p1)
+            )
+
+            (local.set $length)
+            (local.set $offset)
+
+            (br_if $code
+                (local.tee $error)
+            )
+
+            (call $is_paused
+                (local.get $offset)
+                (local.get $length)
+            )
+
+            (local.set $result)
+
+            (br_if $code
+                (local.tee $error)
+            )
+
+            (br_if $code
+                (local.tee $error
+                    (call $set_storage_bool
+                        (i32.const 6)
+                        (i32.const 6)
+                        (local.get $result)
+                    )
+                )
+            )
+        )
+
+        (local.get $error)
+    )
+
+    (global $__heap_base (export "__heap_base") i32 (i32.const 12))
 
     ;; Args
     (data (i32.const 0) "\00\01")
     (data (i32.const 2) "test")
+
+    ;; Key
+    (data (i32.const 6) "result")
 )
@@ -11,6 +11,8 @@
     (import "env0" "sha256" (func $sha256 (param i32 i32) (result i32 i32 i32)))
     (import "env0" "sig_verify" (func $sig_verify (param i32 i32 i32 i32 i32 i32) (result i32 i32)))
 
+    (import "env1" "get_random" (func $get_random (param i32 i32) (result i32 i32 i32)))
+
     (func (export "_constructor") (result i32)
         (i32.const 0)
     )
@@ -197,6 +199,36 @@
         (local.get $error)
     )
 
+    (func (export "get_random") (param $p0 i32) (param $p1 i32) (result i32)
+        (local $offset i32) (local $length i32) (local $error i32)
+        (block $code
+            (call $get_random
+                (local.get $p0)
+                (local.get $p1)
+            )
+
+            (local.set $length)
+            (local.set $offset)
+
+            (br_if $code
+                (local.tee $error)
+            )
+
+            (br_if $code
+                (local.tee $error
+                    (call $set_storage_binary
+                        (i32.const 0) ;; Key offset
+                        (i32.const 6) ;; Key length
+                        (local.get $offset)
+                        (local.get $length)
+                    )
+                )
+            )
+        )
+
+        (local.get $error)
+    )
+
     (global $__heap_base (export "__heap_base") i32 (i32.const 6))
 
     ;; Keys
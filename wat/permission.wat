@@ -0,0 +1,45 @@
+(module
+    (import "env" "memory" (memory 2 16))
+
+    (import "env1" "check_permission" (func $check_permission (param i32 i32 i32) (result i32 i32)))
+
+    (import "env0" "set_storage_bool" (func $set_storage_bool (param i32 i32 i32) (result i32)))
+
+    (func (export "_constructor") (result i32)
+        (i32.const 0)
+    )
+
+    (func (export "check_permission") (param $p0 i32) (param $p1 i32) (param $p2 i32) (result i32)
+        (local $result i32) (local $error i32)
+        (block $code
+            (call $check_permission
+                (local.get $p0)
+                (local.get $p1)
+                (local.get $p2)
+            )
+
+            (local.set $result)
+
+            (br_if $code
+                (local.tee $error)
+            )
+
+            (br_if $code
+                (local.tee $error
+                    (call $set_storage_bool
+                        (i32.const 0)
+                        (i32.const 6)
+                        (local.get $result)
+                    )
+                )
+            )
+        )
+
+        (local.get $error)
+    )
+
+    (global $__heap_base (export "__heap_base") i32 (i32.const 6))
+
+    ;; Key
+    (data (i32.const 0) "result")
+)